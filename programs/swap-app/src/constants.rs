@@ -3,3 +3,74 @@ use anchor_lang::prelude::*;
 #[constant]
 pub const SEED: &str = "anchor";
 pub const ANCHOR_DISCRIMINATOR: usize = 8;
+pub const BASIS_POINTS_DIVISOR: u64 = 10_000;
+
+// An offer can hold at most this many mints: Token A plus extra basket mints.
+// Each extra mint costs three more accounts on make, take and cancel, which keeps
+// a full basket well inside the transaction account limit.
+pub const MAX_BASKET_MINTS: usize = 3;
+pub const MAX_BASKET_EXTRA_MINTS: usize = MAX_BASKET_MINTS - 1;
+
+// Shortest lifetime, in seconds, of an offer with an expiry, so makers cannot fill the book with
+// offers that expire before anyone could take them.
+pub const MIN_OFFER_DURATION: i64 = 60;
+
+// Longest label, in bytes, an offer can carry.
+pub const MAX_LABEL_LEN: usize = 32;
+
+// Longest memo, in bytes, a taker can attach to a take.
+pub const MAX_MEMO_LEN: usize = 128;
+
+// Most offers `cancel_offers_batch` closes in one transaction. Each offer costs four accounts
+// and two token CPIs, so five stays comfortably inside the account and compute limits.
+pub const MAX_BATCH_CANCEL: usize = 5;
+
+// Most offers `take_offers_batch` takes in one transaction. Each offer costs five accounts
+// and three token CPIs, so four leaves room for the taker's accounts and the fee transfer.
+pub const MAX_BATCH_TAKE: usize = 4;
+
+// Most offers `make_offers_ladder` posts in one transaction. Each rung creates two accounts and
+// moves its Token A, so five stays inside the account and compute limits.
+pub const MAX_LADDER_RUNGS: usize = 5;
+
+// Most mints the admin can blacklist at once.
+pub const MAX_BLACKLISTED_MINTS: usize = 16;
+
+// Most mints the admin can allowlist at once.
+pub const MAX_ALLOWLISTED_MINTS: usize = 32;
+
+// Most taker wallets the admin can exempt from fees at once.
+pub const MAX_FEE_EXEMPT_TAKERS: usize = 16;
+
+// Slots that must pass between `commit_take` and `reveal_take`, so a reveal cannot land in the
+// same block as the commitment it opens.
+pub const MIN_REVEAL_DELAY_SLOTS: u64 = 2;
+
+// Slots after `commit_take` within which the commitment must be revealed, about as long as a
+// blockhash stays valid. Without a limit a taker could hold a commitment open and reveal it only
+// once the price had moved their way.
+pub const MAX_REVEAL_DELAY_SLOTS: u64 = 150;
+
+// Most offers a maker's index can list at once.
+pub const MAX_MAKER_INDEX_OFFERS: usize = 32;
+
+// Most open offers a pair's index can list at once.
+pub const MAX_PAIR_INDEX_OFFERS: usize = 32;
+
+// Oldest price, in seconds, an oracle-priced offer accepts from its feed.
+pub const MAX_ORACLE_AGE: i64 = 60;
+
+// Token B mint recorded by offers made with `make_offer_for_sol`, which want native SOL in lamports
+// instead of a token. It is the system program's id, which no mint can have, so such offers can only
+// be taken with `take_offer_with_sol`.
+pub const SOL_WANTED_MINT: Pubkey = Pubkey::new_from_array([0; 32]);
+
+// Layout version written to new offers. Offers made before versioning read as version 0,
+// and `migrate_offer` brings older offers up to this version.
+#[constant]
+pub const OFFER_VERSION: u8 = 6;
+
+// First seed of every offer PDA, followed by the creator's key and the id's 8 little-endian bytes;
+// see `derive_offer_address`.
+#[constant]
+pub const OFFER_SEED: &[u8] = b"offer";
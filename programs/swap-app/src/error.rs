@@ -1,7 +1,169 @@
 use anchor_lang::prelude::*;
 
+// Clients match on the codes Anchor assigns in declaration order, so new variants are only ever
+// appended; tests/errors.ts pins every variant's code.
 #[error_code]
 pub enum ErrorCode {
-    #[msg("Custom error message")]
-    CustomError,
+    #[msg("Token A and Token B must be different mints")]
+    SameMintNotAllowed,
+    #[msg("Offered and wanted amounts must be greater than zero")]
+    ZeroAmount,
+    #[msg("The offer has expired")]
+    OfferExpired,
+    #[msg("The requested fill is larger than what remains on the offer")]
+    FillExceedsOffer,
+    #[msg("The requested fill is too small to pay any Token B")]
+    FillTooSmall,
+    #[msg("Fee basis points cannot exceed 10000")]
+    InvalidFeeBps,
+    #[msg("Only the config admin can perform this action")]
+    Unauthorized,
+    #[msg("The program is paused")]
+    ProgramPaused,
+    #[msg("A maker cannot take their own offer")]
+    SelfTradeNotAllowed,
+    #[msg("Mints with the Token-2022 transfer-fee extension are not supported")]
+    TransferFeeMintUnsupported,
+    #[msg("Arithmetic overflow in amount calculation")]
+    MathOverflow,
+    #[msg("This offer can only be taken by its allowed taker")]
+    UnauthorizedTaker,
+    #[msg("The vault holds less Token A than the offer promises")]
+    VaultBalanceMismatch,
+    #[msg("An offer can hold at most 3 mints")]
+    BasketTooLarge,
+    #[msg("The remaining accounts do not match the offer's basket")]
+    BasketAccountsMismatch,
+    #[msg("Each mint can appear in an offer only once")]
+    DuplicateBasketMint,
+    #[msg("Basket offers must be taken in full")]
+    BasketRequiresFullFill,
+    #[msg("The requested fill is below the offer's minimum fill amount")]
+    BelowMinimumFill,
+    #[msg("The taker's Token B account does not hold the offer's wanted mint")]
+    WantedMintMismatch,
+    #[msg("The Token B due for this fill exceeds the taker's limit")]
+    SlippageExceeded,
+    #[msg("A batch can cancel at most 5 offers")]
+    BatchTooLarge,
+    #[msg("The remaining accounts do not describe plain offers of this maker")]
+    BatchAccountsMismatch,
+    #[msg("The vault still holds tokens and cannot be closed")]
+    VaultNotEmpty,
+    #[msg("Token B must be sent to the offer's recipient account")]
+    WantedDestinationMismatch,
+    #[msg("The offer's vault still holds tokens")]
+    OfferNotStale,
+    #[msg("Offer labels can be at most 32 bytes")]
+    LabelTooLong,
+    #[msg("The reduction must leave some Token A on the offer; cancel it to withdraw everything")]
+    InsufficientVaultBalance,
+    #[msg("A token account used by this take is frozen")]
+    FrozenTokenAccount,
+    #[msg("An auction must fall to a non-zero floor and end after it starts")]
+    InvalidAuction,
+    #[msg("Dutch-auction offers are priced by their auction and cannot be repriced")]
+    AuctionOfferCannotBeRepriced,
+    #[msg("The offer's stored bump does not derive its address")]
+    InvalidOfferBump,
+    #[msg("The offer pays a royalty, but the royalty recipient's token account was not passed")]
+    RoyaltyAccountMismatch,
+    #[msg("The taker's Token B account cannot cover the payment")]
+    InsufficientTakerBalance,
+    #[msg("The maker already has the most open offers the config allows")]
+    TooManyOpenOffers,
+    #[msg("An offer cannot be transferred to its current maker")]
+    OwnershipTransferToSelf,
+    #[msg("This mint has been blacklisted and cannot be offered or requested")]
+    MintBlacklisted,
+    #[msg("The blacklist already holds the most mints it can")]
+    BlacklistFull,
+    #[msg("The allowlist is enabled and this mint is not on it")]
+    MintNotAllowed,
+    #[msg("The allowlist already holds the most mints it can")]
+    AllowlistFull,
+    #[msg("Offered mints with a Token-2022 permanent delegate are not supported")]
+    PermanentDelegateNotAllowed,
+    #[msg("The maker's offer index is full; prune closed offers from it first")]
+    MakerIndexFull,
+    #[msg("An offer's expiry must be at least MIN_OFFER_DURATION seconds away")]
+    ExpiryTooSoon,
+    #[msg("The account is not an offer older than the current version")]
+    InvalidOfferVersion,
+    #[msg("The offer account is already larger than the current layout and cannot be shrunk")]
+    OfferShrinkNotAllowed,
+    #[msg("The token program must be the SPL Token or Token-2022 program")]
+    InvalidTokenProgram,
+    #[msg("The oracle's price is too old to trade at")]
+    StaleOracle,
+    #[msg("The offer is priced by an oracle, but its price feed was not passed")]
+    OracleAccountMismatch,
+    #[msg("An oracle price must be non-zero, and an offer cannot use both an oracle and an auction")]
+    InvalidOracle,
+    #[msg("Oracle-priced offers are priced by their feed and cannot be repriced")]
+    OracleOfferCannotBeRepriced,
+    #[msg("The revealed nonce does not match the taker's commitment")]
+    CommitmentMismatch,
+    #[msg("Too few slots have passed since the commitment to reveal it")]
+    RevealTooEarly,
+    #[msg("The taker's Token B account is also the account receiving the maker's Token B")]
+    AccountAliasConflict,
+    #[msg("The rent payer account is not the one that paid for the offer")]
+    RentPayerMismatch,
+    #[msg("An auto-id offer must use the maker's next id")]
+    OfferIdMismatch,
+    #[msg("The treasury holds less than the amount requested")]
+    InsufficientTreasuryBalance,
+    #[msg("The vault does not hold the offer's Token A")]
+    VaultMintMismatch,
+    #[msg("The vault is not the offer's associated token account for Token A")]
+    VaultAccountMismatch,
+    #[msg("The vault holds more Token A than the offer records")]
+    VaultSurplus,
+    #[msg("A new expiry must be later than the offer's current one and at least MIN_OFFER_DURATION seconds away")]
+    InvalidExpiry,
+    #[msg("A take's memo can be at most MAX_MEMO_LEN bytes")]
+    MemoTooLong,
+    #[msg("The governance token account does not hold the config's governance mint")]
+    GovernanceMintMismatch,
+    #[msg("A ladder can post at most 5 offers")]
+    LadderTooLarge,
+    // No longer raised: every take's `associated_token` constraints already tie the vault to the offer.
+    #[msg("The vault is not owned by the offer")]
+    InvalidVaultAuthority,
+    #[msg("The offered amount is outside the config's minimum and maximum offer amounts")]
+    OfferAmountOutOfRange,
+    #[msg("The minimum offer amount cannot exceed the maximum")]
+    InvalidOfferAmountLimits,
+    #[msg("The pair's offer index is full; prune closed offers from it first")]
+    PairIndexFull,
+    // No longer raised: every take's `has_one = maker` already pins the maker to the offer's.
+    #[msg("The maker account is not the offer's maker")]
+    MakerMismatch,
+    #[msg("The offer can only be swept once its expiry plus the config's grace period has passed")]
+    OfferNotSweepable,
+    #[msg("The expiry grace period cannot be negative")]
+    InvalidGracePeriod,
+    #[msg("The offer wants a Token B mint, not SOL")]
+    OfferNotForSol,
+    #[msg("The fee-exempt list can hold at most 16 takers")]
+    FeeExemptListFull,
+    #[msg("The vault did not receive exactly the offered amount of Token A")]
+    DepositMismatch,
+    #[msg("The maker or rent payer does not hold enough SOL to fund the offer")]
+    InsufficientRent,
+    #[msg("Too many slots have passed since the commitment to reveal it")]
+    RevealTooLate,
+    #[msg("The fill would use up the Token B wanted while Token A is still left on the offer")]
+    FillRoundsToZero,
+    #[msg("The vault holds less Token A than the take would withdraw")]
+    VaultUnderfunded,
+    #[msg("This offer can only be taken through reveal_take")]
+    CommitRevealRequired,
+    #[msg("The SOL paid would leave the maker or treasury below the rent-exempt minimum")]
+    RecipientNotRentExempt,
+    #[msg("Only the maker may cancel an offer before it expires")]
+    OfferNotExpired,
+    #[msg("The taker rebate's reserve does not match the amount or accounts passed")]
+    RebateReserveMismatch,
 }
@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+
+/// Emitted when a maker creates an offer and deposits Token A into its vault.
+#[event]
+pub struct OfferMade {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+}
+
+/// Emitted once when a maker posts a ladder of offers, in place of an `OfferMade` per rung.
+/// Each rung's terms are in its offer account, at the address derived from its id.
+#[event]
+pub struct LadderCreated {
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub rung_count: u8,
+    pub ids: Vec<u64>,
+}
+
+/// Emitted when a taker fills an offer.
+#[event]
+pub struct OfferTaken {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    pub token_a_remaining: u64,
+}
+
+/// Emitted when a maker cancels an offer and its vaulted tokens are refunded.
+#[event]
+pub struct OfferCancelled {
+    pub id: u64,
+    pub maker: Pubkey,
+}
+
+/// Emitted when anyone closes an offer after its grace period past expiry, refunding the maker.
+#[event]
+pub struct OfferSwept {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub sweeper: Pubkey,
+}
+
+/// Emitted when a maker changes the amount of Token B an offer wants.
+#[event]
+pub struct OfferUpdated {
+    pub id: u64,
+    pub maker: Pubkey,
+    pub token_b_wanted_amount: u64,
+}
+
+/// Emitted when a maker hands an open offer over to another wallet.
+#[event]
+pub struct OfferOwnershipTransferred {
+    pub id: u64,
+    pub offer: Pubkey,
+    pub previous_maker: Pubkey,
+    pub new_maker: Pubkey,
+}
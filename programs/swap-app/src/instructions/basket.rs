@@ -0,0 +1,178 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address_with_program_id, Create},
+    token_2022::spl_token_2022::extension::{
+        permanent_delegate::PermanentDelegate, // Token-2022 permanent-delegate extension.
+        transfer_fee::TransferFeeConfig, // Token-2022 transfer-fee extension.
+    },
+    token_interface::{
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+use super::{
+    close_vault, mint_has_extension, reject_disallowed_mint, transfer_tokens, withdraw_from_vault,
+    MakeOffer,
+}; // Utility functions for moving tokens in and out of vaults.
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{BasketEntry, Offer, MAX_BASKET_EXTRA_MINTS}; // The offer and its extra basket entries.
+
+// Each extra basket mint is passed through `remaining_accounts` as three accounts, in order:
+// the mint, the offer's vault for that mint, and the user's token account for that mint.
+pub const BASKET_LEG_ACCOUNTS: usize = 3;
+
+// The accounts for one extra basket mint, loaded from `remaining_accounts`.
+pub struct BasketLeg<'info> {
+    pub mint: InterfaceAccount<'info, Mint>, // Mint of the extra token.
+    pub vault: InterfaceAccount<'info, TokenAccount>, // Offer-owned vault holding it.
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>, // Maker's or taker's account for it.
+}
+
+// Function to create a vault for each extra basket mint and fill it from the maker's account.
+// `basket_amounts[i]` is the amount offered of the mint in the i-th group of `basket_accounts`, the
+// remaining accounts left once any rebate reserve is split off.
+pub fn open_basket_vaults<'info>(
+    context: &Context<'_, '_, 'info, 'info, MakeOffer<'info>>, // Context containing all the accounts involved.
+    basket_accounts: &'info [AccountInfo<'info>], // Basket accounts passed after the named ones.
+    basket_amounts: &[u64], // Amount offered of each extra mint.
+) -> Result<Vec<BasketEntry>> {
+    require!(basket_amounts.len() <= MAX_BASKET_EXTRA_MINTS, ErrorCode::BasketTooLarge);
+    require!(
+        basket_accounts.len() == basket_amounts.len() * BASKET_LEG_ACCOUNTS,
+        ErrorCode::BasketAccountsMismatch
+    );
+
+    let accounts = &context.accounts;
+    let token_program_id = accounts.token_program.key();
+    let offer_key = accounts.offer.key();
+    let mut basket: Vec<BasketEntry> = Vec::with_capacity(basket_amounts.len());
+
+    for (leg, &amount) in basket_accounts.chunks(BASKET_LEG_ACCOUNTS).zip(basket_amounts) {
+        let (mint_info, vault_info, maker_account_info) = (&leg[0], &leg[1], &leg[2]);
+        require!(amount > 0, ErrorCode::ZeroAmount);
+
+        // Extra mints share the instruction's token program and get the same checks as Token A.
+        require_keys_eq!(*mint_info.owner, token_program_id, ErrorCode::BasketAccountsMismatch);
+        let mint = InterfaceAccount::<Mint>::try_from(mint_info)?;
+        require!(
+            mint.key() != accounts.token_mint_a.key()
+                && mint.key() != accounts.token_mint_b.key()
+                && basket.iter().all(|entry| entry.mint != mint.key()),
+            ErrorCode::DuplicateBasketMint
+        );
+        reject_disallowed_mint(&accounts.config, &accounts.blacklist, &accounts.allowlist, &mint.key())?;
+        require!(
+            !mint_has_extension::<TransferFeeConfig>(&mint)?,
+            ErrorCode::TransferFeeMintUnsupported
+        );
+        require!(
+            !mint_has_extension::<PermanentDelegate>(&mint)?,
+            ErrorCode::PermanentDelegateNotAllowed
+        );
+
+        // The vault must be the offer's associated token account for the mint.
+        require_keys_eq!(
+            vault_info.key(),
+            get_associated_token_address_with_program_id(&offer_key, &mint.key(), &token_program_id),
+            ErrorCode::BasketAccountsMismatch
+        );
+        associated_token::create(CpiContext::new(
+            accounts.associated_token_program.to_account_info(),
+            Create {
+                payer: accounts.rent_payer.to_account_info(), // Rent payer covers the vault's rent.
+                associated_token: vault_info.clone(), // Vault being created.
+                authority: accounts.offer.to_account_info(), // Offer PDA owns the vault.
+                mint: mint_info.clone(), // Mint of the extra token.
+                system_program: accounts.system_program.to_account_info(),
+                token_program: accounts.token_program.to_account_info(),
+            },
+        ))?;
+        let vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+        let maker_token_account = InterfaceAccount::<TokenAccount>::try_from(maker_account_info)?;
+
+        transfer_tokens(
+            &maker_token_account, // Source account: Maker's token account.
+            &vault, // Destination account: the extra vault.
+            &amount, // Amount to transfer.
+            &mint, // Mint of the extra token.
+            &accounts.maker, // Authority over the source account.
+            &accounts.token_program, // Token program handling the transfer.
+        )?;
+
+        basket.push(BasketEntry { mint: mint.key(), amount });
+    }
+
+    Ok(basket)
+}
+
+// Function to load and validate the extra basket accounts of an existing offer.
+// Every entry in the offer's basket must be matched, in order, by its mint and vault.
+pub fn load_basket_legs<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>], // Accounts passed after the named ones.
+    offer: &Account<'info, Offer>, // Offer owning the vaults.
+    token_program: &Interface<'info, TokenInterface>, // Token program governing the vaults.
+) -> Result<Vec<BasketLeg<'info>>> {
+    require!(
+        remaining_accounts.len() == offer.basket.len() * BASKET_LEG_ACCOUNTS,
+        ErrorCode::BasketAccountsMismatch
+    );
+
+    offer
+        .basket
+        .iter()
+        .zip(remaining_accounts.chunks(BASKET_LEG_ACCOUNTS))
+        .map(|(entry, leg)| {
+            require_keys_eq!(leg[0].key(), entry.mint, ErrorCode::BasketAccountsMismatch);
+            require_keys_eq!(
+                leg[1].key(),
+                get_associated_token_address_with_program_id(&offer.key(), &entry.mint, &token_program.key()),
+                ErrorCode::BasketAccountsMismatch
+            );
+            Ok(BasketLeg {
+                mint: InterfaceAccount::try_from(&leg[0])?,
+                vault: InterfaceAccount::try_from(&leg[1])?,
+                user_token_account: InterfaceAccount::try_from(&leg[2])?,
+            })
+        })
+        .collect()
+}
+
+// Function to check every extra vault still holds what the offer's basket promises.
+pub fn check_basket_balances(legs: &[BasketLeg], offer: &Offer) -> Result<()> {
+    for (leg, entry) in legs.iter().zip(&offer.basket) {
+        require!(leg.vault.amount >= entry.amount, ErrorCode::VaultBalanceMismatch);
+    }
+    Ok(())
+}
+
+// Function to empty every extra vault into the user's accounts and close the vaults.
+pub fn release_basket<'info>(
+    legs: &[BasketLeg<'info>], // Loaded basket accounts.
+    offer: &Account<'info, Offer>, // Offer PDA owning the vaults.
+    rent_destination: AccountInfo<'info>, // Recipient of the vaults' rent.
+    token_program: &Interface<'info, TokenInterface>, // Token program.
+    signer_seeds: &[&[&[u8]]], // Seeds of the offer PDA.
+) -> Result<()> {
+    for leg in legs {
+        withdraw_from_vault(
+            &leg.vault, // Source vault.
+            &leg.user_token_account, // Destination account.
+            leg.vault.amount, // Everything held in the vault.
+            &leg.mint, // Mint of the extra token.
+            offer.to_account_info(), // Authority (offer PDA).
+            token_program, // Token program.
+            signer_seeds,
+        )?;
+        close_vault(
+            &leg.vault, // Vault to close.
+            rent_destination.clone(), // Recipient of the rent.
+            offer.to_account_info(), // Authority (offer PDA).
+            token_program, // Token program.
+            signer_seeds,
+        )?;
+    }
+    Ok(())
+}
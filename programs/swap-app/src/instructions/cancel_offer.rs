@@ -0,0 +1,308 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, // Function to close a token account.
+        CloseAccount, // Struct for closing a token account.
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+use super::{
+    close_vault, is_native_mint, load_basket_legs, load_rebate_reserve, mul_div, release_basket,
+    split_rebate_reserve, unwrap_sol, withdraw_from_vault,
+}; // Utility functions for moving tokens out of the vault.
+use crate::error::ErrorCode; // Custom program errors.
+// Importing the state accounts and the event emitted on cancel.
+use crate::{Config, MakerStats, Offer, OfferCancelled, PairIndex, BASIS_POINTS_DIVISOR, OFFER_SEED};
+
+#[derive(Accounts)]
+pub struct CancelOffer<'info> {
+    // Whoever cancels the offer: its maker at any time, or the account that paid its rent once it
+    // has expired, such as a sponsor reclaiming their rent from a maker who went inactive.
+    pub canceller: Signer<'info>,
+
+    /// CHECK: The maker of the offer, receiving the refund, pinned by the offer's `has_one = maker`.
+    /// Signs as the `canceller` unless the rent payer cancels.
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    // Token mint for the offered token (A).
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // The maker's token account for the offered token (A), receiving the refund.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    // The global config holding the maker rebate settings.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // The treasury PDA that owns the protocol's fee accounts.
+    #[account(seeds = [b"treasury"], bump)]
+    pub treasury: SystemAccount<'info>,
+
+    // The treasury's token account for the offered token (A), paying any maker rebate.
+    // Optional: without it the cancel simply pays no rebate.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program
+    )]
+    pub treasury_token_account_a: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The maker's open-offer counter, decremented when the offer closes.
+    #[account(
+        mut,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // The offer account being cancelled. Closed at the end of the instruction.
+    #[account(
+        mut,
+        close = rent_payer,
+        has_one = maker,
+        has_one = token_mint_a,
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // The pair's offer index, if one has been created. The offer is removed from it.
+    #[account(
+        mut,
+        seeds = [b"pair", offer.token_mint_a.as_ref(), offer.token_mint_b.as_ref()],
+        bump = pair_index.bump
+    )]
+    pub pair_index: Option<Box<Account<'info, PairIndex>>>,
+
+    /// CHECK: The account that paid the offer's rent, receiving it back.
+    /// Pinned to the offer's recorded rent payer. It may be owned by any program, such as a maker's
+    /// PDA that paid its own rent, since it only ever receives lamports.
+    #[account(mut, address = offer.rent_recipient() @ ErrorCode::RentPayerMismatch)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    // The vault holding the tokens offered by the maker.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Required Solana programs for system operations.
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> CancelOffer<'info> {
+    // Borrows the accounts `refund_and_close` works on, letting `cancel_offer` share its logic
+    // with the other instructions that close an offer and refund its maker.
+    pub fn as_refund<'a>(&'a mut self, remaining_accounts: &'info [AccountInfo<'info>]) -> Refund<'a, 'info> {
+        Refund {
+            token_mint_a: &self.token_mint_a,
+            vault: Some((&self.vault, &self.maker_token_account_a)),
+            maker_stats: &mut self.maker_stats,
+            offer: &self.offer,
+            pair_index: self.pair_index.as_deref_mut(),
+            rent_payer: self.rent_payer.to_account_info(),
+            token_program: &self.token_program,
+            remaining_accounts,
+        }
+    }
+}
+
+// The accounts closing an offer works on, already validated by the instruction's account constraints.
+pub struct Refund<'a, 'info> {
+    pub token_mint_a: &'a InterfaceAccount<'info, Mint>,
+    // The Token A vault and the maker's Token A account it is emptied into. `None` for a stale
+    // offer, whose vault may already be gone and is closed by `close_if_stale` itself.
+    pub vault: Option<(&'a InterfaceAccount<'info, TokenAccount>, &'a InterfaceAccount<'info, TokenAccount>)>,
+    pub maker_stats: &'a mut Account<'info, MakerStats>,
+    pub offer: &'a Account<'info, Offer>,
+    pub pair_index: Option<&'a mut Account<'info, PairIndex>>,
+    pub rent_payer: AccountInfo<'info>,
+    pub token_program: &'a Interface<'info, TokenInterface>,
+    pub remaining_accounts: &'info [AccountInfo<'info>],
+}
+
+// Function to check the canceller may cancel the offer at `now`: its maker at any time, or the account
+// its rent goes back to once it has expired. Only the maker can sign for their Token A account, so
+// only they may ask for it to be closed.
+pub fn check_canceller(accounts: &CancelOffer, close_token_account_a: bool, now: i64) -> Result<()> {
+    let canceller = accounts.canceller.key();
+    let offer = &accounts.offer;
+    if canceller == offer.maker {
+        return Ok(());
+    }
+    require_keys_eq!(canceller, offer.rent_recipient(), ErrorCode::Unauthorized);
+    require!(offer.is_expired(now), ErrorCode::OfferNotExpired);
+    require!(!close_token_account_a, ErrorCode::Unauthorized);
+    Ok(())
+}
+
+// Function to compute the rebate earned by an offer cancelled at `now`, in Token A.
+// Offers earn `rebate_bps` of their unfilled Token A once they have been open for at least
+// `min_rebate_duration` seconds; a zero rebate rate disables rebates entirely. Topping an offer up
+// restarts its `created_at`, so the whole of its unfilled Token A has been open that long.
+pub fn maker_rebate(offer: &Offer, config: &Config, now: i64) -> Result<u64> {
+    let open_for = now.saturating_sub(offer.created_at);
+    if config.rebate_bps == 0 || open_for < config.min_rebate_duration {
+        return Ok(0);
+    }
+    mul_div(offer.token_a_remaining, config.rebate_bps as u64, BASIS_POINTS_DIVISOR)
+}
+
+// Function to pay the maker's rebate from the treasury, capped at what the treasury holds
+// so that an underfunded treasury never blocks a cancellation.
+pub fn pay_maker_rebate(ctx: &Context<CancelOffer>) -> Result<()> {
+    let Some(treasury_token_account_a) = ctx.accounts.treasury_token_account_a.as_ref() else {
+        return Ok(());
+    };
+    let now = Clock::get()?.unix_timestamp;
+    let rebate = maker_rebate(&ctx.accounts.offer, &ctx.accounts.config, now)?
+        .min(treasury_token_account_a.amount);
+    if rebate == 0 {
+        return Ok(());
+    }
+
+    // Seeds for signing as the treasury PDA.
+    let seeds = &[b"treasury".as_ref(), &[ctx.bumps.treasury]];
+    let signer_seeds = [&seeds[..]];
+
+    withdraw_from_vault(
+        treasury_token_account_a, // Source account (treasury's token A).
+        &ctx.accounts.maker_token_account_a, // Destination account (maker's token A).
+        rebate, // Rebate amount.
+        &ctx.accounts.token_mint_a, // Mint for token A.
+        ctx.accounts.treasury.to_account_info(), // Authority (treasury PDA).
+        &ctx.accounts.token_program, // Token program.
+        &signer_seeds,
+    )
+}
+
+// Function to close the maker's Token A account once the refund is in, returning its rent to them.
+// Only reached when the maker is the canceller, as `check_canceller` ensures.
+// Wrapped SOL is unwrapped, so a maker who offered plain SOL from an account opened for the offer
+// gets plain SOL back. Any other account is only closed if it is empty, and otherwise kept.
+pub fn close_maker_token_account_a(accounts: &mut CancelOffer) -> Result<()> {
+    if is_native_mint(&accounts.token_mint_a.key()) {
+        return unwrap_sol(
+            &accounts.maker_token_account_a, // Maker's wrapped SOL account.
+            &accounts.canceller, // Maker, cancelling, receives the lamports.
+            &accounts.token_program, // Token program.
+        );
+    }
+
+    // The loaded balance predates the refund.
+    accounts.maker_token_account_a.reload()?;
+    if accounts.maker_token_account_a.amount > 0 {
+        return Ok(());
+    }
+    close_account(CpiContext::new(
+        accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: accounts.maker_token_account_a.to_account_info(), // Empty account to close.
+            destination: accounts.maker.to_account_info(), // Maker receives the rent.
+            authority: accounts.canceller.to_account_info(), // Maker, cancelling, owns the account.
+        },
+    ))
+}
+
+// Function to return everything an offer holds to its maker and close its vaults, returning their
+// rent to whoever paid for it, then take the offer off the maker's stats and the pair's index.
+// Shared by every instruction that closes an offer without a take; the offer account itself is
+// closed by the instruction's `close` constraint. Basket and rebate reserve refunds are checked to go
+// to the maker's own accounts, since not every caller is the maker.
+pub fn refund_and_close(refund: Refund) -> Result<()> {
+    // Seeds for generating the vault's PDA, which stay those of the offer's creator.
+    let offer = refund.offer;
+    let creator = offer.creator;
+    let id_bytes = offer.id.to_le_bytes();
+    let seeds = &[OFFER_SEED, creator.as_ref(), &id_bytes[..], &[offer.bump]];
+    let signer_seeds = [&seeds[..]];
+
+    if let Some((vault, maker_token_account_a)) = refund.vault {
+        // Returning everything left in the vault to the maker.
+        withdraw_from_vault(
+            vault, // Source vault.
+            maker_token_account_a, // Destination account (maker's token A).
+            vault.amount, // Amount to transfer.
+            refund.token_mint_a, // Mint for token A.
+            offer.to_account_info(), // Authority (offer PDA).
+            refund.token_program, // Token program.
+            &signer_seeds,
+        )?;
+
+        // Closing the vault, returning its rent to whoever paid for it.
+        close_vault(
+            vault, // Vault to close.
+            refund.rent_payer.clone(), // Recipient of the rent.
+            offer.to_account_info(), // Authority (offer PDA).
+            refund.token_program, // Token program.
+            &signer_seeds,
+        )?;
+    }
+
+    // Refunding the extra basket tokens and any rebate reserve to the maker's own accounts and
+    // closing their vaults.
+    let (basket_accounts, reserve_accounts) =
+        split_rebate_reserve(refund.remaining_accounts, offer.rebate_reserve > 0)?;
+    let legs = load_basket_legs(basket_accounts, offer, refund.token_program)?;
+    for leg in &legs {
+        require_keys_eq!(leg.user_token_account.owner, offer.maker, ErrorCode::BasketAccountsMismatch);
+    }
+    release_basket(
+        &legs, // Extra basket accounts.
+        offer, // Authority (offer PDA).
+        refund.rent_payer.clone(), // Recipient of the vaults' rent.
+        refund.token_program, // Token program.
+        &signer_seeds,
+    )?;
+    release_basket(
+        &load_rebate_reserve(reserve_accounts, offer, refund.token_program)?, // The rebate reserve, if any.
+        offer, // Authority (offer PDA).
+        refund.rent_payer, // Recipient of the vault's rent.
+        refund.token_program, // Token program.
+        &signer_seeds,
+    )?;
+
+    refund.maker_stats.record_closed();
+    if let Some(pair_index) = refund.pair_index {
+        pair_index.remove(&offer.key());
+    }
+    Ok(())
+}
+
+// Function to return the vaulted tokens (A, any basket tokens and any rebate reserve) to the maker and
+// close the vaults, then close the maker's Token A account too if `close_token_account_a` is set.
+pub fn refund_and_close_vault<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CancelOffer<'info>>,
+    close_token_account_a: bool,
+) -> Result<()> {
+    refund_and_close(ctx.accounts.as_refund(ctx.remaining_accounts))?;
+    if close_token_account_a {
+        close_maker_token_account_a(ctx.accounts)?;
+    }
+
+    // Announce the cancellation to indexers.
+    emit!(OfferCancelled {
+        id: ctx.accounts.offer.id,
+        maker: ctx.accounts.offer.maker,
+    });
+    Ok(())
+}
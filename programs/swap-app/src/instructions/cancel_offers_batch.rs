@@ -0,0 +1,128 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+// Utility functions for moving tokens out of the vault.
+use super::{close_vault, withdraw_from_vault};
+use crate::error::ErrorCode; // Custom program errors.
+// Importing the `Offer` struct and the event emitted on cancel.
+use crate::{MakerStats, Offer, OfferCancelled, MAX_BATCH_CANCEL, OFFER_SEED};
+
+// Each offer in the batch is passed through `remaining_accounts` as four accounts, in order:
+// the offer, its Token A mint, its vault, and the maker's Token A account.
+pub const BATCH_CANCEL_ACCOUNTS: usize = 4;
+
+#[derive(Accounts)]
+pub struct CancelOffersBatch<'info> {
+    // The maker (creator) of every offer in the batch. They receive all refunds and rent.
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    // The maker's open-offer counter, decremented for every offer closed.
+    #[account(
+        mut,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Token program governing every vault in the batch.
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Function to refund and close every offer passed in `remaining_accounts`.
+pub fn refund_and_close_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, CancelOffersBatch<'info>>,
+) -> Result<()> {
+    let remaining_accounts = ctx.remaining_accounts;
+    require!(
+        !remaining_accounts.is_empty() && remaining_accounts.len().is_multiple_of(BATCH_CANCEL_ACCOUNTS),
+        ErrorCode::BatchAccountsMismatch
+    );
+    require!(
+        remaining_accounts.len() / BATCH_CANCEL_ACCOUNTS <= MAX_BATCH_CANCEL,
+        ErrorCode::BatchTooLarge
+    );
+
+    for group in remaining_accounts.chunks(BATCH_CANCEL_ACCOUNTS) {
+        refund_and_close_offer(ctx.accounts, group)?;
+        ctx.accounts.maker_stats.record_closed();
+    }
+    Ok(())
+}
+
+// Function to refund and close a single offer of the batch, applying the same checks as
+// the `CancelOffer` account constraints.
+fn refund_and_close_offer<'info>(
+    accounts: &CancelOffersBatch<'info>, // Named accounts of the batch.
+    group: &'info [AccountInfo<'info>], // The offer's four remaining accounts.
+) -> Result<()> {
+    let maker_key = accounts.maker.key();
+    let token_program_id = accounts.token_program.key();
+
+    // The offer must be this maker's, at its PDA, hold nothing outside its Token A vault,
+    // and owe its rent to the maker alone.
+    let offer = Account::<Offer>::try_from(&group[0])?;
+    require_keys_eq!(offer.maker, maker_key, ErrorCode::BatchAccountsMismatch);
+    require_keys_eq!(offer.rent_recipient(), maker_key, ErrorCode::BatchAccountsMismatch);
+    require!(offer.basket.is_empty(), ErrorCode::BatchAccountsMismatch);
+    require!(offer.rebate_reserve == 0, ErrorCode::BatchAccountsMismatch);
+    let id_bytes = offer.id.to_le_bytes();
+    let seeds = &[OFFER_SEED, offer.creator.as_ref(), &id_bytes[..], &[offer.bump]];
+    let offer_address = Pubkey::create_program_address(seeds, &crate::ID)
+        .map_err(|_| ErrorCode::BatchAccountsMismatch)?;
+    require_keys_eq!(offer_address, offer.key(), ErrorCode::BatchAccountsMismatch);
+    let signer_seeds = [&seeds[..]];
+
+    // The mint, vault, and refund account must match the offer.
+    require_keys_eq!(group[1].key(), offer.token_mint_a, ErrorCode::BatchAccountsMismatch);
+    require_keys_eq!(
+        group[2].key(),
+        get_associated_token_address_with_program_id(&offer.key(), &offer.token_mint_a, &token_program_id),
+        ErrorCode::BatchAccountsMismatch
+    );
+    require_keys_eq!(
+        group[3].key(),
+        get_associated_token_address_with_program_id(&maker_key, &offer.token_mint_a, &token_program_id),
+        ErrorCode::BatchAccountsMismatch
+    );
+    let token_mint_a = InterfaceAccount::<Mint>::try_from(&group[1])?;
+    let vault = InterfaceAccount::<TokenAccount>::try_from(&group[2])?;
+    let maker_token_account_a = InterfaceAccount::<TokenAccount>::try_from(&group[3])?;
+
+    // Returning everything left in the vault to the maker.
+    withdraw_from_vault(
+        &vault, // Source vault.
+        &maker_token_account_a, // Destination account (maker's token A).
+        vault.amount, // Amount to transfer.
+        &token_mint_a, // Mint for token A.
+        offer.to_account_info(), // Authority (offer PDA).
+        &accounts.token_program, // Token program.
+        &signer_seeds,
+    )?;
+
+    // Closing the vault, returning its rent to the maker who paid for it.
+    close_vault(
+        &vault, // Vault to close.
+        accounts.maker.to_account_info(), // Recipient of the rent.
+        offer.to_account_info(), // Authority (offer PDA).
+        &accounts.token_program, // Token program.
+        &signer_seeds,
+    )?;
+
+    // Announce the cancellation to indexers.
+    emit!(OfferCancelled {
+        id: offer.id,
+        maker: offer.maker,
+    });
+
+    // Closing the offer, returning its rent to the maker.
+    offer.close(accounts.maker.to_account_info())
+}
@@ -0,0 +1,101 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{
+        Mint, // Represents the token mint (currency).
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+use super::{
+    associated_token_account_space, check_funding, is_native_mint, rent_due, rungs_rent_due,
+    LADDER_RUNG_ACCOUNTS,
+}; // Funding checks shared with the instructions creating offers.
+use crate::error::ErrorCode; // Custom program errors.
+// The accounts an offer creates, and the ladder's cap.
+use crate::{derive_offer_address, MakerStats, ANCHOR_DISCRIMINATOR, MAX_LADDER_RUNGS};
+
+// Each offer about to be created is passed through `remaining_accounts` as two accounts, in order:
+// the offer PDA for its id and the offer's vault for Token A, as for a rung of `make_offers_ladder`.
+#[derive(Accounts)]
+pub struct CheckOfferFunding<'info> {
+    // The maker about to create the offers, paying for their own accounts and any wrapped SOL.
+    /// CHECK: Only its lamports are read.
+    pub maker: UncheckedAccount<'info>,
+
+    // The wallet about to pay the rent of the offers and their vaults. The maker when they pay it.
+    /// CHECK: Only its lamports are read.
+    pub rent_payer: UncheckedAccount<'info>,
+
+    // Token mint for the offered token (A), sizing the vaults.
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // The maker's token account for Token A, which `make_offer` creates if it is missing.
+    /// CHECK: Pinned to the maker's associated token account for Token A by its seeds; only its
+    /// lamports and whether it exists are read.
+    #[account(
+        seeds = [maker.key().as_ref(), token_program.key().as_ref(), token_mint_a.key().as_ref()],
+        seeds::program = associated_token_program.key(),
+        bump
+    )]
+    pub maker_token_account_a: UncheckedAccount<'info>,
+
+    // The maker's open-offer counter, created on their first offer.
+    /// CHECK: Pinned to the maker's stats PDA by its seeds; only its lamports and whether it exists are read.
+    #[account(seeds = [b"maker", maker.key().as_ref()], bump)]
+    pub maker_stats: UncheckedAccount<'info>,
+
+    // Token program the vaults will belong to.
+    pub token_program: Interface<'info, TokenInterface>,
+
+    // Associated token program deriving the token accounts' addresses.
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+// Function to check the maker and the rent payer hold the SOL for the offers with `ids`, failing with
+// `InsufficientRent` naming the amount. The maker pays for their Token A account and stats if they are
+// missing, and wraps `token_a_offered_amount` when Token A is native. The rent payer pays for each
+// offer and vault, net of any lamports already at their addresses, and for `basket_legs` extra vaults
+// taken to be the size of the Token A vault. Nothing is written.
+pub fn check_funding_for_offers(
+    ctx: Context<CheckOfferFunding>,
+    ids: Vec<u64>, // Id of each offer about to be created.
+    token_a_offered_amount: u64, // Token A the offers deposit in total.
+    basket_legs: u8, // Extra basket vaults the offer creates.
+) -> Result<()> {
+    require!(
+        !ids.is_empty() && ctx.remaining_accounts.len() == ids.len() * LADDER_RUNG_ACCOUNTS,
+        ErrorCode::BatchAccountsMismatch
+    );
+    require!(ids.len() <= MAX_LADDER_RUNGS, ErrorCode::LadderTooLarge);
+
+    // Each offer and vault must be the ones its id derives, so their rent is priced correctly.
+    let accounts = &ctx.accounts;
+    let token_program_id = accounts.token_program.key();
+    for (group, id) in ctx.remaining_accounts.chunks(LADDER_RUNG_ACCOUNTS).zip(ids) {
+        let (offer_address, _) = derive_offer_address(&accounts.maker.key(), id);
+        require_keys_eq!(group[0].key(), offer_address, ErrorCode::BatchAccountsMismatch);
+        require_keys_eq!(
+            group[1].key(),
+            get_associated_token_address_with_program_id(&offer_address, &accounts.token_mint_a.key(), &token_program_id),
+            ErrorCode::BatchAccountsMismatch
+        );
+    }
+
+    let token_account_space = associated_token_account_space(&accounts.token_mint_a, &accounts.token_program)?;
+    let deposit = if is_native_mint(&accounts.token_mint_a.key()) { token_a_offered_amount } else { 0 };
+    let maker_rent = rent_due(&accounts.maker_token_account_a, token_account_space)?
+        .checked_add(rent_due(&accounts.maker_stats, ANCHOR_DISCRIMINATOR + MakerStats::INIT_SPACE)?)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let maker_required = deposit.checked_add(maker_rent).ok_or(ErrorCode::MathOverflow)?;
+    let basket_rent = Rent::get()?
+        .minimum_balance(token_account_space)
+        .checked_mul(basket_legs as u64)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let rent_payer_required = rungs_rent_due(ctx.remaining_accounts, token_account_space)?
+        .checked_add(basket_rent)
+        .ok_or(ErrorCode::MathOverflow)?;
+    check_funding(&accounts.maker, &accounts.rent_payer, maker_required, rent_payer_required)
+}
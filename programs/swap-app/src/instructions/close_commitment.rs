@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+use crate::TakeCommitment; // The commitment account being closed.
+
+#[derive(Accounts)]
+pub struct CloseCommitment<'info> {
+    // The taker who made the commitment, receiving its rent.
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    // The taker's commitment, closed whether or not it can still be revealed. The offer is not
+    // needed, so a commitment outlives the offer it was made for and can still be closed.
+    #[account(
+        mut,
+        close = taker,
+        seeds = [b"commit", commitment.offer.as_ref(), taker.key().as_ref()],
+        bump = commitment.bump
+    )]
+    pub commitment: Account<'info, TakeCommitment>,
+}
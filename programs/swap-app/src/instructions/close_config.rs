@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{Allowlist, Blacklist, Config, FeeExempt}; // Global settings accounts.
+
+#[derive(Accounts)]
+pub struct CloseConfig<'info> {
+    // The admin recorded in the config, receiving the rent of every closed account.
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    // The global config PDA being closed.
+    #[account(
+        mut,
+        close = admin,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // The mint blacklist, closed with the config so that it can be initialized again.
+    #[account(mut, close = admin, seeds = [b"blacklist"], bump = blacklist.bump)]
+    pub blacklist: Account<'info, Blacklist>,
+
+    // The mint allowlist, closed with the config so that it can be initialized again.
+    #[account(mut, close = admin, seeds = [b"allowlist"], bump = allowlist.bump)]
+    pub allowlist: Account<'info, Allowlist>,
+
+    // The fee-exempt takers, closed with the config so that they can be initialized again.
+    #[account(mut, close = admin, seeds = [b"fee_exempt"], bump = fee_exempt.bump)]
+    pub fee_exempt: Account<'info, FeeExempt>,
+}
@@ -0,0 +1,133 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{
+        close_account, // Function to close a token account.
+        CloseAccount, // Struct for closing a token account.
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+use super::{refund_and_close, Refund}; // The refund shared with `cancel_offer`.
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{MakerStats, Offer, PairIndex, OFFER_SEED}; // Importing the state accounts.
+
+#[derive(Accounts)]
+pub struct CloseStaleOffer<'info> {
+    // The maker (creator) of the offer.
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    // Token mint for the offered token (A). Its owner fixes the token program, and with it
+    // the vault address, so the real vault cannot be swapped for an empty lookalike.
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // The maker's open-offer counter, decremented when the offer closes.
+    #[account(
+        mut,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // The offer account being cleaned up. Closed at the end of the instruction.
+    #[account(
+        mut,
+        close = rent_payer,
+        has_one = maker,
+        has_one = token_mint_a,
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // The pair's offer index, if one has been created. The offer is removed from it.
+    #[account(
+        mut,
+        seeds = [b"pair", offer.token_mint_a.as_ref(), offer.token_mint_b.as_ref()],
+        bump = pair_index.bump
+    )]
+    pub pair_index: Option<Box<Account<'info, PairIndex>>>,
+
+    /// CHECK: The account that paid the offer's rent, receiving it back.
+    /// Pinned to the offer's recorded rent payer. It may be owned by any program, such as a maker's
+    /// PDA that paid its own rent, since it only ever receives lamports.
+    #[account(mut, address = offer.rent_recipient() @ ErrorCode::RentPayerMismatch)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    /// CHECK: Pinned to the offer's vault address; it may no longer exist, so its contents
+    /// are inspected in `close_if_stale`.
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(
+            &offer.key(),
+            &token_mint_a.key(),
+            &token_program.key()
+        )
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    // Token program governing the vault.
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> CloseStaleOffer<'info> {
+    // Borrows the accounts `refund_and_close` works on. The vault is left out, since it may no
+    // longer exist, and so are basket and rebate reserve accounts, since such offers are never stale.
+    pub fn as_refund(&mut self) -> Refund<'_, 'info> {
+        Refund {
+            token_mint_a: &self.token_mint_a,
+            vault: None,
+            maker_stats: &mut self.maker_stats,
+            offer: &self.offer,
+            pair_index: self.pair_index.as_deref_mut(),
+            rent_payer: self.rent_payer.to_account_info(),
+            token_program: &self.token_program,
+            remaining_accounts: &[],
+        }
+    }
+}
+
+// Function to close an offer whose vault is gone or empty, so nothing can be bypassed by closing it.
+// An empty vault that still exists is closed as well, with its rent going to the rent payer.
+pub fn close_if_stale(ctx: Context<CloseStaleOffer>) -> Result<()> {
+    // Basket and rebate reserve vaults are not passed here, so offers holding either are never
+    // treated as stale.
+    require!(ctx.accounts.offer.basket.is_empty(), ErrorCode::OfferNotStale);
+    require!(ctx.accounts.offer.rebate_reserve == 0, ErrorCode::OfferNotStale);
+
+    let vault = ctx.accounts.vault.to_account_info();
+    if vault.data_is_empty() {
+        return refund_and_close(ctx.accounts.as_refund());
+    }
+
+    // The vault still exists, so it must hold nothing.
+    let vault_amount = TokenAccount::try_deserialize(&mut &vault.try_borrow_data()?[..])?.amount;
+    require!(vault_amount == 0, ErrorCode::OfferNotStale);
+
+    // Seeds for generating the vault's PDA, which stay those of the offer's creator.
+    let creator = ctx.accounts.offer.creator;
+    let seeds = &[
+        OFFER_SEED,
+        creator.as_ref(),
+        &ctx.accounts.offer.id.to_le_bytes()[..],
+        &[ctx.accounts.offer.bump],
+    ];
+    let signer_seeds = [&seeds[..]];
+
+    // Closing the empty vault, returning its rent to whoever paid for it.
+    close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: vault, // Vault to close.
+            destination: ctx.accounts.rent_payer.to_account_info(), // Recipient of the rent.
+            authority: ctx.accounts.offer.to_account_info(), // Authority (offer PDA).
+        },
+        &signer_seeds,
+    ))?;
+    refund_and_close(ctx.accounts.as_refund())
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+// The offer being committed to and the commitment account.
+use crate::{Offer, TakeCommitment, ANCHOR_DISCRIMINATOR, OFFER_SEED};
+
+#[derive(Accounts)]
+pub struct CommitTake<'info> {
+    // The taker committing to the offer, paying for the commitment.
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    // The offer the taker intends to take.
+    #[account(
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // The taker's commitment for this offer. A new commitment replaces an unrevealed one.
+    #[account(
+        init_if_needed,
+        payer = taker,
+        space = ANCHOR_DISCRIMINATOR + TakeCommitment::INIT_SPACE,
+        seeds = [b"commit", offer.key().as_ref(), taker.key().as_ref()],
+        bump
+    )]
+    pub commitment: Account<'info, TakeCommitment>,
+
+    // Required system program for account creation.
+    pub system_program: Program<'info, System>,
+}
+
+// Function to record the taker's hash and the slot it was committed in.
+pub fn save_commitment(ctx: Context<CommitTake>, hash: [u8; 32]) -> Result<()> {
+    ctx.accounts.commitment.set_inner(TakeCommitment {
+        taker: ctx.accounts.taker.key(), // Taker who may reveal it.
+        offer: ctx.accounts.offer.key(), // Offer it applies to.
+        hash, // Hash of the taker and their nonce.
+        slot: Clock::get()?.slot, // Slot the reveal must wait out.
+        bump: ctx.bumps.commitment, // Bump for the TakeCommitment PDA.
+    });
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::{MakerIndex, ANCHOR_DISCRIMINATOR}; // The maker's offer index and sizing constants.
+
+#[derive(Accounts)]
+pub struct CreateMakerIndex<'info> {
+    // The maker owning the index, paying for it.
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    // The maker's offer index PDA, created empty.
+    #[account(
+        init,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + MakerIndex::INIT_SPACE,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+
+    // Required system program for account creation.
+    pub system_program: Program<'info, System>,
+}
+
+// Function to save the empty index.
+pub fn save_maker_index(ctx: Context<CreateMakerIndex>) -> Result<()> {
+    ctx.accounts.maker_index.set_inner(MakerIndex {
+        maker: ctx.accounts.maker.key(), // Owner of the index.
+        bump: ctx.bumps.maker_index, // Bump for the MakerIndex PDA.
+        offers: Vec::new(), // No offers indexed yet.
+    });
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint; // Represents the token mint (currency).
+
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{PairIndex, ANCHOR_DISCRIMINATOR}; // The pair's offer index and sizing constants.
+
+#[derive(Accounts)]
+pub struct CreatePairIndex<'info> {
+    // Anyone may create a pair's index, paying for it.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    // Token mint offered (A) by the offers the index lists.
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // Token mint wanted (B) by the offers the index lists. Must differ from Token A.
+    #[account(constraint = token_mint_b.key() != token_mint_a.key() @ ErrorCode::SameMintNotAllowed)]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    // The pair's offer index PDA, created empty.
+    #[account(
+        init,
+        payer = payer,
+        space = ANCHOR_DISCRIMINATOR + PairIndex::INIT_SPACE,
+        seeds = [b"pair", token_mint_a.key().as_ref(), token_mint_b.key().as_ref()],
+        bump
+    )]
+    pub pair_index: Account<'info, PairIndex>,
+
+    // Required system program for account creation.
+    pub system_program: Program<'info, System>,
+}
+
+// Function to save the empty index.
+pub fn save_pair_index(ctx: Context<CreatePairIndex>) -> Result<()> {
+    ctx.accounts.pair_index.set_inner(PairIndex {
+        token_mint_a: ctx.accounts.token_mint_a.key(), // Offered mint of the pair.
+        token_mint_b: ctx.accounts.token_mint_b.key(), // Wanted mint of the pair.
+        bump: ctx.bumps.pair_index, // Bump for the PairIndex PDA.
+        offers: Vec::new(), // No offers indexed yet.
+    });
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{PriceFeed, ANCHOR_DISCRIMINATOR}; // The price feed account and sizing constants.
+
+#[derive(Accounts)]
+pub struct CreatePriceFeed<'info> {
+    // The signer creating the feed. They become its authority and pay for the account.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    // The new price feed account, at a fresh keypair address.
+    #[account(
+        init,
+        payer = authority,
+        space = ANCHOR_DISCRIMINATOR + PriceFeed::INIT_SPACE
+    )]
+    pub price_feed: Account<'info, PriceFeed>,
+
+    // Required system program for account creation.
+    pub system_program: Program<'info, System>,
+}
+
+// Function to save the feed with its first price, published now.
+pub fn save_price_feed(ctx: Context<CreatePriceFeed>, price: u64, expo: i32) -> Result<()> {
+    // A zero price would let takers have oracle-priced offers for free.
+    require!(price > 0, ErrorCode::InvalidOracle);
+
+    ctx.accounts.price_feed.set_inner(PriceFeed {
+        authority: ctx.accounts.authority.key(), // Signer becomes the authority.
+        price, // Value of one whole Token A in whole Token B, before `expo`.
+        expo, // Power of ten applied to `price`.
+        publish_time: Clock::get()?.unix_timestamp, // Published now.
+    });
+    Ok(())
+}
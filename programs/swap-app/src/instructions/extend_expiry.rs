@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+// Importing the `Offer` struct and the shortest allowed lifetime.
+use crate::{Offer, MIN_OFFER_DURATION, OFFER_SEED};
+
+#[derive(Accounts)]
+pub struct ExtendExpiry<'info> {
+    // The maker (creator) of the offer. Only the maker can keep it open for longer.
+    pub maker: Signer<'info>,
+
+    // The offer account whose expiry is being pushed back.
+    #[account(
+        mut,
+        has_one = maker,
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+}
+
+// Function to move the offer's expiry to `expiry`, which must be later than the current one and
+// at least `MIN_OFFER_DURATION` seconds away. Offers that never expire have nothing to extend.
+pub fn save_expiry(ctx: Context<ExtendExpiry>, expiry: i64) -> Result<()> {
+    let offer = &mut ctx.accounts.offer;
+    require!(offer.expiry != 0 && expiry > offer.expiry, ErrorCode::InvalidExpiry);
+    require!(
+        expiry >= Clock::get()?.unix_timestamp + MIN_OFFER_DURATION,
+        ErrorCode::InvalidExpiry
+    );
+    offer.expiry = expiry;
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use anchor_lang::prelude::*;
+
+use crate::{Offer, OFFER_SEED}; // Importing the `Offer` struct, which represents the offer details.
+
+#[derive(Accounts)]
+pub struct GetOffer<'info> {
+    // The offer account being read. The seeds check rejects accounts that are not real offers.
+    #[account(
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+}
+
+// Function to hand back a copy of the offer's terms. Nothing is written.
+pub fn read_offer(ctx: Context<GetOffer>) -> Result<Offer> {
+    Ok((*ctx.accounts.offer).clone())
+}
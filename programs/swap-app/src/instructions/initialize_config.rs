@@ -0,0 +1,92 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+// Global settings accounts and sizing constants.
+use crate::{Allowlist, Blacklist, Config, FeeExempt, ANCHOR_DISCRIMINATOR, BASIS_POINTS_DIVISOR};
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    // The signer creating the config. They become its admin and pay for the account.
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    // The global config PDA. `init` guarantees this can only ever succeed once.
+    #[account(
+        init,
+        payer = admin,
+        space = ANCHOR_DISCRIMINATOR + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // The mint blacklist PDA, created empty alongside the config.
+    #[account(
+        init,
+        payer = admin,
+        space = ANCHOR_DISCRIMINATOR + Blacklist::INIT_SPACE,
+        seeds = [b"blacklist"],
+        bump
+    )]
+    pub blacklist: Account<'info, Blacklist>,
+
+    // The mint allowlist PDA, created empty and disabled alongside the config.
+    #[account(
+        init,
+        payer = admin,
+        space = ANCHOR_DISCRIMINATOR + Allowlist::INIT_SPACE,
+        seeds = [b"allowlist"],
+        bump
+    )]
+    pub allowlist: Account<'info, Allowlist>,
+
+    // The fee-exempt takers PDA, created empty alongside the config.
+    #[account(
+        init,
+        payer = admin,
+        space = ANCHOR_DISCRIMINATOR + FeeExempt::INIT_SPACE,
+        seeds = [b"fee_exempt"],
+        bump
+    )]
+    pub fee_exempt: Account<'info, FeeExempt>,
+
+    // Required system program for account creation.
+    pub system_program: Program<'info, System>,
+}
+
+// Function to save the initial global settings, making the signer the admin.
+pub fn save_config(ctx: Context<InitializeConfig>, fee_bps: u16) -> Result<()> {
+    // A fee above 100% would take more than the taker pays.
+    require!(fee_bps as u64 <= BASIS_POINTS_DIVISOR, ErrorCode::InvalidFeeBps);
+
+    ctx.accounts.config.set_inner(Config {
+        admin: ctx.accounts.admin.key(), // Signer becomes the admin.
+        fee_bps, // Protocol fee charged on Token B.
+        bump: ctx.bumps.config, // Bump for the Config PDA.
+        paused: false, // The program starts unpaused.
+        rebate_bps: 0, // Maker rebates start disabled.
+        min_rebate_duration: 0, // No rebate duration until rebates are enabled.
+        flat_fee_lamports: 0, // The flat SOL fee starts disabled.
+        max_open_offers: 0, // Makers start without an open-offer cap.
+        allowlist_enabled: false, // Any mint may be offered until the allowlist is enabled.
+        governance_mint: Pubkey::default(), // No governance discount until one is configured.
+        governance_threshold: 0, // No governance balance required.
+        discounted_fee_bps: 0, // Unused without a governance mint.
+        min_offer_amount: 0, // Offers start without a minimum size.
+        max_offer_amount: 0, // Offers start without a maximum size.
+        expiry_grace_period: 0, // Expired offers can be swept straight away.
+    });
+    ctx.accounts.blacklist.set_inner(Blacklist {
+        bump: ctx.bumps.blacklist, // Bump for the Blacklist PDA.
+        mints: Vec::new(), // No mints start blocked.
+    });
+    ctx.accounts.allowlist.set_inner(Allowlist {
+        bump: ctx.bumps.allowlist, // Bump for the Allowlist PDA.
+        mints: Vec::new(), // No mints start permitted.
+    });
+    ctx.accounts.fee_exempt.set_inner(FeeExempt {
+        bump: ctx.bumps.fee_exempt, // Bump for the FeeExempt PDA.
+        takers: Vec::new(), // Every taker starts paying fees.
+    });
+    Ok(())
+}
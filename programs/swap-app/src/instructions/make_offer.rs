@@ -6,9 +6,19 @@ use anchor_spl::{
 };
 
 // Importing custom modules and constants.
-use crate::{Offer, ANCHOR_DISCRIMINATOR}; // `Offer` is a custom struct, and `ANCHOR_DISCRIMINATOR` ensures unique account identification.
+use crate::{
+    Allowlist, BasketEntry, Blacklist, Config, DutchAuction, MakerIndex, MakerStats, Offer,
+    OfferMade, PairIndex, ANCHOR_DISCRIMINATOR, OFFER_SEED, OFFER_VERSION,
+}; // `Offer` is a custom struct, and `ANCHOR_DISCRIMINATOR` ensures unique account identification.
+use crate::error::ErrorCode; // Custom program errors.
 
-use super::transfer_tokens; // Function to handle token transfers between accounts.
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    permanent_delegate::PermanentDelegate, // Token-2022 permanent-delegate extension.
+    transfer_fee::TransferFeeConfig, // Token-2022 transfer-fee extension.
+};
+
+// Functions to inspect mints and handle token transfers between accounts.
+use super::{is_native_mint, mint_has_extension, transfer_tokens, wrap_sol};
 
 /// Context structure for the `MakeOffer` instruction. This defines the accounts involved.
 #[derive(Accounts)]
@@ -17,29 +27,78 @@ pub struct MakeOffer<'info> {
     #[account(mut)] // Signer account, mutable because the transaction might alter its state.
     pub maker: Signer<'info>,
 
+    // Pays the rent of the offer and its vaults, and gets it back when they close.
+    // Makers paying their own rent pass themselves; a platform may sponsor it instead.
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
     // Token Mint A account (immutable) associated with the token program.
     #[account(mint::token_program = token_program)]
     pub token_mint_a: InterfaceAccount<'info, Mint>,
 
     // Token Mint B account (immutable) associated with the token program.
-    #[account(mint::token_program = token_program)]
+    // Swapping a mint for itself is meaningless, so it must differ from Token Mint A.
+    #[account(
+        mint::token_program = token_program,
+        constraint = token_mint_b.key() != token_mint_a.key() @ ErrorCode::SameMintNotAllowed
+    )]
     pub token_mint_b: InterfaceAccount<'info, Mint>,
 
     // Maker's token account for Token A, associated with the `maker` authority.
+    // Created if missing so a maker holding only SOL can offer wrapped SOL (and receive refunds).
     #[account(
-        mut, // Mutable because tokens will be deducted from this account.
+        init_if_needed, // Created if missing; mutable because tokens will be deducted from this account.
+        payer = maker, // Maker pays for the initialization cost if the account is new.
         associated_token::mint = token_mint_a, // The mint for this token account is Token A.
         associated_token::authority = maker, // Authority over this account is the maker.
         associated_token::token_program = token_program // Program that governs this account.
     )]
-    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+    pub maker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Global config, checked for the emergency pause and the offer amount limits.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // Mints the admin has blocked, checked against every mint of the offer.
+    #[account(seeds = [b"blacklist"], bump = blacklist.bump)]
+    pub blacklist: Account<'info, Blacklist>,
+
+    // Mints the admin permits, checked against every mint of the offer while the allowlist is enabled.
+    #[account(seeds = [b"allowlist"], bump = allowlist.bump)]
+    pub allowlist: Box<Account<'info, Allowlist>>,
+
+    // The maker's open-offer counter, created on their first offer.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + MakerStats::INIT_SPACE,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // The maker's offer index, if they keep one. The new offer is appended to it.
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Option<Box<Account<'info, MakerIndex>>>,
+
+    // The pair's offer index, if one has been created. The new offer is appended to it.
+    #[account(
+        mut,
+        seeds = [b"pair", token_mint_a.key().as_ref(), token_mint_b.key().as_ref()],
+        bump = pair_index.bump
+    )]
+    pub pair_index: Option<Box<Account<'info, PairIndex>>>,
 
     // Offer account, initialized during the transaction.
     #[account(
         init, // Creates a new account.
-        payer = maker, // Maker pays for the initialization cost.
+        payer = rent_payer, // Rent payer covers the initialization cost.
         space = ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE, // Allocating space for the Offer struct.
-        seeds = [b"offer", maker.key().as_ref(), id.to_le_bytes().as_ref()], // PDA seeds for uniqueness.
+        seeds = [OFFER_SEED, maker.key().as_ref(), id.to_le_bytes().as_ref()], // PDA seeds for uniqueness.
         bump // Automatically calculates the bump for the PDA.
     )]
     pub offer: Account<'info, Offer>,
@@ -47,7 +106,7 @@ pub struct MakeOffer<'info> {
     // Vault account to hold the tokens being offered, associated with the Offer account.
     #[account(
         init, // Creates a new account.
-        payer = maker, // Maker pays for the initialization cost.
+        payer = rent_payer, // Rent payer covers the initialization cost.
         associated_token::mint = token_mint_a, // The mint for this token account is Token A.
         associated_token::authority = offer, // Authority over this account is the offer account.
         associated_token::token_program = token_program // Program that governs this account.
@@ -64,12 +123,173 @@ pub struct MakeOffer<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
+/// Rejects mints the escrow cannot hold safely.
+/// A transfer fee would leave the vault (or the maker) with fewer tokens than the offer records.
+/// Token-2022 extensions can only be set when a mint is created, so checking here covers `take_offer` too.
+/// A permanent delegate could move the offered tokens out of the vault behind the taker's back,
+/// so Token A may not have one; Token B never sits in escrow, so it may.
+/// Mints on the admin's blacklist are refused as well, as are mints missing from the allowlist while it is enabled.
+pub fn reject_unsupported_mints(context: &Context<MakeOffer>) -> Result<()> {
+    let accounts = &context.accounts;
+    reject_unsupported_pair(
+        &accounts.token_mint_a,
+        &accounts.token_mint_b,
+        &accounts.config,
+        &accounts.blacklist,
+        &accounts.allowlist,
+    )
+}
+
+/// Applies the checks of `reject_unsupported_mints` to a pair of mints.
+/// Shared with `make_offers_ladder`, whose offers must pass the same checks.
+pub fn reject_unsupported_pair<'info>(
+    token_mint_a: &InterfaceAccount<'info, Mint>,
+    token_mint_b: &InterfaceAccount<'info, Mint>,
+    config: &Config,
+    blacklist: &Blacklist,
+    allowlist: &Allowlist,
+) -> Result<()> {
+    reject_unsupported_offered_mint(token_mint_a, config, blacklist, allowlist)?;
+    reject_disallowed_mint(config, blacklist, allowlist, &token_mint_b.key())?;
+    require!(
+        !mint_has_extension::<TransferFeeConfig>(token_mint_b)?,
+        ErrorCode::TransferFeeMintUnsupported
+    );
+    Ok(())
+}
+
+/// Applies the checks of `reject_unsupported_mints` to Token A alone.
+/// Shared with `make_offer_for_sol`, whose offers have no Token B mint.
+pub fn reject_unsupported_offered_mint<'info>(
+    token_mint_a: &InterfaceAccount<'info, Mint>,
+    config: &Config,
+    blacklist: &Blacklist,
+    allowlist: &Allowlist,
+) -> Result<()> {
+    require!(
+        !mint_has_extension::<PermanentDelegate>(token_mint_a)?,
+        ErrorCode::PermanentDelegateNotAllowed
+    );
+    reject_disallowed_mint(config, blacklist, allowlist, &token_mint_a.key())?;
+    require!(
+        !mint_has_extension::<TransferFeeConfig>(token_mint_a)?,
+        ErrorCode::TransferFeeMintUnsupported
+    );
+    Ok(())
+}
+
+/// Refuses a mint that is blacklisted, or missing from the allowlist while it is enabled.
+/// Shared with the extra basket mints so every mint of an offer gets the same checks.
+pub fn reject_disallowed_mint(
+    config: &Config,
+    blacklist: &Blacklist,
+    allowlist: &Allowlist,
+    mint: &Pubkey,
+) -> Result<()> {
+    require!(!blacklist.contains(mint), ErrorCode::MintBlacklisted);
+    require!(
+        !config.allowlist_enabled || allowlist.contains(mint),
+        ErrorCode::MintNotAllowed
+    );
+    Ok(())
+}
+
+/// Checks the maker and the rent payer hold the SOL the rest of `make_offer` spends, so a short
+/// wallet fails with `InsufficientRent` naming the amount instead of deep inside the system program.
+/// That is the wrapped SOL deposit when Token A is native, paid by the maker, and the rent of the
+/// `basket_legs` extra vaults (the basket's and any rebate reserve's), paid by the rent payer and
+/// taken to be the size of the Token A vault.
+/// The offer, its vault and the maker's accounts are created by Anchor before the handler runs, so
+/// their rent is checked by the `check_offer_funding` instruction placed ahead of `make_offer`.
+pub fn check_deposit_funding(context: &Context<MakeOffer>, token_a_offered_amount: u64, basket_legs: usize) -> Result<()> {
+    let accounts = &context.accounts;
+    let deposit = if is_native_mint(&accounts.token_mint_a.key()) { token_a_offered_amount } else { 0 };
+    let vault_rent = Rent::get()?.minimum_balance(accounts.vault.to_account_info().data_len());
+    let basket_rent = vault_rent.checked_mul(basket_legs as u64).ok_or(ErrorCode::MathOverflow)?;
+    check_funding(&accounts.maker, &accounts.rent_payer, deposit, basket_rent)
+}
+
+/// Fails with `InsufficientRent` unless the maker holds `maker_required` lamports and the rent payer
+/// `rent_payer_required`. A maker paying their own rent funds both from the same wallet.
+pub fn check_funding(
+    maker: &AccountInfo,
+    rent_payer: &AccountInfo,
+    maker_required: u64,
+    rent_payer_required: u64,
+) -> Result<()> {
+    if maker.key() == rent_payer.key() {
+        return check_payer_funds(
+            maker,
+            maker_required.checked_add(rent_payer_required).ok_or(ErrorCode::MathOverflow)?,
+        );
+    }
+    check_payer_funds(maker, maker_required)?;
+    check_payer_funds(rent_payer, rent_payer_required)
+}
+
+/// Fails with `InsufficientRent`, logging the shortfall, unless `payer` holds `required` lamports.
+pub fn check_payer_funds(payer: &AccountInfo, required: u64) -> Result<()> {
+    if payer.lamports() < required {
+        msg!(
+            "{} holds {} lamports but the offer needs {} from it",
+            payer.key(),
+            payer.lamports(),
+            required
+        );
+        return err!(ErrorCode::InsufficientRent);
+    }
+    Ok(())
+}
+
+/// Returns the lamports still needed to create `account` with `space` bytes of data, net of any
+/// already sent to its address, or nothing if it already exists.
+pub fn rent_due(account: &AccountInfo, space: usize) -> Result<u64> {
+    if !account.data_is_empty() {
+        return Ok(0);
+    }
+    Ok(Rent::get()?.minimum_balance(space).saturating_sub(account.lamports()))
+}
+
+/// Counts the new offer against the maker's open offers, refusing it beyond the config's cap.
+pub fn count_open_offer(context: &mut Context<MakeOffer>) -> Result<()> {
+    let max_open_offers = context.accounts.config.max_open_offers;
+    let maker_stats = &mut context.accounts.maker_stats;
+    maker_stats.maker = context.accounts.maker.key();
+    maker_stats.bump = context.bumps.maker_stats;
+    maker_stats.record_created();
+    maker_stats.record_opened(max_open_offers)
+}
+
+/// Appends the new offer to the maker's index and the pair's index, for each one passed.
+pub fn index_offer(context: &mut Context<MakeOffer>) -> Result<()> {
+    let offer = context.accounts.offer.key();
+    if let Some(maker_index) = context.accounts.maker_index.as_mut() {
+        maker_index.push(offer)?;
+    }
+    match context.accounts.pair_index.as_mut() {
+        Some(pair_index) => pair_index.push(offer),
+        None => Ok(()),
+    }
+}
+
 /// Transfers the offered tokens from the maker's account to the vault.
 /// `token_a_offered_amount` specifies the amount of tokens to transfer.
+/// When Token A is wrapped SOL, the maker's SOL is wrapped straight into the vault instead,
+/// so makers can offer SOL without holding wrapped SOL first.
 pub fn send_offered_tokens_to_vault(
     context: &Context<MakeOffer>, // Context containing all the accounts involved.
     token_a_offered_amount: u64, // Amount of Token A to transfer.
 ) -> Result<()> {
+    if is_native_mint(&context.accounts.token_mint_a.key()) {
+        return wrap_sol(
+            &context.accounts.maker, // Maker pays the SOL.
+            &context.accounts.vault, // Vault holds it as wrapped SOL.
+            token_a_offered_amount, // Lamports to wrap.
+            &context.accounts.system_program, // System program moving the lamports.
+            &context.accounts.token_program, // Token program syncing the vault.
+        );
+    }
+
     transfer_tokens(
         &context.accounts.maker_token_account_a, // Source account: Maker's token account.
         &context.accounts.vault, // Destination account: Vault.
@@ -80,13 +300,57 @@ pub fn send_offered_tokens_to_vault(
     )
 }
 
+/// Reloads the vault after the deposit and checks it received exactly `token_a_offered_amount`,
+/// so an offer never records Token A its vault does not hold. Transfer-fee mints, which would
+/// deliver less, are already refused; this catches any other shortfall.
+pub fn check_vault_deposit(context: &mut Context<MakeOffer>, token_a_offered_amount: u64) -> Result<()> {
+    check_deposit(&mut context.accounts.vault, token_a_offered_amount)
+}
+
+/// Reloads `vault` after a deposit and fails with `DepositMismatch` unless it holds exactly
+/// `expected_amount`. Shared with the ladder's rungs and with top-ups.
+pub fn check_deposit(vault: &mut InterfaceAccount<TokenAccount>, expected_amount: u64) -> Result<()> {
+    vault.reload()?;
+    require!(vault.amount == expected_amount, ErrorCode::DepositMismatch);
+    Ok(())
+}
+
 /// Saves the offer details into the `Offer` account.
 /// `id` is the unique identifier for the offer.
+/// `token_a_offered_amount` specifies the amount of Token A deposited into the vault.
 /// `token_b_wanted_amount` specifies the amount of Token B the maker wants in exchange.
+/// `expiry` is the unix timestamp after which the offer can no longer be taken (0 = never).
+/// `allowed_taker` restricts the offer to a single counterparty when set.
+/// `basket` lists the extra tokens vaulted alongside Token A, if any.
+/// `min_fill_amount` is the smallest partial fill of Token A the maker accepts (0 = any).
+/// `token_b_destination` is the token account receiving Token B instead of the maker's ATA, if any.
+/// `label` is a short human-readable description shown by UIs.
+/// `auction` makes the offer a Dutch auction priced by its terms instead of `token_b_wanted_amount`.
+/// `royalty_recipient` is paid `royalty_bps` of every Token B payment (0 = no royalty).
+/// `oracle` prices the offer from a price feed plus `spread_bps` instead (default pubkey = none).
+/// `taker_rebate_bps` is the share of each taker's protocol fee paid back to them (0 = none).
+/// `commit_only` restricts takes to `reveal_take`.
+/// `rebate_reserve` is the Token B escrowed to pay the rebates, already in its vault.
+#[allow(clippy::too_many_arguments)]
 pub fn save_offer(
     context: Context<MakeOffer>, // Context containing all the accounts involved.
     id: u64, // Unique identifier for the offer.
+    token_a_offered_amount: u64, // Amount of Token A offered.
     token_b_wanted_amount: u64, // Desired amount of Token B.
+    expiry: i64, // Expiry timestamp, or 0 for no expiry.
+    allowed_taker: Option<Pubkey>, // Only taker allowed to fill the offer, if any.
+    basket: Vec<BasketEntry>, // Extra tokens offered alongside Token A.
+    min_fill_amount: u64, // Smallest partial fill accepted, or 0 for any.
+    token_b_destination: Option<Pubkey>, // Custom Token B recipient, if any.
+    label: String, // Human-readable description.
+    auction: Option<DutchAuction>, // Dutch-auction terms, if any.
+    royalty_recipient: Pubkey, // Wallet paid the royalty.
+    royalty_bps: u16, // Royalty on each Token B payment, in basis points.
+    oracle: Pubkey, // Price feed pricing the offer, if any.
+    spread_bps: u16, // Premium over the oracle price, in basis points.
+    taker_rebate_bps: u16, // Share of the taker's fee paid back to them, in basis points.
+    commit_only: bool, // Whether only `reveal_take` may take the offer.
+    rebate_reserve: u64, // Token B escrowed to pay the rebates.
 ) -> Result<()> {
     // Populate the `Offer` account with the provided details.
     context.accounts.offer.set_inner(Offer {
@@ -96,6 +360,39 @@ pub fn save_offer(
         token_mint_b: context.accounts.token_mint_b.key(), // Public key of Token B mint.
         token_b_wanted_amount, // Amount of Token B wanted.
         bump: context.bumps.offer, // Bump for the Offer PDA.
+        token_a_offered_amount, // Amount of Token A offered.
+        expiry, // Expiry timestamp.
+        allowed_taker, // Private counterparty, if any.
+        basket, // Extra tokens offered alongside Token A.
+        min_fill_amount, // Smallest partial fill accepted.
+        token_a_remaining: token_a_offered_amount, // Nothing has been filled yet.
+        created_at: Clock::get()?.unix_timestamp, // Creation time, for sorting offers by age.
+        token_b_destination, // Custom Token B recipient, if any.
+        label, // Human-readable description.
+        auction, // Dutch-auction terms, if any.
+        creator: context.accounts.maker.key(), // Original maker, fixing the offer's address.
+        royalty_recipient, // Wallet paid the royalty.
+        royalty_bps, // Royalty on each Token B payment.
+        token_a_decimals: context.accounts.token_mint_a.decimals, // Decimals of Token A.
+        token_b_decimals: context.accounts.token_mint_b.decimals, // Decimals of Token B.
+        version: OFFER_VERSION, // Current account layout.
+        oracle, // Price feed pricing the offer, if any.
+        spread_bps, // Premium over the oracle price.
+        rent_payer: context.accounts.rent_payer.key(), // Account the rent goes back to.
+        taker_rebate_bps, // Share of the taker's fee paid back to them.
+        commit_only, // Whether only `reveal_take` may take the offer.
+        rebate_reserve, // Token B escrowed to pay the rebates.
+    });
+
+    // Announce the new offer to indexers.
+    let offer = &context.accounts.offer;
+    emit!(OfferMade {
+        id: offer.id,
+        maker: offer.maker,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_offered_amount: offer.token_a_offered_amount,
+        token_b_wanted_amount: offer.token_b_wanted_amount,
     });
     Ok(()) // Indicate success.
 }
@@ -0,0 +1,181 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::spl_token::native_mint, // Wrapped SOL, whose decimals lamports share.
+    token_interface::{
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+// Mint checks shared with `make_offer`, and token transfers.
+use super::{is_native_mint, reject_unsupported_offered_mint, transfer_tokens};
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{
+    Allowlist, Blacklist, Config, MakerStats, Offer, OfferMade, ANCHOR_DISCRIMINATOR,
+    MIN_OFFER_DURATION, OFFER_SEED, OFFER_VERSION, SOL_WANTED_MINT,
+}; // State accounts, the event emitted for the new offer, and the offer's layout constants.
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct MakeOfferForSol<'info> {
+    // The maker posting the offer. Pays the rent of the offer and its vault, and funds the vault.
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    // Token mint for the offered token (A). Wrapped SOL is refused, since it would be SOL for SOL.
+    #[account(
+        mint::token_program = token_program,
+        constraint = !is_native_mint(&token_mint_a.key()) @ ErrorCode::SameMintNotAllowed
+    )]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // The maker's token account for Token A, funding the vault.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Global config, checked for the emergency pause, the open-offer cap and the offer amount limits.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // Mints the admin has blocked, checked against Token A.
+    #[account(seeds = [b"blacklist"], bump = blacklist.bump)]
+    pub blacklist: Account<'info, Blacklist>,
+
+    // Mints the admin permits, checked against Token A while the allowlist is enabled.
+    #[account(seeds = [b"allowlist"], bump = allowlist.bump)]
+    pub allowlist: Box<Account<'info, Allowlist>>,
+
+    // The maker's open-offer counter, created on their first offer.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + MakerStats::INIT_SPACE,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // Offer account, initialized at the same PDA a `make_offer` with this id would use.
+    #[account(
+        init,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE,
+        seeds = [OFFER_SEED, maker.key().as_ref(), id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // Vault holding the offered Token A, owned by the offer account.
+    #[account(
+        init,
+        payer = maker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Required programs for creating the offer and its vault.
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+// Function to post an offer of Token A for `lamports_wanted` of native SOL, vaulting the Token A
+// and saving the offer with `SOL_WANTED_MINT` as its Token B mint. It gets the checks of
+// `make_offer` that apply to Token A, with every optional feature left off.
+pub fn open_sol_offer(
+    ctx: Context<MakeOfferForSol>,
+    id: u64, // Unique identifier for the offer.
+    token_a_offered_amount: u64, // Amount of Token A offered.
+    lamports_wanted: u64, // Lamports of SOL wanted for all of it.
+    expiry: i64, // Expiry timestamp, or 0 for no expiry.
+) -> Result<()> {
+    // The checks `make_offer` applies, for an offer with no Token B mint.
+    let accounts = &ctx.accounts;
+    require!(!accounts.config.paused, ErrorCode::ProgramPaused);
+    require!(token_a_offered_amount > 0, ErrorCode::ZeroAmount);
+    require!(lamports_wanted > 0, ErrorCode::ZeroAmount);
+    require!(
+        accounts.config.accepts_offer_amount(token_a_offered_amount),
+        ErrorCode::OfferAmountOutOfRange
+    );
+    let now = Clock::get()?.unix_timestamp;
+    require!(expiry == 0 || expiry >= now + MIN_OFFER_DURATION, ErrorCode::ExpiryTooSoon);
+    reject_unsupported_offered_mint(
+        &accounts.token_mint_a,
+        &accounts.config,
+        &accounts.blacklist,
+        &accounts.allowlist,
+    )?;
+
+    let max_open_offers = ctx.accounts.config.max_open_offers;
+    let maker_stats = &mut ctx.accounts.maker_stats;
+    maker_stats.maker = ctx.accounts.maker.key();
+    maker_stats.bump = ctx.bumps.maker_stats;
+    maker_stats.record_created();
+    maker_stats.record_opened(max_open_offers)?;
+
+    // Moving the offered Token A into the vault.
+    transfer_tokens(
+        &ctx.accounts.maker_token_account_a, // Source account: Maker's token account.
+        &ctx.accounts.vault, // Destination account: Vault.
+        &token_a_offered_amount, // Amount to transfer.
+        &ctx.accounts.token_mint_a, // Mint associated with Token A.
+        &ctx.accounts.maker, // Authority over the source account.
+        &ctx.accounts.token_program, // Token program handling the transfer.
+    )?;
+
+    // Saving the offer's terms, with every optional feature left off.
+    let maker_key = ctx.accounts.maker.key();
+    ctx.accounts.offer.set_inner(Offer {
+        id, // Offer ID.
+        maker: maker_key, // Maker's public key.
+        token_mint_a: ctx.accounts.token_mint_a.key(), // Public key of Token A mint.
+        token_mint_b: SOL_WANTED_MINT, // Native SOL rather than a Token B mint.
+        token_b_wanted_amount: lamports_wanted, // Lamports wanted.
+        bump: ctx.bumps.offer, // Bump for the Offer PDA.
+        token_a_offered_amount, // Amount of Token A offered.
+        expiry, // Expiry timestamp.
+        allowed_taker: None, // Open to anyone.
+        basket: Vec::new(), // No extra tokens.
+        min_fill_amount: 0, // Any partial fill is accepted.
+        token_a_remaining: token_a_offered_amount, // Nothing has been filled yet.
+        created_at: now, // Creation time, for sorting offers by age.
+        token_b_destination: None, // SOL goes to the maker's wallet.
+        label: String::new(), // No description.
+        auction: None, // Fixed price.
+        creator: maker_key, // Original maker, fixing the offer's address.
+        royalty_recipient: Pubkey::default(), // No royalty.
+        royalty_bps: 0, // No royalty.
+        token_a_decimals: ctx.accounts.token_mint_a.decimals, // Decimals of Token A.
+        token_b_decimals: native_mint::DECIMALS, // Lamports have the decimals of SOL.
+        version: OFFER_VERSION, // Current account layout.
+        oracle: Pubkey::default(), // No price feed.
+        spread_bps: 0, // No oracle spread.
+        rent_payer: maker_key, // Maker paid the rent.
+        taker_rebate_bps: 0, // No taker rebate.
+        commit_only: false, // Open to direct takes.
+        rebate_reserve: 0, // No rebate reserve.
+    });
+
+    // Announce the new offer to indexers.
+    let offer = &ctx.accounts.offer;
+    emit!(OfferMade {
+        id,
+        maker: maker_key,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_offered_amount,
+        token_b_wanted_amount: lamports_wanted,
+    });
+    Ok(())
+}
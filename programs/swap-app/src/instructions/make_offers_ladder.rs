@@ -0,0 +1,308 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address_with_program_id, AssociatedToken, Create},
+    token_interface::{
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+use super::{
+    associated_token_account_space, check_deposit, check_funding, create_pda_account,
+    is_native_mint, reject_unsupported_pair, rent_due, transfer_tokens, wrap_sol,
+}; // Mint, funding and deposit checks shared with `make_offer`, account creation, and token transfers.
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{
+    derive_offer_address, Allowlist, Blacklist, Config, LadderCreated, MakerIndex, MakerStats,
+    Offer, PairIndex, ANCHOR_DISCRIMINATOR, MAX_LADDER_RUNGS, MIN_OFFER_DURATION, OFFER_SEED,
+    OFFER_VERSION,
+}; // State accounts, the event summarizing the ladder, and the offer's layout constants.
+
+// Each rung of the ladder is passed through `remaining_accounts` as two accounts, in order:
+// the offer PDA for the rung's id and the offer's vault for Token A. Both are created here.
+pub const LADDER_RUNG_ACCOUNTS: usize = 2;
+
+#[derive(Accounts)]
+pub struct MakeOffersLadder<'info> {
+    // The maker posting the ladder. Pays the rent of every offer and vault, and funds every vault.
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    // Token mint for the offered token (A), shared by every rung.
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // Token mint for the wanted token (B), shared by every rung. Must differ from Token A.
+    #[account(
+        mint::token_program = token_program,
+        constraint = token_mint_b.key() != token_mint_a.key() @ ErrorCode::SameMintNotAllowed
+    )]
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    // The maker's token account for Token A, funding every vault.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Global config, checked for the emergency pause, the open-offer cap and the offer amount limits.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // Mints the admin has blocked, checked against both mints of the ladder.
+    #[account(seeds = [b"blacklist"], bump = blacklist.bump)]
+    pub blacklist: Account<'info, Blacklist>,
+
+    // Mints the admin permits, checked against both mints while the allowlist is enabled.
+    #[account(seeds = [b"allowlist"], bump = allowlist.bump)]
+    pub allowlist: Box<Account<'info, Allowlist>>,
+
+    // The maker's open-offer counter, created on their first offer and counting every rung.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + MakerStats::INIT_SPACE,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // The maker's offer index, if they have created one. Every rung is appended to it.
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Option<Box<Account<'info, MakerIndex>>>,
+
+    // The pair's offer index, if one has been created. Every rung is appended to it.
+    #[account(
+        mut,
+        seeds = [b"pair", token_mint_a.key().as_ref(), token_mint_b.key().as_ref()],
+        bump = pair_index.bump
+    )]
+    pub pair_index: Option<Box<Account<'info, PairIndex>>>,
+
+    // Required programs for creating the offers and their vaults.
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+// Function to post one offer per rung, each created at the PDA of its id like `make_offer` would.
+// Every rung shares the pair and `expiry`, and gets the same checks as a single offer.
+pub fn open_ladder<'info>(
+    ctx: Context<'_, '_, 'info, 'info, MakeOffersLadder<'info>>,
+    ids: Vec<u64>, // Id of each rung's offer.
+    token_a_offered_amounts: Vec<u64>, // Token A each rung offers.
+    token_b_wanted_amounts: Vec<u64>, // Token B each rung wants for it.
+    expiry: i64, // Expiry shared by every rung, or 0 for none.
+) -> Result<()> {
+    // One offered and one wanted amount per id, one account group per rung, within the cap.
+    let rungs = ids.len();
+    require!(
+        rungs > 0
+            && token_a_offered_amounts.len() == rungs
+            && token_b_wanted_amounts.len() == rungs
+            && ctx.remaining_accounts.len() == rungs * LADDER_RUNG_ACCOUNTS,
+        ErrorCode::BatchAccountsMismatch
+    );
+    require!(rungs <= MAX_LADDER_RUNGS, ErrorCode::LadderTooLarge);
+
+    // The checks `make_offer` applies, once for the whole ladder.
+    let accounts = &ctx.accounts;
+    require!(!accounts.config.paused, ErrorCode::ProgramPaused);
+    let now = Clock::get()?.unix_timestamp;
+    require!(expiry == 0 || expiry >= now + MIN_OFFER_DURATION, ErrorCode::ExpiryTooSoon);
+    reject_unsupported_pair(
+        &accounts.token_mint_a,
+        &accounts.token_mint_b,
+        &accounts.config,
+        &accounts.blacklist,
+        &accounts.allowlist,
+    )?;
+
+    // The maker pays the rent of every rung's offer and vault, net of any lamports already at their
+    // addresses, and the SOL wrapped into the vaults when Token A is native, so a wallet short of it
+    // fails here naming the amount, before any rung is created. Anchor creates the maker's stats
+    // before this runs, so their rent is covered by `check_offer_funding` placed ahead of the ladder.
+    let deposits = if is_native_mint(&accounts.token_mint_a.key()) {
+        token_a_offered_amounts
+            .iter()
+            .try_fold(0u64, |total, &amount| total.checked_add(amount))
+            .ok_or(ErrorCode::MathOverflow)?
+    } else {
+        0
+    };
+    let token_account_space = associated_token_account_space(&accounts.token_mint_a, &accounts.token_program)?;
+    let rent = rungs_rent_due(ctx.remaining_accounts, token_account_space)?;
+    check_funding(&accounts.maker, &accounts.maker, deposits, rent)?;
+
+    let maker_stats = &mut ctx.accounts.maker_stats;
+    maker_stats.maker = ctx.accounts.maker.key();
+    maker_stats.bump = ctx.bumps.maker_stats;
+
+    let remaining_accounts = ctx.remaining_accounts;
+    for (i, group) in remaining_accounts.chunks(LADDER_RUNG_ACCOUNTS).enumerate() {
+        let max_open_offers = ctx.accounts.config.max_open_offers;
+        ctx.accounts.maker_stats.record_created();
+        ctx.accounts.maker_stats.record_opened(max_open_offers)?;
+        open_rung(
+            ctx.accounts,
+            group,
+            ids[i],
+            token_a_offered_amounts[i],
+            token_b_wanted_amounts[i],
+            expiry,
+            now,
+        )?;
+        index_rung(ctx.accounts, group[0].key())?;
+    }
+
+    // Announce the whole ladder to indexers at once, rather than one event per rung.
+    emit!(LadderCreated {
+        maker: ctx.accounts.maker.key(),
+        token_mint_a: ctx.accounts.token_mint_a.key(),
+        token_mint_b: ctx.accounts.token_mint_b.key(),
+        rung_count: rungs as u8,
+        ids,
+    });
+    Ok(())
+}
+
+// Function to sum the rent still due on each rung's offer and vault in `groups`, net of any lamports
+// already at their addresses. A vault takes `token_account_space` bytes.
+pub fn rungs_rent_due(groups: &[AccountInfo], token_account_space: usize) -> Result<u64> {
+    groups.chunks(LADDER_RUNG_ACCOUNTS).try_fold(0u64, |total, group| {
+        let rung_rent = rent_due(&group[0], ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE)?
+            .checked_add(rent_due(&group[1], token_account_space)?)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(total.checked_add(rung_rent).ok_or(ErrorCode::MathOverflow)?)
+    })
+}
+
+// Function to append a rung's offer to the maker's index and the pair's index, for each one passed.
+fn index_rung(accounts: &mut MakeOffersLadder, offer: Pubkey) -> Result<()> {
+    if let Some(maker_index) = accounts.maker_index.as_mut() {
+        maker_index.push(offer)?;
+    }
+    match accounts.pair_index.as_mut() {
+        Some(pair_index) => pair_index.push(offer),
+        None => Ok(()),
+    }
+}
+
+// Function to create, fund and save a single rung's offer and vault.
+fn open_rung<'info>(
+    accounts: &MakeOffersLadder<'info>, // Named accounts of the ladder.
+    group: &'info [AccountInfo<'info>], // The rung's two remaining accounts.
+    id: u64, // Id of the rung's offer.
+    token_a_offered_amount: u64, // Token A the rung offers.
+    token_b_wanted_amount: u64, // Token B the rung wants.
+    expiry: i64, // Expiry shared by every rung.
+    now: i64, // Current time, recorded as the creation time.
+) -> Result<()> {
+    require!(token_a_offered_amount > 0, ErrorCode::ZeroAmount);
+    require!(token_b_wanted_amount > 0, ErrorCode::ZeroAmount);
+    require!(
+        accounts.config.accepts_offer_amount(token_a_offered_amount),
+        ErrorCode::OfferAmountOutOfRange
+    );
+
+    let maker_key = accounts.maker.key();
+    let token_mint_a = accounts.token_mint_a.key();
+    let (offer_info, vault_info) = (&group[0], &group[1]);
+
+    // The offer must sit at the PDA of its id, and the vault must be the offer's Token A account.
+    let (offer_address, bump) = derive_offer_address(&maker_key, id);
+    require_keys_eq!(offer_info.key(), offer_address, ErrorCode::BatchAccountsMismatch);
+    require_keys_eq!(
+        vault_info.key(),
+        get_associated_token_address_with_program_id(&offer_address, &token_mint_a, &accounts.token_program.key()),
+        ErrorCode::BatchAccountsMismatch
+    );
+
+    // Creating the offer account, signed for with the offer's seeds since it is a PDA.
+    let id_bytes = id.to_le_bytes();
+    let seeds = &[OFFER_SEED, maker_key.as_ref(), &id_bytes[..], &[bump]];
+    create_pda_account(
+        &accounts.maker, // Maker pays the offer's rent.
+        offer_info, // Offer being created.
+        ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE, // Space for the current layout.
+        &[&seeds[..]], // Offer seeds sign for the PDA.
+        &accounts.system_program, // System program creating it.
+    )?;
+
+    // Creating the vault and moving the rung's Token A into it.
+    associated_token::create(CpiContext::new(
+        accounts.associated_token_program.to_account_info(),
+        Create {
+            payer: accounts.maker.to_account_info(), // Maker pays the vault's rent.
+            associated_token: vault_info.clone(), // Vault being created.
+            authority: offer_info.clone(), // Offer PDA owns the vault.
+            mint: accounts.token_mint_a.to_account_info(), // Token A.
+            system_program: accounts.system_program.to_account_info(),
+            token_program: accounts.token_program.to_account_info(),
+        },
+    ))?;
+    // Wrapped SOL is wrapped straight from the maker's SOL into the vault, as `make_offer` does.
+    let mut vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+    if is_native_mint(&token_mint_a) {
+        wrap_sol(
+            &accounts.maker, // Maker pays the SOL.
+            &vault, // The rung's vault holds it as wrapped SOL.
+            token_a_offered_amount, // Lamports to wrap.
+            &accounts.system_program, // System program moving the lamports.
+            &accounts.token_program, // Token program syncing the vault.
+        )?;
+    } else {
+        transfer_tokens(
+            &accounts.maker_token_account_a, // Source account: Maker's token account.
+            &vault, // Destination account: the rung's vault.
+            &token_a_offered_amount, // Amount to transfer.
+            &accounts.token_mint_a, // Mint associated with Token A.
+            &accounts.maker, // Authority over the source account.
+            &accounts.token_program, // Token program handling the transfer.
+        )?;
+    }
+    check_deposit(&mut vault, token_a_offered_amount)?;
+
+    // Saving the offer's terms, with every optional feature left off as a plain `make_offer` would.
+    let offer = Offer {
+        id, // Offer ID.
+        maker: maker_key, // Maker's public key.
+        token_mint_a, // Public key of Token A mint.
+        token_mint_b: accounts.token_mint_b.key(), // Public key of Token B mint.
+        token_b_wanted_amount, // Amount of Token B wanted.
+        bump, // Bump for the Offer PDA.
+        token_a_offered_amount, // Amount of Token A offered.
+        expiry, // Expiry timestamp.
+        allowed_taker: None, // Open to anyone.
+        basket: Vec::new(), // No extra tokens.
+        min_fill_amount: 0, // Any partial fill is accepted.
+        token_a_remaining: token_a_offered_amount, // Nothing has been filled yet.
+        created_at: now, // Creation time, for sorting offers by age.
+        token_b_destination: None, // Token B goes to the maker's account.
+        label: String::new(), // No description.
+        auction: None, // Fixed price.
+        creator: maker_key, // Original maker, fixing the offer's address.
+        royalty_recipient: Pubkey::default(), // No royalty.
+        royalty_bps: 0, // No royalty.
+        token_a_decimals: accounts.token_mint_a.decimals, // Decimals of Token A.
+        token_b_decimals: accounts.token_mint_b.decimals, // Decimals of Token B.
+        version: OFFER_VERSION, // Current account layout.
+        oracle: Pubkey::default(), // No price feed.
+        spread_bps: 0, // No oracle spread.
+        rent_payer: maker_key, // Maker paid the rent.
+        taker_rebate_bps: 0, // No taker rebate.
+        commit_only: false, // Open to direct takes.
+        rebate_reserve: 0, // No rebate reserve.
+    };
+    offer.try_serialize(&mut &mut offer_info.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+use super::transfer_lamports; // Utility function for paying the extra rent.
+use crate::error::ErrorCode; // Custom program errors.
+// The offer account and its layout constants.
+use crate::{Offer, ANCHOR_DISCRIMINATOR, OFFER_VERSION};
+
+#[derive(Accounts)]
+pub struct MigrateOffer<'info> {
+    // The maker of the offer, paying the rent for any extra space.
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    /// CHECK: An offer written by an older version of the program may be too short to deserialize
+    /// as the current `Offer`, so its owner and discriminator are checked by hand, and its maker and
+    /// address once it has been reallocated.
+    #[account(mut, owner = crate::ID)]
+    pub offer: UncheckedAccount<'info>,
+
+    // Required system program for the rent transfer.
+    pub system_program: Program<'info, System>,
+}
+
+// Function to grow an older offer account to the current layout, defaulting the new fields,
+// and stamp it with the current version.
+pub fn upgrade_offer(ctx: Context<MigrateOffer>) -> Result<()> {
+    let offer_info = ctx.accounts.offer.to_account_info();
+    let mut offer = grow_offer_account(
+        &ctx.accounts.maker, // Maker pays the extra rent.
+        &offer_info, // Offer account being grown.
+        &ctx.accounts.system_program, // System program moving the lamports.
+    )?;
+
+    // Only older offers can be migrated, which also rules out downgrading a newer one.
+    require!(offer.version < OFFER_VERSION, ErrorCode::InvalidOfferVersion);
+    offer.version = OFFER_VERSION;
+    let mut data = offer_info.try_borrow_mut_data()?;
+    offer.try_serialize(&mut &mut data[..])
+}
+
+// Function to grow an offer account, possibly written by an older version of the program, to the
+// current size and read it back, checking it belongs to the maker and sits at its own address.
+pub fn grow_offer_account<'info>(
+    // Maker of the offer, paying any extra rent
+    maker: &Signer<'info>,
+
+    // Offer account being grown, already checked to be owned by the program
+    offer_info: &AccountInfo<'info>,
+
+    // System program used for the rent transfer
+    system_program: &Program<'info, System>,
+) -> Result<Offer> {
+    require!(
+        offer_info.try_borrow_data()?.starts_with(&Offer::DISCRIMINATOR),
+        ErrorCode::InvalidOfferVersion
+    );
+
+    // Fields are only ever appended, so growing the account with zeroed bytes gives every new
+    // field its default value.
+    let space = ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE;
+    if offer_info.data_len() < space {
+        let rent = Rent::get()?.minimum_balance(space).saturating_sub(offer_info.lamports());
+        if rent > 0 {
+            transfer_lamports(maker, offer_info.clone(), rent, system_program)?;
+        }
+        offer_info.realloc(space, true)?;
+    }
+
+    let offer = Offer::try_deserialize(&mut &offer_info.try_borrow_data()?[..])?;
+    require_keys_eq!(offer.maker, maker.key(), ErrorCode::Unauthorized);
+    require!(offer.has_canonical_address(offer_info.key), ErrorCode::InvalidOfferBump);
+    Ok(offer)
+}
@@ -1,9 +1,132 @@
 pub mod make_offer;
 pub use make_offer::*;
 
+pub mod make_offers_ladder;
+pub use make_offers_ladder::*;
+
+pub mod make_offer_for_sol;
+pub use make_offer_for_sol::*;
+
+pub mod check_offer_funding;
+pub use check_offer_funding::*;
+
 pub mod take_offer;
 pub use take_offer::*;
 
+pub mod take_offer_existing_accounts;
+pub use take_offer_existing_accounts::*;
+
+pub mod take_offer_with_sol;
+pub use take_offer_with_sol::*;
+
+pub mod commit_take;
+pub use commit_take::*;
+
+pub mod reveal_take;
+pub use reveal_take::*;
+
+pub mod close_commitment;
+pub use close_commitment::*;
+
+pub mod take_offers_batch;
+pub use take_offers_batch::*;
+
+pub mod cancel_offer;
+pub use cancel_offer::*;
+
+pub mod admin_close_offer;
+pub use admin_close_offer::*;
+
+pub mod cancel_offers_batch;
+pub use cancel_offers_batch::*;
+
+pub mod close_stale_offer;
+pub use close_stale_offer::*;
+
+pub mod sweep_expired_offer;
+pub use sweep_expired_offer::*;
+
+pub mod top_up_offer;
+pub use top_up_offer::*;
+
+pub mod rebalance_offer;
+pub use rebalance_offer::*;
+
+pub mod reduce_offer;
+pub use reduce_offer::*;
+
+pub mod update_offer;
+pub use update_offer::*;
+
+pub mod extend_expiry;
+pub use extend_expiry::*;
+
+pub mod transfer_offer_ownership;
+pub use transfer_offer_ownership::*;
+
+pub mod create_maker_index;
+pub use create_maker_index::*;
+
+pub mod prune_maker_index;
+pub use prune_maker_index::*;
+
+pub mod create_pair_index;
+pub use create_pair_index::*;
+
+pub mod prune_pair_index;
+pub use prune_pair_index::*;
+
+pub mod reset_maker_stats;
+pub use reset_maker_stats::*;
+
+pub mod migrate_offer;
+pub use migrate_offer::*;
+
+pub mod resize_offer;
+pub use resize_offer::*;
+
+pub mod get_offer;
+pub use get_offer::*;
+
+pub mod verify_offer_integrity;
+pub use verify_offer_integrity::*;
+
+pub mod create_price_feed;
+pub use create_price_feed::*;
+
+pub mod update_price_feed;
+pub use update_price_feed::*;
+
+pub mod quote_take;
+pub use quote_take::*;
+
+pub mod initialize_config;
+pub use initialize_config::*;
+
+pub mod close_config;
+pub use close_config::*;
+
+pub mod update_config;
+pub use update_config::*;
+
+pub mod withdraw_treasury;
+pub use withdraw_treasury::*;
+
+pub mod update_blacklist;
+pub use update_blacklist::*;
+
+pub mod update_allowlist;
+pub use update_allowlist::*;
+
+pub mod update_fee_exempt;
+pub use update_fee_exempt::*;
+
+pub mod basket;
+pub use basket::*;
+
+pub mod rebate_reserve;
+pub use rebate_reserve::*;
+
 pub mod shared;
 pub use shared::*;
 
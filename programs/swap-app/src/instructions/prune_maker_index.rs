@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::{MakerIndex, Offer}; // The maker's offer index and the offers it lists.
+
+#[derive(Accounts)]
+pub struct PruneMakerIndex<'info> {
+    // The maker owning the index.
+    pub maker: Signer<'info>,
+
+    // The maker's offer index PDA being pruned.
+    #[account(
+        mut,
+        seeds = [b"maker_index", maker.key().as_ref()],
+        bump = maker_index.bump
+    )]
+    pub maker_index: Account<'info, MakerIndex>,
+}
+
+// Function to drop indexed offers that are no longer the maker's open offers. Each candidate is
+// passed in `remaining_accounts`; it is dropped when it has been closed or now belongs to another
+// maker. Accounts that are not in the index are ignored.
+pub fn prune<'info>(ctx: Context<'_, '_, 'info, 'info, PruneMakerIndex<'info>>) -> Result<()> {
+    let maker = ctx.accounts.maker.key();
+    let index = &mut ctx.accounts.maker_index;
+
+    for offer_info in ctx.remaining_accounts {
+        if !index.offers.contains(offer_info.key) {
+            continue;
+        }
+        let still_open = *offer_info.owner == crate::ID
+            && Account::<Offer>::try_from(offer_info).is_ok_and(|offer| offer.maker == maker);
+        if !still_open {
+            index.offers.retain(|indexed| indexed != offer_info.key);
+        }
+    }
+    Ok(())
+}
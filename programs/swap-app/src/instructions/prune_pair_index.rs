@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::{Offer, PairIndex}; // The pair's offer index and the offers it lists.
+
+#[derive(Accounts)]
+pub struct PrunePairIndex<'info> {
+    // The pair's offer index PDA being pruned. Anyone may prune it, since only closed offers are dropped.
+    #[account(
+        mut,
+        seeds = [b"pair", pair_index.token_mint_a.as_ref(), pair_index.token_mint_b.as_ref()],
+        bump = pair_index.bump
+    )]
+    pub pair_index: Account<'info, PairIndex>,
+}
+
+// Function to drop indexed offers that have been closed, for example by a batch take or cancel,
+// which do not update pair indexes. Each candidate is passed in `remaining_accounts`; accounts that
+// are not in the index are ignored.
+pub fn drop_closed_offers<'info>(ctx: Context<'_, '_, 'info, 'info, PrunePairIndex<'info>>) -> Result<()> {
+    let index = &mut ctx.accounts.pair_index;
+
+    for offer_info in ctx.remaining_accounts {
+        if !index.offers.contains(offer_info.key) {
+            continue;
+        }
+        let still_open = *offer_info.owner == crate::ID && Account::<Offer>::try_from(offer_info).is_ok();
+        if !still_open {
+            index.remove(offer_info.key);
+        }
+    }
+    Ok(())
+}
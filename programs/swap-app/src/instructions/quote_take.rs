@@ -0,0 +1,117 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount; // Represents a token account.
+
+// The pricing shared by every take instruction.
+use super::{fee_bps_for_taker, is_exempt_taker, take_cost, total_token_b_due};
+use crate::error::ErrorCode; // Custom program errors.
+// The offer being priced, its feed, and the global fee settings.
+use crate::{Config, FeeExempt, Offer, PriceFeed, OFFER_SEED};
+
+#[derive(Accounts)]
+pub struct QuoteTake<'info> {
+    // The global config holding the protocol and flat fees.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // The offer being priced. The seeds check rejects accounts that are not real offers.
+    #[account(
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // The price feed named by an oracle-priced offer. Only needed when the offer has an oracle.
+    #[account(address = offer.oracle @ ErrorCode::OracleAccountMismatch)]
+    pub price_feed: Option<Account<'info, PriceFeed>>,
+
+    /// CHECK: The taker the take is priced for, only read for its key. Omit it, and the two
+    /// accounts below, to price the take at the normal fee.
+    pub taker: Option<UncheckedAccount<'info>>,
+
+    // The taker's account of the config's governance token, pricing the take at the discounted fee
+    // when it holds enough, as `take_offer` would. Needs the taker.
+    #[account(
+        constraint = governance_token_account.mint == config.governance_mint @ ErrorCode::GovernanceMintMismatch,
+        constraint = taker.as_ref().map(|taker| taker.key()) == Some(governance_token_account.owner)
+            @ anchor_lang::error::ErrorCode::ConstraintTokenOwner
+    )]
+    pub governance_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The admin's list of fee-exempt takers, pricing the take without any fee for a taker on it.
+    #[account(seeds = [b"fee_exempt"], bump = fee_exempt.bump)]
+    pub fee_exempt: Option<Box<Account<'info, FeeExempt>>>,
+}
+
+impl QuoteTake<'_> {
+    // Function to pick the protocol fee `take_offer` would charge the taker, and whether they are
+    // exempt from the flat fee. Without a taker, the normal fee and flat fee apply.
+    fn taker_fees(&self) -> (u16, bool) {
+        let Some(taker) = self.taker.as_ref() else {
+            return (self.config.fee_bps, false);
+        };
+        let fee_exempt = self.fee_exempt.as_deref().map(|fee_exempt| &**fee_exempt);
+        let fee_bps = fee_bps_for_taker(
+            &self.config,
+            &taker.key(),
+            self.governance_token_account.as_deref().map(|governance_token_account| &**governance_token_account),
+            fee_exempt,
+        );
+        (fee_bps, is_exempt_taker(&taker.key(), fee_exempt))
+    }
+}
+
+/// What taking part of an offer right now would move, as priced by `take_offer`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TakeQuote {
+    /// Token A the taker would receive.
+    pub token_a_amount: u64,
+    /// Token B the taker would pay, protocol fee and royalty included.
+    pub token_b_amount: u64,
+    /// Part of `token_b_amount` sent to the treasury.
+    pub protocol_fee: u64,
+    /// Part of `token_b_amount` sent to the royalty recipient.
+    pub royalty: u64,
+    /// Part of `token_b_amount` left for the maker.
+    pub maker_amount: u64,
+    /// Token B the offer's rebate reserve would pay back to the taker once the take is paid.
+    pub taker_rebate: u64,
+    /// SOL the taker would pay the treasury on top.
+    pub flat_fee_lamports: u64,
+}
+
+// Function to price taking `token_a_amount` of the offer at the current time. Nothing is written.
+pub fn price_take(ctx: Context<QuoteTake>, token_a_amount: u64) -> Result<TakeQuote> {
+    let now = Clock::get()?.unix_timestamp;
+    let (fee_bps, exempt) = ctx.accounts.taker_fees();
+    let cost = take_cost(
+        &ctx.accounts.offer,
+        &ctx.accounts.config,
+        fee_bps,
+        token_a_amount,
+        now,
+        ctx.accounts.price_feed.as_deref(),
+    )?;
+    Ok(TakeQuote {
+        token_a_amount,
+        token_b_amount: cost.token_b_amount,
+        protocol_fee: cost.protocol_fee,
+        royalty: cost.royalty,
+        maker_amount: cost.maker_amount,
+        taker_rebate: cost.taker_rebate,
+        flat_fee_lamports: if exempt { 0 } else { cost.flat_fee_lamports },
+    })
+}
+
+// Function to price taking everything left on the offer at the current time, following its auction
+// or price feed if it has one. Fixed-price offers simply cost their stored wanted amount. Nothing is written.
+pub fn price_offer(ctx: Context<QuoteTake>) -> Result<u64> {
+    let now = Clock::get()?.unix_timestamp;
+    let (fee_bps, _) = ctx.accounts.taker_fees();
+    total_token_b_due(
+        &ctx.accounts.offer,
+        &ctx.accounts.config,
+        fee_bps,
+        now,
+        ctx.accounts.price_feed.as_deref(),
+    )
+}
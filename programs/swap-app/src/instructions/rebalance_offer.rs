@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+// The top-up and re-pricing steps combined here.
+use super::{check_top_up, check_wanted_amount, deposit_top_up, reprice, TopUpOffer};
+
+// Function to top up the offer with `token_a_amount` and re-price it to `token_b_wanted_amount` at
+// once, with the accounts of `top_up_offer`. Both are checked before any tokens move, so either
+// both changes land or neither does.
+pub fn top_up_and_reprice(ctx: Context<TopUpOffer>, token_a_amount: u64, token_b_wanted_amount: u64) -> Result<()> {
+    check_top_up(ctx.accounts, token_a_amount)?;
+    check_wanted_amount(&ctx.accounts.offer, token_b_wanted_amount)?;
+
+    deposit_top_up(ctx.accounts, token_a_amount)?;
+    reprice(&mut ctx.accounts.offer, token_b_wanted_amount);
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address_with_program_id, Create},
+    token_2022::spl_token_2022::extension::permanent_delegate::PermanentDelegate, // Token-2022 permanent-delegate extension.
+    token_interface::{
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+use super::{
+    check_deposit, mint_has_extension, transfer_tokens, BasketLeg, MakeOffer, BASKET_LEG_ACCOUNTS,
+}; // The basket's account layout, which the reserve shares.
+use crate::error::ErrorCode; // Custom program errors.
+use crate::Offer; // The offer owning the reserve.
+
+// An offer's rebate reserve is passed through `remaining_accounts` after any basket accounts, laid out
+// like a basket leg: Token B's mint, the offer's reserve vault for Token B, and the maker's token
+// account for Token B, which funds the reserve and gets back whatever is left of it. It is only passed
+// while the offer holds a reserve.
+pub const REBATE_RESERVE_ACCOUNTS: usize = BASKET_LEG_ACCOUNTS;
+
+// Function to split the reserve's accounts off the end of `remaining_accounts` when `has_reserve` is
+// set, leaving any basket accounts in front of them. Without a reserve nothing is split off.
+pub fn split_rebate_reserve<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    has_reserve: bool,
+) -> Result<(&'info [AccountInfo<'info>], &'info [AccountInfo<'info>])> {
+    let reserve_accounts = if has_reserve { REBATE_RESERVE_ACCOUNTS } else { 0 };
+    let split = remaining_accounts
+        .len()
+        .checked_sub(reserve_accounts)
+        .ok_or(ErrorCode::RebateReserveMismatch)?;
+    Ok(remaining_accounts.split_at(split))
+}
+
+// Function to create the offer's reserve vault for Token B and fill it with `rebate_reserve` from the
+// maker's account. Token B only sits in escrow here, so it is refused a permanent delegate like Token A.
+pub fn open_rebate_reserve<'info>(
+    context: &Context<'_, '_, 'info, 'info, MakeOffer<'info>>, // Context containing all the accounts involved.
+    reserve_accounts: &'info [AccountInfo<'info>], // The reserve's accounts, split off `remaining_accounts`.
+    rebate_reserve: u64, // Token B escrowed for rebates.
+) -> Result<()> {
+    if rebate_reserve == 0 {
+        return Ok(());
+    }
+    let accounts = &context.accounts;
+    let (mint_info, vault_info, maker_account_info) = (&reserve_accounts[0], &reserve_accounts[1], &reserve_accounts[2]);
+    require_keys_eq!(mint_info.key(), accounts.token_mint_b.key(), ErrorCode::RebateReserveMismatch);
+    require!(
+        !mint_has_extension::<PermanentDelegate>(&accounts.token_mint_b)?,
+        ErrorCode::PermanentDelegateNotAllowed
+    );
+
+    // The vault must be the offer's associated token account for Token B.
+    require_keys_eq!(
+        vault_info.key(),
+        get_associated_token_address_with_program_id(
+            &accounts.offer.key(),
+            &accounts.token_mint_b.key(),
+            &accounts.token_program.key()
+        ),
+        ErrorCode::RebateReserveMismatch
+    );
+    associated_token::create(CpiContext::new(
+        accounts.associated_token_program.to_account_info(),
+        Create {
+            payer: accounts.rent_payer.to_account_info(), // Rent payer covers the vault's rent.
+            associated_token: vault_info.clone(), // Vault being created.
+            authority: accounts.offer.to_account_info(), // Offer PDA owns the vault.
+            mint: mint_info.clone(), // Token B's mint.
+            system_program: accounts.system_program.to_account_info(),
+            token_program: accounts.token_program.to_account_info(),
+        },
+    ))?;
+    let mut vault = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+    let maker_token_account = InterfaceAccount::<TokenAccount>::try_from(maker_account_info)?;
+
+    transfer_tokens(
+        &maker_token_account, // Source account: Maker's token account for Token B.
+        &vault, // Destination account: the reserve vault.
+        &rebate_reserve, // Amount to transfer.
+        &accounts.token_mint_b, // Mint associated with Token B.
+        &accounts.maker, // Authority over the source account.
+        &accounts.token_program, // Token program handling the transfer.
+    )?;
+    check_deposit(&mut vault, rebate_reserve)
+}
+
+// Function to load and validate an existing offer's reserve accounts, split off with
+// `split_rebate_reserve`, as a list holding its one leg, or nothing when the offer holds no reserve.
+// What is left of the reserve only ever goes back to the maker, so their account is checked as well.
+pub fn load_rebate_reserve<'info>(
+    reserve_accounts: &'info [AccountInfo<'info>], // The reserve's accounts, if the offer holds one.
+    offer: &Account<'info, Offer>, // Offer owning the vault.
+    token_program: &Interface<'info, TokenInterface>, // Token program governing the vault.
+) -> Result<Vec<BasketLeg<'info>>> {
+    if offer.rebate_reserve == 0 {
+        return Ok(Vec::new());
+    }
+    require_keys_eq!(reserve_accounts[0].key(), offer.token_mint_b, ErrorCode::RebateReserveMismatch);
+    require_keys_eq!(
+        reserve_accounts[1].key(),
+        get_associated_token_address_with_program_id(&offer.key(), &offer.token_mint_b, &token_program.key()),
+        ErrorCode::RebateReserveMismatch
+    );
+    let leg = BasketLeg {
+        mint: InterfaceAccount::<Mint>::try_from(&reserve_accounts[0])?,
+        vault: InterfaceAccount::try_from(&reserve_accounts[1])?,
+        user_token_account: InterfaceAccount::try_from(&reserve_accounts[2])?,
+    };
+    require_keys_eq!(leg.user_token_account.owner, offer.maker, ErrorCode::RebateReserveMismatch);
+    require!(leg.vault.amount >= offer.rebate_reserve, ErrorCode::VaultBalanceMismatch);
+    Ok(vec![leg])
+}
@@ -0,0 +1,112 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+use super::withdraw_from_vault; // Utility function for moving tokens out of the vault.
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{Config, Offer, OFFER_SEED}; // Importing the state accounts.
+
+#[derive(Accounts)]
+pub struct ReduceOffer<'info> {
+    // The maker (creator) of the offer. Only the maker can withdraw from it.
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    // Token mint for the offered token (A).
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // The maker's token account for the offered token (A), receiving the withdrawn tokens.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    // Global config, checked for the offer amount limits.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // The offer account being reduced.
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = token_mint_a,
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // The vault holding the tokens offered by the maker.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Required Solana programs for system operations.
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+// Function to return some Token A from the vault to the maker and shrink the offer to match.
+// Something must stay on the offer; withdrawing everything is what `cancel_offer` is for.
+// What stays must still be within the config's offer amount limits.
+// The wanted Token B is left as it is; makers re-price with `update_offer` if they want to.
+pub fn withdraw_from_offer(ctx: Context<ReduceOffer>, token_a_amount: u64) -> Result<()> {
+    require!(token_a_amount > 0, ErrorCode::ZeroAmount);
+    require!(
+        token_a_amount < ctx.accounts.offer.token_a_remaining
+            && token_a_amount < ctx.accounts.vault.amount,
+        ErrorCode::InsufficientVaultBalance
+    );
+    require!(
+        ctx.accounts.config.accepts_offer_amount(ctx.accounts.offer.token_a_remaining - token_a_amount),
+        ErrorCode::OfferAmountOutOfRange
+    );
+
+    // Seeds for generating the vault's PDA, which stay those of the offer's creator.
+    let creator = ctx.accounts.offer.creator;
+    let seeds = &[
+        OFFER_SEED,
+        creator.as_ref(),
+        &ctx.accounts.offer.id.to_le_bytes()[..],
+        &[ctx.accounts.offer.bump],
+    ];
+    let signer_seeds = [&seeds[..]];
+
+    // Returning the requested Token A to the maker.
+    withdraw_from_vault(
+        &ctx.accounts.vault, // Source vault.
+        &ctx.accounts.maker_token_account_a, // Destination account (maker's token A).
+        token_a_amount, // Amount to transfer.
+        &ctx.accounts.token_mint_a, // Mint for token A.
+        ctx.accounts.offer.to_account_info(), // Authority (offer PDA).
+        &ctx.accounts.token_program, // Token program.
+        &signer_seeds,
+    )?;
+
+    // Recording the smaller offer.
+    let offer = &mut ctx.accounts.offer;
+    offer.token_a_offered_amount = offer
+        .token_a_offered_amount
+        .checked_sub(token_a_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    offer.token_a_remaining = offer
+        .token_a_remaining
+        .checked_sub(token_a_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{Config, MakerStats}; // Global settings account and the stats being reset.
+
+#[derive(Accounts)]
+pub struct ResetMakerStats<'info> {
+    // The admin recorded in the config. Nobody else may reset a maker's stats.
+    pub admin: Signer<'info>,
+
+    // The global config, naming the admin.
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // The maker's stats PDA whose analytics counters are reset.
+    #[account(
+        mut,
+        seeds = [b"maker", maker_stats.maker.as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+}
+
+// Function to zero the maker's analytics counters. The open-offer count and the next auto id
+// are left alone, since the program relies on them.
+pub fn clear_counters(ctx: Context<ResetMakerStats>) -> Result<()> {
+    let maker_stats = &mut ctx.accounts.maker_stats;
+    maker_stats.offers_created = 0; // No offers counted as made.
+    maker_stats.offers_taken = 0; // No offers counted as taken.
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use anchor_lang::prelude::*;
+
+use super::grow_offer_account; // Utility function for growing an offer account.
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{Offer, ANCHOR_DISCRIMINATOR}; // The offer account and its sizing constants.
+
+#[derive(Accounts)]
+pub struct ResizeOffer<'info> {
+    // The maker of the offer, paying the rent for the extra space.
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    /// CHECK: The offer being grown to the current layout. An offer made before newer fields
+    /// existed is too short to deserialize as the current `Offer`, so its owner is checked here and
+    /// its discriminator, maker and address once it has been grown. The added bytes are zeroed, so
+    /// fields appended since the offer was made read as their defaults (an empty label, for
+    /// instance). Offers already larger than the current layout are refused rather than truncated.
+    #[account(
+        mut,
+        owner = crate::ID,
+        constraint = offer.data_len() <= ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE
+            @ ErrorCode::OfferShrinkNotAllowed
+    )]
+    pub offer: UncheckedAccount<'info>,
+
+    // Required system program for the rent transfer.
+    pub system_program: Program<'info, System>,
+}
+
+// Function to grow an offer account to the current size, leaving its fields as they are.
+pub fn grow_offer(ctx: Context<ResizeOffer>) -> Result<()> {
+    grow_offer_account(
+        &ctx.accounts.maker, // Maker pays the extra rent.
+        &ctx.accounts.offer.to_account_info(), // Offer account being grown.
+        &ctx.accounts.system_program, // System program moving the lamports.
+    )?;
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+use super::take_offer::*; // The regular take, which a successful reveal runs. Nested accounts need its generated items too.
+use crate::error::ErrorCode; // Custom program errors.
+// The commitment account and the reveal window.
+use crate::{TakeCommitment, MAX_REVEAL_DELAY_SLOTS, MIN_REVEAL_DELAY_SLOTS};
+
+#[derive(Accounts)]
+pub struct RevealTake<'info> {
+    // Every account of a regular take.
+    pub take: TakeOffer<'info>,
+
+    // The taker's commitment for this offer, closed by the reveal with its rent returned to them.
+    #[account(
+        mut,
+        seeds = [b"commit", take.offer.key().as_ref(), take.taker.key().as_ref()],
+        bump = commitment.bump
+    )]
+    pub commitment: Account<'info, TakeCommitment>,
+}
+
+// Function to check the revealed nonce against the commitment and the reveal window, consume the
+// commitment, and then take the offer exactly as `take_offer` would.
+pub fn reveal_and_fill<'info>(
+    ctx: Context<'_, '_, 'info, 'info, RevealTake<'info>>,
+    nonce: [u8; 32],
+    token_a_requested_amount: u64,
+    max_token_b_amount: u64,
+    unwrap: bool,
+) -> Result<()> {
+    let commitment = &ctx.accounts.commitment;
+    require!(commitment.is_revealed_by(&nonce), ErrorCode::CommitmentMismatch);
+    let slot = Clock::get()?.slot;
+    require!(slot >= commitment.slot + MIN_REVEAL_DELAY_SLOTS, ErrorCode::RevealTooEarly);
+    require!(slot <= commitment.slot + MAX_REVEAL_DELAY_SLOTS, ErrorCode::RevealTooLate);
+    ctx.accounts.commitment.close(ctx.accounts.take.taker.to_account_info())?;
+
+    let mut take = ctx.accounts.take.as_take(ctx.remaining_accounts, unwrap);
+    take.revealed = true;
+    fill_offer(take, token_a_requested_amount, max_token_b_amount)?;
+    if unwrap {
+        unwrap_taker_token_b(&ctx.accounts.take)?;
+    }
+    Ok(())
+}
@@ -10,9 +10,50 @@ use anchor_spl::
         TokenAccount,   // Represents a token account
         TokenInterface, // Interface for token program interactions
         TransferChecked, // Struct for checked token transfers
-        transfer_checked // Function to perform a checked token transfer
+        transfer_checked, // Function to perform a checked token transfer
+        CloseAccount,   // Struct for closing a token account
+        close_account,  // Function to close a token account
+        SyncNative,     // Struct for syncing a wrapped SOL account
+        sync_native     // Function to sync a wrapped SOL account's token balance
     };
 
+// Import the System Program transfer used to move lamports when wrapping SOL, and the
+// instructions used to create accounts at program addresses
+use anchor_lang::system_program::{
+    allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer
+};
+
+// Import the token program query for the size of a new token account
+use anchor_spl::token_2022::{get_account_data_size, GetAccountDataSize};
+
+// Import the program's custom errors
+use crate::error::ErrorCode;
+
+// Import the state read when pricing a take, and the pricing and fee helpers of `take_offer`
+use crate::{Config, FeeExempt, Offer, PriceFeed, BASIS_POINTS_DIVISOR};
+use super::{protocol_fee, royalty_amount, token_b_amount_for_fill};
+
+// Import Token-2022 extension parsing so mint extensions can be inspected
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, Extension, ExtensionType, StateWithExtensions},
+    solana_zk_token_sdk::instruction::Pod,
+};
+
+// Function to insist that a token program is one of the two canonical SPL token programs
+// `Interface<TokenInterface>` already checks this during account validation; repeating it right
+// before each transfer CPI keeps transfers safe even if an account struct is ever loosened
+pub fn check_token_program(
+    // Token program about to be invoked
+    token_program: &AccountInfo
+) -> Result<()> {
+    require!(
+        *token_program.key == anchor_spl::token::ID || *token_program.key == spl_token_2022::ID,
+        ErrorCode::InvalidTokenProgram
+    );
+    Ok(())
+}
+
 // Function to transfer tokens with additional safety checks
 // Generic lifetime 'info ensures all referenced accounts live for the same duration
 pub fn transfer_tokens<'info>(
@@ -34,6 +75,9 @@ pub fn transfer_tokens<'info>(
     // Token program interface for performing the transfer
     token_program: &Interface<'info, TokenInterface>
 ) -> Result<()> {
+    // Refuse anything but a real token program before handing it our accounts
+    check_token_program(&token_program.to_account_info())?;
+
     // Create a TransferChecked struct with required account information
     // This prepares the context for a cross-program invocation (CPI)
     let transfer_account_options = TransferChecked {
@@ -60,4 +104,390 @@ pub fn transfer_tokens<'info>(
         *amount,        // Amount to transfer (dereferenced)
         mint.decimals   // Number of decimal places for the token
     )
-}
\ No newline at end of file
+}
+
+// Function to move tokens out of a vault whose authority is a program-derived address
+// The PDA cannot sign a transaction, so the program signs on its behalf with `signer_seeds`
+pub fn withdraw_from_vault<'info>(
+    // Vault token account holding the escrowed tokens
+    vault: &InterfaceAccount<'info, TokenAccount>,
+
+    // Destination token account for the withdrawn tokens
+    to: &InterfaceAccount<'info, TokenAccount>,
+
+    // Amount of tokens to withdraw
+    amount: u64,
+
+    // Mint (token type) information for decimals and validation
+    mint: &InterfaceAccount<'info, Mint>,
+
+    // PDA that owns the vault
+    authority: AccountInfo<'info>,
+
+    // Token program interface for performing the transfer
+    token_program: &Interface<'info, TokenInterface>,
+
+    // Seeds used to derive the vault authority PDA
+    signer_seeds: &[&[&[u8]]]
+) -> Result<()> {
+    // Refuse anything but a real token program before signing for the vault
+    check_token_program(&token_program.to_account_info())?;
+
+    let transfer_account_options = TransferChecked {
+        from: vault.to_account_info(), // Source vault
+        to: to.to_account_info(),      // Destination token account
+        mint: mint.to_account_info(),  // Mint information for validation
+        authority                      // Vault authority PDA
+    };
+
+    let cpi_context = CpiContext::new_with_signer(
+        token_program.to_account_info(), // Token program to invoke
+        transfer_account_options,        // Transfer parameters
+        signer_seeds                     // PDA signature
+    );
+
+    transfer_checked(cpi_context, amount, mint.decimals)
+}
+
+// Function to close an emptied vault and send its rent lamports to `destination`
+pub fn close_vault<'info>(
+    // Vault token account to close (must hold no tokens)
+    vault: &InterfaceAccount<'info, TokenAccount>,
+
+    // Account receiving the vault's rent lamports
+    destination: AccountInfo<'info>,
+
+    // PDA that owns the vault
+    authority: AccountInfo<'info>,
+
+    // Token program interface for performing the close
+    token_program: &Interface<'info, TokenInterface>,
+
+    // Seeds used to derive the vault authority PDA
+    signer_seeds: &[&[&[u8]]]
+) -> Result<()> {
+    let close_account_options = CloseAccount {
+        account: vault.to_account_info(), // Vault to close
+        destination,                      // Recipient of the rent lamports
+        authority                         // Vault authority PDA
+    };
+
+    let cpi_context = CpiContext::new_with_signer(
+        token_program.to_account_info(), // Token program to invoke
+        close_account_options,           // Close parameters
+        signer_seeds                     // PDA signature
+    );
+
+    close_account(cpi_context)
+}
+
+// Function to check whether a mint carries the Token-2022 extension `T`
+// Legacy Token Program mints can never have extensions
+pub fn mint_has_extension<'info, T: Extension + Pod>(
+    // Mint whose extensions are inspected
+    mint: &InterfaceAccount<'info, Mint>
+) -> Result<bool> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(false);
+    }
+
+    // Parse the mint's TLV extension data and look for `T`
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_with_extensions = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    Ok(mint_with_extensions.get_extension::<T>().is_ok())
+}
+
+// Function to compute `amount * numerator / denominator`, rounded down
+// The product is widened to u128 and every step is checked, so bad inputs surface
+// as `MathOverflow` instead of a panic
+pub fn mul_div(amount: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    let result = (amount as u128)
+        .checked_mul(numerator as u128)
+        .and_then(|product| product.checked_div(denominator as u128))
+        .ok_or(ErrorCode::MathOverflow)?;
+    u64::try_from(result).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+// Function to compute `amount * numerator / denominator`, rounded up
+// Checked the same way as `mul_div`
+pub fn mul_div_ceil(amount: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    let result = (amount as u128)
+        .checked_mul(numerator as u128)
+        .and_then(|product| product.checked_add((denominator as u128).checked_sub(1)?))
+        .and_then(|product| product.checked_div(denominator as u128))
+        .ok_or(ErrorCode::MathOverflow)?;
+    u64::try_from(result).map_err(|_| ErrorCode::MathOverflow.into())
+}
+
+// What a take costs the taker, and where the Token B goes
+// The protocol fee and royalty are taken out of `token_b_amount`, not added on top of it;
+// the flat fee is charged separately in SOL
+// A taker rebate is paid back afterwards out of the offer's rebate reserve, so the treasury still
+// gets its full fee and the maker their full share
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TakeCost {
+    pub token_b_amount: u64,    // Total Token B the taker pays
+    pub protocol_fee: u64,      // Part of it sent to the treasury
+    pub royalty: u64,           // Part of it sent to the royalty recipient
+    pub maker_amount: u64,      // Part of it left for the maker
+    pub taker_rebate: u64,      // Token B paid back to the taker from the offer's rebate reserve
+    pub flat_fee_lamports: u64, // SOL the taker pays the treasury on top
+}
+
+// Function to price taking `token_a_amount` of an offer at `now`
+// Shared by every take instruction, so clients replicating it get exactly the on-chain amounts
+// The split is computed in a fixed order: the protocol fee, then the royalty, each rounded down
+// from the full payment, and the maker gets exactly what remains, so the rounding dust goes to
+// the maker and the parts always sum to `token_b_amount`; a fee and royalty that together exceed
+// the payment fail with `MathOverflow` rather than over-distributing
+// `price_feed` is the offer's feed, required when the offer is oracle-priced
+// `fee_bps` is the protocol fee charged to this taker, see `Config::taker_fee_bps`
+pub fn take_cost(
+    offer: &Offer,
+    config: &Config,
+    fee_bps: u16,
+    token_a_amount: u64,
+    now: i64,
+    price_feed: Option<&PriceFeed>
+) -> Result<TakeCost> {
+    // 1. The full payment, 2. the fee and 3. the royalty out of it, 4. the remainder for the maker
+    let token_b_amount = token_b_amount_for_fill(offer, token_a_amount, now, price_feed)?;
+    let protocol_fee = protocol_fee(token_b_amount, fee_bps)?;
+    let royalty = royalty_amount(offer, token_b_amount)?;
+    let maker_amount = token_b_amount
+        .checked_sub(protocol_fee)
+        .and_then(|amount| amount.checked_sub(royalty))
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // 5. The taker's rebate, rounded down and paid from the reserve, so it never exceeds what is left of it
+    let taker_rebate = mul_div(protocol_fee, offer.taker_rebate_bps as u64, BASIS_POINTS_DIVISOR)?.min(offer.rebate_reserve);
+    Ok(TakeCost {
+        token_b_amount,
+        protocol_fee,
+        royalty,
+        maker_amount,
+        taker_rebate,
+        flat_fee_lamports: config.flat_fee_lamports,
+    })
+}
+
+// Function to compute the Token B a taker must hold to take everything left on an offer at `now`
+// `fee_bps` is the protocol fee charged to this taker, see `fee_bps_for_taker`
+pub fn total_token_b_due(
+    offer: &Offer,
+    config: &Config,
+    fee_bps: u16,
+    now: i64,
+    price_feed: Option<&PriceFeed>
+) -> Result<u64> {
+    Ok(take_cost(offer, config, fee_bps, offer.token_a_remaining, now, price_feed)?.token_b_amount)
+}
+
+// Function to check whether `taker` passed the fee-exempt list and is on it
+// An exempt taker pays neither the protocol fee nor the flat fee
+pub fn is_exempt_taker(taker: &Pubkey, fee_exempt: Option<&FeeExempt>) -> bool {
+    fee_exempt.is_some_and(|fee_exempt| fee_exempt.contains(taker))
+}
+
+// Function to pick the protocol fee charged to `taker`, used by every instruction that prices a take
+// None when they are fee-exempt, discounted when they pass a governance token account holding at
+// least the config's threshold, see `Config::taker_fee_bps`
+pub fn fee_bps_for_taker(
+    config: &Config,
+    taker: &Pubkey,
+    governance_token_account: Option<&TokenAccount>,
+    fee_exempt: Option<&FeeExempt>
+) -> u16 {
+    if is_exempt_taker(taker, fee_exempt) {
+        return 0;
+    }
+    match governance_token_account {
+        Some(governance_token_account) => config.taker_fee_bps(governance_token_account.amount),
+        None => config.fee_bps,
+    }
+}
+
+// Function to check whether a mint is wrapped SOL under either token program
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == anchor_spl::token::spl_token::native_mint::ID || *mint == spl_token_2022::native_mint::ID
+}
+
+// Function to wrap native SOL by moving lamports into a wrapped SOL token account
+// `sync_native` then updates the token balance to match the new lamports
+pub fn wrap_sol<'info>(
+    // Wallet paying the SOL
+    from: &Signer<'info>,
+
+    // Wrapped SOL token account receiving the lamports
+    to: &InterfaceAccount<'info, TokenAccount>,
+
+    // Amount of lamports to wrap
+    amount: u64,
+
+    // System program used for the lamport transfer
+    system_program: &Program<'info, System>,
+
+    // Token program that owns the wrapped SOL account
+    token_program: &Interface<'info, TokenInterface>
+) -> Result<()> {
+    transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            Transfer {
+                from: from.to_account_info(), // Wallet paying the SOL
+                to: to.to_account_info()      // Wrapped SOL account
+            }
+        ),
+        amount
+    )?;
+
+    sync_native(CpiContext::new(
+        token_program.to_account_info(),
+        SyncNative {
+            account: to.to_account_info() // Wrapped SOL account to sync
+        }
+    ))
+}
+
+// Function to move plain lamports from a wallet to any account, such as a fee collected in SOL
+pub fn transfer_lamports<'info>(
+    // Wallet paying the SOL
+    from: &Signer<'info>,
+
+    // Account receiving the lamports
+    to: AccountInfo<'info>,
+
+    // Amount of lamports to transfer
+    amount: u64,
+
+    // System program used for the lamport transfer
+    system_program: &Program<'info, System>
+) -> Result<()> {
+    transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            Transfer {
+                from: from.to_account_info(), // Wallet paying the SOL
+                to                            // Account receiving the lamports
+            }
+        ),
+        amount
+    )
+}
+
+// Function to create a program-owned account of `space` bytes at a PDA, signed for with its seeds
+// Anyone can send lamports to an address before it is created, which makes a plain `create_account`
+// fail; like Anchor's `init`, an address already holding lamports is instead topped up to the
+// rent-exempt minimum, allocated and assigned to the program
+pub fn create_pda_account<'info>(
+    // Wallet paying the rent
+    payer: &Signer<'info>,
+
+    // Account being created, not yet owned by any program
+    account: &AccountInfo<'info>,
+
+    // Size of the account's data
+    space: usize,
+
+    // Seeds of the account's address, signing for it
+    signer_seeds: &[&[&[u8]]],
+
+    // System program creating the account
+    system_program: &Program<'info, System>
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let lamports = account.lamports();
+    if lamports == 0 {
+        return create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                CreateAccount {
+                    from: payer.to_account_info(), // Wallet paying the rent
+                    to: account.clone()            // Account being created
+                },
+                signer_seeds
+            ),
+            rent,
+            space as u64,
+            &crate::ID
+        );
+    }
+
+    if rent > lamports {
+        transfer_lamports(payer, account.clone(), rent - lamports, system_program)?;
+    }
+    allocate(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            Allocate { account_to_allocate: account.clone() },
+            signer_seeds
+        ),
+        space as u64
+    )?;
+    assign(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            Assign { account_to_assign: account.clone() },
+            signer_seeds
+        ),
+        &crate::ID
+    )
+}
+
+// Function to ask the token program how large an associated token account for `mint` will be
+// Token-2022 accounts grow with the mint's extensions, and associated ones always carry
+// `ImmutableOwner`; legacy Token accounts are always the same size
+pub fn associated_token_account_space<'info>(
+    // Mint of the token account
+    mint: &InterfaceAccount<'info, Mint>,
+
+    // Token program the account will belong to
+    token_program: &Interface<'info, TokenInterface>
+) -> Result<usize> {
+    let extensions: &[ExtensionType] = if token_program.key() == spl_token_2022::ID {
+        &[ExtensionType::ImmutableOwner]
+    } else {
+        &[]
+    };
+    let space = get_account_data_size(
+        CpiContext::new(
+            token_program.to_account_info(),
+            GetAccountDataSize { mint: mint.to_account_info() }
+        ),
+        extensions
+    )?;
+    Ok(space as usize)
+}
+
+// Function to unwrap SOL by closing a wrapped SOL token account owned by `owner`
+// Closing a native account releases both its rent and its wrapped balance as lamports
+pub fn unwrap_sol<'info>(
+    // Wrapped SOL token account to close
+    token_account: &InterfaceAccount<'info, TokenAccount>,
+
+    // Owner of the token account, who receives the lamports
+    owner: &Signer<'info>,
+
+    // Token program that owns the wrapped SOL account
+    token_program: &Interface<'info, TokenInterface>
+) -> Result<()> {
+    close_account(CpiContext::new(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: token_account.to_account_info(), // Wrapped SOL account to close
+            destination: owner.to_account_info(),      // Recipient of the lamports
+            authority: owner.to_account_info()         // Owner authorizing the close
+        }
+    ))
+}
+
+// Function to log the compute units left at a named checkpoint, for developers measuring what each
+// step of an instruction costs. Only builds with the `compute-logs` feature log anything
+pub fn log_compute_units(_checkpoint: &str) {
+    #[cfg(feature = "compute-logs")]
+    {
+        msg!("compute checkpoint: {}", _checkpoint);
+        anchor_lang::solana_program::log::sol_log_compute_units();
+    }
+}
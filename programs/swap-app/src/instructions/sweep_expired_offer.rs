@@ -0,0 +1,133 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+use super::{refund_and_close, Refund}; // The refund shared with `cancel_offer`.
+use crate::error::ErrorCode; // Custom program errors.
+// Importing the state accounts and the event emitted on a sweep.
+use crate::{Config, MakerStats, Offer, OfferSwept, PairIndex, OFFER_SEED};
+
+#[derive(Accounts)]
+pub struct SweepExpiredOffer<'info> {
+    // Anyone cleaning up the expired offer. Pays for the maker's Token A account if it is missing,
+    // but never receives any of the offer's tokens or rent.
+    #[account(mut)]
+    pub sweeper: Signer<'info>,
+
+    // The global config holding the grace period makers get after expiry.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// CHECK: The maker of the offer, receiving every refund, pinned by the offer's `has_one = maker`.
+    /// It may be owned by any program, so the offers of makers such as a DAO's treasury PDA can be
+    /// closed too; the refunds go to token accounts whose authority it is.
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+
+    // Token mint for the offered token (A).
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // The maker's token account for the offered token (A), receiving the refund.
+    // It will be created if it doesn't exist, since the maker takes no part in the transaction.
+    #[account(
+        init_if_needed,
+        payer = sweeper,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The maker's open-offer counter, decremented when the offer closes.
+    #[account(
+        mut,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // The expired offer being swept. Closed at the end of the instruction.
+    #[account(
+        mut,
+        close = rent_payer,
+        has_one = maker,
+        has_one = token_mint_a,
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // The pair's offer index, if one has been created. The offer is removed from it.
+    #[account(
+        mut,
+        seeds = [b"pair", offer.token_mint_a.as_ref(), offer.token_mint_b.as_ref()],
+        bump = pair_index.bump
+    )]
+    pub pair_index: Option<Box<Account<'info, PairIndex>>>,
+
+    /// CHECK: The account that paid the offer's rent, receiving it back.
+    /// Pinned to the offer's recorded rent payer. It may be owned by any program, such as a maker's
+    /// PDA that paid its own rent, since it only ever receives lamports.
+    #[account(mut, address = offer.rent_recipient() @ ErrorCode::RentPayerMismatch)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    // The vault holding the tokens offered by the maker.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Required Solana programs for system operations.
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> SweepExpiredOffer<'info> {
+    // Borrows the accounts `refund_and_close` works on, as `CancelOffer::as_refund` does.
+    pub fn as_refund<'a>(&'a mut self, remaining_accounts: &'info [AccountInfo<'info>]) -> Refund<'a, 'info> {
+        Refund {
+            token_mint_a: &self.token_mint_a,
+            vault: Some((&self.vault, &*self.maker_token_account_a)),
+            maker_stats: &mut self.maker_stats,
+            offer: &self.offer,
+            pair_index: self.pair_index.as_deref_mut(),
+            rent_payer: self.rent_payer.to_account_info(),
+            token_program: &self.token_program,
+            remaining_accounts,
+        }
+    }
+}
+
+// Function to refund an offer whose grace period after expiry is over and close it and its vaults,
+// exactly as if the maker had cancelled. Basket refunds are checked to go to the maker's accounts,
+// since the maker does not sign to choose them.
+pub fn sweep<'info>(ctx: Context<'_, '_, 'info, 'info, SweepExpiredOffer<'info>>) -> Result<()> {
+    // Until the grace period is over only the maker and the rent payer may close the offer, with `cancel_offer`.
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        ctx.accounts.offer.is_sweepable(ctx.accounts.config.expiry_grace_period, now),
+        ErrorCode::OfferNotSweepable
+    );
+
+    refund_and_close(ctx.accounts.as_refund(ctx.remaining_accounts))?;
+
+    // Announce the sweep to indexers.
+    emit!(OfferSwept {
+        id: ctx.accounts.offer.id,
+        maker: ctx.accounts.offer.maker,
+        sweeper: ctx.accounts.sweeper.key(),
+    });
+    Ok(())
+}
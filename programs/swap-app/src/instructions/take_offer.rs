@@ -1,20 +1,26 @@
 use anchor_lang::prelude::*;
 // Importing Anchor SPL libraries for handling associated tokens and token operations.
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    memo::{build_memo, BuildMemo, Memo}, // SPL Memo program, for annotating takes.
     token_interface::{
-        close_account, // Function to safely close token accounts.
-        transfer_checked, // Function to transfer tokens with validation.
-        CloseAccount, // Struct to define closing account instructions.
         Mint, // Represents the token mint (currency).
         TokenAccount, // Represents a token account.
         TokenInterface, // Represents the token program interface.
-        TransferChecked, // Struct to define transfer instructions.
     },
 };
 
-use super::transfer_tokens; // A utility function defined elsewhere for token transfers.
-use crate::Offer; // Importing the `Offer` struct, which represents the offer details.
+use super::{
+    check_basket_balances, close_vault, fee_bps_for_taker, is_exempt_taker, is_native_mint,
+    load_basket_legs, load_rebate_reserve, log_compute_units, mul_div, mul_div_ceil,
+    release_basket, split_rebate_reserve, take_cost, transfer_lamports, transfer_tokens,
+    unwrap_sol, withdraw_from_vault, wrap_sol, BasketLeg, TakeCost,
+}; // Utility functions defined elsewhere for token transfers.
+use crate::{
+    Config, FeeExempt, MakerStats, Offer, OfferTaken, PairIndex, PriceFeed, BASIS_POINTS_DIVISOR,
+    MAX_MEMO_LEN, OFFER_SEED,
+}; // Importing the state accounts and the event emitted when an offer is taken.
+use crate::error::ErrorCode; // Custom program errors.
 
 #[derive(Accounts)]
 pub struct TakeOffer<'info> {
@@ -22,9 +28,15 @@ pub struct TakeOffer<'info> {
     #[account(mut)]
     pub taker: Signer<'info>,
 
-    // The maker (creator) of the offer. This account is mutable as it may receive tokens.
-    #[account(mut)]
-    pub maker: SystemAccount<'info>,
+    /// CHECK: The maker (creator) of the offer, pinned by the offer's `has_one = maker`. It may be
+    /// owned by any program, so makers such as a DAO's treasury PDA can be paid; the Token B it
+    /// receives goes to `maker_token_account_b`, whose mint and authority are checked below.
+    /// Taking your own offer is pointless and only fakes volume, so it must differ from the taker.
+    #[account(
+        mut,
+        constraint = maker.key() != taker.key() @ ErrorCode::SelfTradeNotAllowed
+    )]
+    pub maker: UncheckedAccount<'info>,
 
     // Token mint for the offered token (A).
     pub token_mint_a: InterfaceAccount<'info, Mint>,
@@ -43,36 +55,92 @@ pub struct TakeOffer<'info> {
     )]
     pub taker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    // The taker's token account for the wanted token (B).
+    // The taker's token account for the wanted token (B). Any token account of the taker works.
+    // A wrapped SOL account is topped up from the taker's SOL when short, and only closed when the
+    // take asks to unwrap, so otherwise a pre-wrapped SOL account keeps whatever is left wrapped.
+    // Its mint is compared explicitly so a wrong account fails with a clear error.
     #[account(
         mut,
-        associated_token::mint = token_mint_b,
-        associated_token::authority = taker,
-        associated_token::token_program = token_program
+        constraint = taker_token_account_b.mint == token_mint_b.key() @ ErrorCode::WantedMintMismatch,
+        token::authority = taker,
+        token::token_program = token_program
     )]
     pub taker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    // The maker's token account for the wanted token (B).
+    // The maker's token account for the wanted token (B), receiving Token B unless the offer
+    // names a custom destination. Any existing token account of the maker works, so makers whose
+    // custodian set up a non-associated account can be paid there; omit it otherwise.
+    #[account(
+        mut,
+        constraint = maker_token_account_b.mint == token_mint_b.key() @ ErrorCode::WantedMintMismatch,
+        token::authority = maker,
+        token::token_program = token_program
+    )]
+    pub maker_token_account_b: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The offer's custom Token B destination, required when the offer names one.
+    #[account(
+        mut,
+        constraint = token_b_destination.mint == token_mint_b.key() @ ErrorCode::WantedMintMismatch,
+        token::token_program = token_program
+    )]
+    pub token_b_destination: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The royalty recipient's associated token account for the wanted token (B), required when
+    // the offer pays a royalty; omit it otherwise.
+    #[account(
+        mut,
+        constraint = royalty_token_account.mint == token_mint_b.key() @ ErrorCode::WantedMintMismatch,
+        token::token_program = token_program
+    )]
+    pub royalty_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The global config holding the protocol fee and the emergency pause.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // The treasury PDA that owns the protocol's fee accounts and collects the flat SOL fee.
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: SystemAccount<'info>,
+
+    // The treasury's token account for the wanted token (B), receiving the protocol fee.
+    // It will be created if it doesn't exist.
     #[account(
         init_if_needed,
         payer = taker,
         associated_token::mint = token_mint_b,
-        associated_token::authority = maker,
+        associated_token::authority = treasury,
         associated_token::token_program = token_program
     )]
-    pub maker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub treasury_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The maker's open-offer counter, decremented once the offer is completely filled.
+    #[account(
+        mut,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Box<Account<'info, MakerStats>>,
 
     // The offer account containing details about the trade.
+    // Closed by `withdraw_and_close_vault` once the offer is completely filled.
+    // Its stored bump signs for the vault, so it is re-derived and checked before it is trusted.
     #[account(
         mut,
-        close = maker,
         has_one = maker,
         has_one = token_mint_a,
         has_one = token_mint_b,
-        seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        constraint = offer.has_canonical_address(&offer.key()) @ ErrorCode::InvalidOfferBump,
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
         bump = offer.bump
     )]
-    offer: Account<'info, Offer>,
+    pub offer: Account<'info, Offer>,
+
+    /// CHECK: The account that paid the offer's rent, receiving it back once the offer is filled.
+    /// Pinned to the offer's recorded rent payer. It may be owned by any program, such as a maker's
+    /// PDA that paid its own rent, since it only ever receives lamports.
+    #[account(mut, address = offer.rent_recipient() @ ErrorCode::RentPayerMismatch)]
+    pub rent_payer: UncheckedAccount<'info>,
 
     // The vault holding the tokens offered by the maker.
     #[account(
@@ -83,71 +151,617 @@ pub struct TakeOffer<'info> {
     )]
     pub vault: InterfaceAccount<'info, TokenAccount>,
 
+    // The price feed named by an oracle-priced offer. Only needed when the offer has an oracle.
+    #[account(address = offer.oracle @ ErrorCode::OracleAccountMismatch)]
+    pub price_feed: Option<Box<Account<'info, PriceFeed>>>,
+
+    // The taker's account of the config's governance token, earning them the fee discount when it
+    // holds enough. It may live under either token program. Omit it to pay the normal fee.
+    #[account(
+        constraint = governance_token_account.mint == config.governance_mint @ ErrorCode::GovernanceMintMismatch,
+        token::authority = taker
+    )]
+    pub governance_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The admin's list of fee-exempt takers. A taker on it passes it to pay no protocol fee and no
+    // flat fee; omit it to pay both as usual.
+    #[account(seeds = [b"fee_exempt"], bump = fee_exempt.bump)]
+    pub fee_exempt: Option<Box<Account<'info, FeeExempt>>>,
+
+    // The pair's offer index, if one has been created. The offer is removed from it once filled.
+    #[account(
+        mut,
+        seeds = [b"pair", token_mint_a.key().as_ref(), token_mint_b.key().as_ref()],
+        bump = pair_index.bump
+    )]
+    pub pair_index: Option<Box<Account<'info, PairIndex>>>,
+
     // Required Solana programs for system operations.
     pub system_program: Program<'info, System>,
     pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
-// Function to transfer the wanted tokens (B) from the taker to the maker.
-pub fn send_wanted_tokens_to_maker(ctx: &Context<TakeOffer>) -> Result<()> {
-    transfer_tokens(
-        &ctx.accounts.taker_token_account_b, // Source account (taker's token B).
-        &ctx.accounts.maker_token_account_b, // Destination account (maker's token B).
-        &ctx.accounts.offer.token_b_wanted_amount, // Amount to transfer.
-        &ctx.accounts.token_mint_b, // Token mint for B.
-        &ctx.accounts.taker, // Signer (taker).
-        &ctx.accounts.token_program, // Token program.
+impl<'info> TakeOffer<'info> {
+    // Borrows the accounts a take works on, letting `take_offer` share its logic with
+    // `take_offer_existing_accounts`. `unwrap_sol` is the taker's choice to unwrap wrapped SOL.
+    pub fn as_take<'a>(
+        &'a mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        unwrap_sol: bool,
+    ) -> Take<'a, 'info> {
+        Take {
+            taker: &self.taker,
+            maker: &self.maker,
+            token_mint_a: &self.token_mint_a,
+            token_mint_b: &self.token_mint_b,
+            taker_token_account_a: &self.taker_token_account_a,
+            taker_token_account_b: &self.taker_token_account_b,
+            maker_token_account_b: self.maker_token_account_b.as_deref(),
+            token_b_destination: self.token_b_destination.as_deref(),
+            royalty_token_account: self.royalty_token_account.as_deref(),
+            config: &self.config,
+            treasury: &self.treasury,
+            treasury_token_account_b: &self.treasury_token_account_b,
+            maker_stats: &mut self.maker_stats,
+            offer: &mut self.offer,
+            rent_payer: &self.rent_payer,
+            vault: &mut self.vault,
+            price_feed: self.price_feed.as_deref().map(|price_feed| &**price_feed),
+            governance_token_account: self.governance_token_account.as_deref(),
+            fee_exempt: self.fee_exempt.as_deref().map(|fee_exempt| &**fee_exempt),
+            pair_index: self.pair_index.as_deref_mut(),
+            system_program: &self.system_program,
+            token_program: &self.token_program,
+            remaining_accounts,
+            unwrap_sol,
+            revealed: false,
+        }
+    }
+}
+
+// The accounts a take works on, already validated by the instruction's account constraints.
+pub struct Take<'a, 'info> {
+    pub taker: &'a Signer<'info>,
+    pub maker: &'a UncheckedAccount<'info>,
+    pub token_mint_a: &'a InterfaceAccount<'info, Mint>,
+    pub token_mint_b: &'a InterfaceAccount<'info, Mint>,
+    pub taker_token_account_a: &'a InterfaceAccount<'info, TokenAccount>,
+    pub taker_token_account_b: &'a InterfaceAccount<'info, TokenAccount>,
+    pub maker_token_account_b: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub token_b_destination: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub royalty_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub config: &'a Account<'info, Config>,
+    pub treasury: &'a SystemAccount<'info>,
+    pub treasury_token_account_b: &'a InterfaceAccount<'info, TokenAccount>,
+    pub maker_stats: &'a mut Account<'info, MakerStats>,
+    pub offer: &'a mut Account<'info, Offer>,
+    pub rent_payer: &'a UncheckedAccount<'info>,
+    pub vault: &'a mut InterfaceAccount<'info, TokenAccount>,
+    pub price_feed: Option<&'a PriceFeed>,
+    pub governance_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub fee_exempt: Option<&'a FeeExempt>,
+    pub pair_index: Option<&'a mut Account<'info, PairIndex>>,
+    pub system_program: &'a Program<'info, System>,
+    pub token_program: &'a Interface<'info, TokenInterface>,
+    pub remaining_accounts: &'info [AccountInfo<'info>],
+    // Whether to close the taker's wrapped SOL account after the withdrawal, unwrapping it to SOL.
+    pub unwrap_sol: bool,
+    // Whether the take comes through `reveal_take`, the only way to fill a commit-only offer.
+    pub revealed: bool,
+}
+
+// Function to split the SPL Memo program off the end of `remaining_accounts` when the taker attaches
+// a memo, leaving any basket accounts in front of it. Without a memo nothing is split off.
+pub fn split_memo_program<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    memo: &str,
+) -> Result<(Option<&'info AccountInfo<'info>>, &'info [AccountInfo<'info>])> {
+    require!(memo.len() <= MAX_MEMO_LEN, ErrorCode::MemoTooLong);
+    if memo.is_empty() {
+        return Ok((None, remaining_accounts));
+    }
+    let (memo_program, basket_accounts) = remaining_accounts
+        .split_last()
+        .ok_or(anchor_lang::error::ErrorCode::AccountNotEnoughKeys)?;
+    Ok((Some(memo_program), basket_accounts))
+}
+
+// Function to write the taker's memo into the transaction through the SPL Memo program.
+pub fn log_memo<'info>(memo_program: &'info AccountInfo<'info>, memo: &str) -> Result<()> {
+    let memo_program = Program::<Memo>::try_from(memo_program)?;
+    build_memo(
+        CpiContext::new(memo_program.to_account_info(), BuildMemo {}),
+        memo.as_bytes(),
     )
 }
 
-// Function to withdraw tokens from the vault and close it.
-pub fn withdraw_and_close_vault(ctx: Context<TakeOffer>) -> Result<()> {
-    // Seeds for generating the vault's PDA.
-    let seeds = &[
-        b"offer",
-        ctx.accounts.maker.to_account_info().key.as_ref(),
-        &ctx.accounts.offer.id.to_le_bytes()[..],
-        &[ctx.accounts.offer.bump],
+// Function to fill `token_a_requested_amount` of the offer for at most `max_token_b_amount`
+// of Token B, closing the vaults and the offer once it is completely filled.
+// Token B is paid before Token A is withdrawn, so every step returns its error with `?` and nothing
+// is caught: any failure, including one inside a token program CPI, aborts the whole transaction,
+// and the Solana runtime discards the payment and the recorded fill along with it.
+pub fn fill_offer(mut take: Take, token_a_requested_amount: u64, max_token_b_amount: u64) -> Result<()> {
+    // Step 0: Refuse to fill offers while the program is paused, once their deadline has passed,
+    // when the offer is reserved for a different taker, or when only part of a basket is requested.
+    // Commit-only offers are refused unless the take reveals a commitment.
+    let now = Clock::get()?.unix_timestamp;
+    check_take(take.config, take.offer, &take.taker.key(), token_a_requested_amount, now)?;
+    if !take.revealed {
+        check_direct_take(take.offer)?;
+    }
+
+    // Step 1: Confirm no account involved is frozen, the taker is not paying themselves, and the
+    // vault and any rebate reserve can deliver what the offer promises, then work out the Token B owed
+    // for the requested share of the offer and hold it to the taker's limit and balance.
+    check_accounts_not_frozen(&take)?;
+    check_accounts_not_aliased(&take)?;
+    check_vault_balance(take.vault, take.offer)?;
+    let mut reserve = load_take_rebate_reserve(&mut take)?;
+    let cost = take_cost(
+        take.offer,
+        take.config,
+        taker_fee_bps(&take),
+        token_a_requested_amount,
+        now,
+        take.price_feed,
+    )?;
+    require!(cost.token_b_amount <= max_token_b_amount, ErrorCode::SlippageExceeded);
+    let wrapped = wrap_taker_sol(&take, cost.token_b_amount)?;
+    check_taker_balance(&take, cost.token_b_amount - wrapped)?;
+    log_compute_units("take_offer priced");
+
+    // Step 2: Record the fill on the offer before any tokens move (checks-effects-interactions),
+    // so the offer already reflects this take if a later transfer calls back into the program.
+    record_fill(take.offer, token_a_requested_amount, cost.token_b_amount)?;
+    record_rebate(take.offer, cost.taker_rebate)?;
+
+    // Step 3: Transfer that amount of Token B from the taker's account to the maker's account,
+    // pay the taker any rebate out of the reserve, and collect any flat SOL fee.
+    send_wanted_tokens_to_maker(&take, &cost)?;
+    pay_taker_rebate(&take, &mut reserve, cost.taker_rebate)?;
+    collect_flat_fee(&take, cost.flat_fee_lamports)?;
+    log_compute_units("take_offer paid");
+
+    // Step 4: Withdraw the requested Token A from the vault to the taker's account,
+    // releasing any basket tokens and closing the vaults and the offer if nothing is left.
+    withdraw_and_close_vault(&mut take, token_a_requested_amount, cost.token_b_amount)
+}
+
+// Function to check the offer can be taken right now by `taker`, before it is priced. Shared by
+// every instruction that takes a single offer, so they all refuse the same takes.
+pub fn check_take(
+    config: &Config,
+    offer: &Offer,
+    taker: &Pubkey,
+    token_a_requested_amount: u64,
+    now: i64,
+) -> Result<()> {
+    require!(!config.paused, ErrorCode::ProgramPaused);
+    require!(!offer.is_expired(now), ErrorCode::OfferExpired);
+    require!(offer.can_be_taken_by(taker), ErrorCode::UnauthorizedTaker);
+    require!(
+        offer.basket.is_empty() || token_a_requested_amount == offer.token_a_remaining,
+        ErrorCode::BasketRequiresFullFill
+    );
+    Ok(())
+}
+
+// Function to refuse a take that does not come through `reveal_take` when the offer is commit-only.
+// Shared by every instruction that takes an offer directly.
+pub fn check_direct_take(offer: &Offer) -> Result<()> {
+    require!(!offer.commit_only, ErrorCode::CommitRevealRequired);
+    Ok(())
+}
+
+// Function to check the vault still holds everything left on the offer, so a taker
+// is never charged for Token A that is no longer there.
+pub fn check_vault_balance(vault: &TokenAccount, offer: &Offer) -> Result<()> {
+    require!(vault.amount >= offer.token_a_remaining, ErrorCode::VaultUnderfunded);
+    Ok(())
+}
+
+// Function to split the offer's rebate reserve off the take's remaining accounts, leaving the basket
+// accounts in front of it for `withdraw_and_close_vault`, and load it.
+pub fn load_take_rebate_reserve<'info>(take: &mut Take<'_, 'info>) -> Result<Vec<BasketLeg<'info>>> {
+    let (basket_accounts, reserve_accounts) =
+        split_rebate_reserve(take.remaining_accounts, take.offer.rebate_reserve > 0)?;
+    take.remaining_accounts = basket_accounts;
+    load_rebate_reserve(reserve_accounts, take.offer, take.token_program)
+}
+
+// Function to check the taker can pay `token_b_amount` (fees and royalty included) before any
+// tokens move, so an underfunded take fails with a clear error instead of midway through.
+pub fn check_taker_balance(take: &Take, token_b_amount: u64) -> Result<()> {
+    require!(
+        take.taker_token_account_b.amount >= token_b_amount,
+        ErrorCode::InsufficientTakerBalance
+    );
+    Ok(())
+}
+
+// Function to let takers pay for wrapped SOL in plain SOL: when Token B is the native mint and the
+// taker's Token B account holds less than `token_b_amount`, the shortfall is wrapped into it from
+// the taker's SOL. Returns how much was wrapped, which the account's loaded balance does not show.
+pub fn wrap_taker_sol(take: &Take, token_b_amount: u64) -> Result<u64> {
+    let shortfall = token_b_amount.saturating_sub(take.taker_token_account_b.amount);
+    if shortfall == 0 || !is_native_mint(&take.token_mint_b.key()) {
+        return Ok(0);
+    }
+    wrap_sol(
+        take.taker, // Taker pays the SOL.
+        take.taker_token_account_b, // Taker's wrapped SOL account.
+        shortfall, // Lamports to wrap.
+        take.system_program, // System program moving the lamports.
+        take.token_program, // Token program syncing the account.
+    )?;
+    Ok(shortfall)
+}
+
+// Function to close the taker's wrapped SOL Token B account after a `take_offer` with `unwrap` set,
+// so an account opened only to pay leaves nothing behind: what is left in it, rent included, goes
+// back to the taker as SOL. Other mints are untouched.
+pub fn unwrap_taker_token_b(accounts: &TakeOffer) -> Result<()> {
+    if !is_native_mint(&accounts.token_mint_b.key()) {
+        return Ok(());
+    }
+    unwrap_sol(
+        &accounts.taker_token_account_b, // Taker's wrapped SOL account.
+        &accounts.taker, // Taker receives the lamports.
+        &accounts.token_program, // Token program.
+    )
+}
+
+// Function to check whether the taker passed the fee-exempt list and is on it.
+pub fn is_fee_exempt(take: &Take) -> bool {
+    is_exempt_taker(&take.taker.key(), take.fee_exempt)
+}
+
+// Function to pick the protocol fee for this taker: none when they are fee-exempt, discounted when
+// they pass a governance token account holding at least the config's threshold.
+pub fn taker_fee_bps(take: &Take) -> u16 {
+    fee_bps_for_taker(
+        take.config,
+        &take.taker.key(),
+        take.governance_token_account.map(|governance_token_account| &**governance_token_account),
+        take.fee_exempt,
+    )
+}
+
+// Function to check no token account the take pays into or out of is frozen, so the failure
+// names the problem instead of surfacing from deep inside the token program.
+pub fn check_accounts_not_frozen(take: &Take) -> Result<()> {
+    let token_accounts = [
+        Some(take.taker_token_account_a),
+        Some(take.taker_token_account_b),
+        take.maker_token_account_b,
+        take.token_b_destination,
+        take.royalty_token_account,
     ];
-    let signer_seeds = [&seeds[..]];
+    check_not_frozen(token_accounts.into_iter().flatten().map(|token_account| &**token_account))
+}
+
+// Function to refuse a take touching any of `token_accounts` while it is frozen.
+pub fn check_not_frozen<'a>(token_accounts: impl IntoIterator<Item = &'a TokenAccount>) -> Result<()> {
+    for token_account in token_accounts {
+        require!(!token_account.is_frozen(), ErrorCode::FrozenTokenAccount);
+    }
+    Ok(())
+}
+
+// Function to check the maker's Token B does not land back in the account it is paid from, which
+// could happen if the offer's destination (or a reassigned maker account) is the taker's own.
+pub fn check_accounts_not_aliased(take: &Take) -> Result<()> {
+    require_keys_neq!(
+        token_b_recipient(take)?.key(),
+        take.taker_token_account_b.key(),
+        ErrorCode::AccountAliasConflict
+    );
+    Ok(())
+}
+
+// Function to compute how much Token B the taker pays for `token_a_requested_amount` of Token A.
+// The price is proportional to what is still outstanding on the offer, rounded up so that a
+// partial fill never hands out more Token A than the Token B paid for it buys. Dutch-auction
+// offers are instead priced from their auction's current amount for the whole offer at `now`,
+// rounded up the same way, and oracle-priced offers from their `price_feed`.
+// All amounts are in base units, so mints with 0 decimals simply fill in whole tokens; a fill
+// worth nothing, such as one of no Token A, fails with `FillTooSmall` instead of costing nothing.
+pub fn token_b_amount_for_fill(
+    offer: &Offer,
+    token_a_requested_amount: u64,
+    now: i64,
+    price_feed: Option<&PriceFeed>,
+) -> Result<u64> {
+    require!(
+        token_a_requested_amount <= offer.token_a_remaining,
+        ErrorCode::FillExceedsOffer
+    );
+
+    // Partial fills below the maker's minimum are refused; taking everything that is left
+    // is always allowed, even once less than the minimum remains.
+    require!(
+        token_a_requested_amount == offer.token_a_remaining
+            || token_a_requested_amount >= offer.min_fill_amount,
+        ErrorCode::BelowMinimumFill
+    );
 
-    // Instruction for transferring tokens from the vault to the taker.
-    let accounts = TransferChecked {
-        from: ctx.accounts.vault.to_account_info(), // Source vault.
-        to: ctx.accounts.taker_token_account_a.to_account_info(), // Destination account.
-        mint: ctx.accounts.token_mint_a.to_account_info(), // Mint for token A.
-        authority: ctx.accounts.offer.to_account_info(), // Authority (offer PDA).
+    let token_b_amount = match &offer.auction {
+        _ if offer.is_oracle_priced() => price_feed
+            .ok_or(ErrorCode::OracleAccountMismatch)?
+            .token_b_amount(offer, token_a_requested_amount, now)?,
+        Some(auction) => mul_div_ceil(
+            auction.current_amount(now),
+            token_a_requested_amount,
+            offer.token_a_offered_amount,
+        )?,
+        None => mul_div_ceil(
+            offer.token_b_wanted_amount,
+            token_a_requested_amount,
+            offer.token_a_remaining,
+        )?,
     };
 
-    // Creating CPI context for the transfer.
-    let cpi_content: CpiContext<TransferChecked> = CpiContext::new_with_signer(
-        ctx.accounts.token_program.to_account_info(),
-        accounts,
-        &signer_seeds,
+    // A fill that costs nothing would hand out Token A for free.
+    require!(token_b_amount > 0, ErrorCode::FillTooSmall);
+    Ok(token_b_amount)
+}
+
+// Function to record a fill on the offer, reducing what is still outstanding.
+// Called before any tokens move, so the offer never shows terms that have already been paid out.
+// Dutch-auction and oracle-priced offers keep their wanted amount, since their price comes from elsewhere.
+// A fill whose rounded-up price uses up the wanted amount while Token A is still left fails with
+// `FillRoundsToZero`, since the rest of the offer would then cost nothing.
+pub fn record_fill(offer: &mut Offer, token_a_amount: u64, token_b_amount: u64) -> Result<()> {
+    offer.token_a_remaining = offer
+        .token_a_remaining
+        .checked_sub(token_a_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if offer.auction.is_some() || offer.is_oracle_priced() {
+        return Ok(());
+    }
+    offer.token_b_wanted_amount = offer
+        .token_b_wanted_amount
+        .checked_sub(token_b_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(
+        offer.token_b_wanted_amount > 0 || offer.token_a_remaining == 0,
+        ErrorCode::FillRoundsToZero
     );
+    Ok(())
+}
 
-    // Performing the transfer.
-    transfer_checked(
-        cpi_content,
-        ctx.accounts.vault.amount, // Amount to transfer.
-        ctx.accounts.token_mint_a.decimals, // Token decimal places.
-    )?;
+// Function to take the taker's rebate off the offer's rebate reserve before it is paid out.
+pub fn record_rebate(offer: &mut Offer, taker_rebate: u64) -> Result<()> {
+    offer.rebate_reserve = offer
+        .rebate_reserve
+        .checked_sub(taker_rebate)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+// Function to announce a fill of `token_a_amount` for `token_b_amount` to indexers, once it has been
+// recorded on the offer.
+pub fn emit_offer_taken(offer: &Offer, taker: Pubkey, token_a_amount: u64, token_b_amount: u64) {
+    emit!(OfferTaken {
+        id: offer.id,
+        maker: offer.maker,
+        taker,
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_offered_amount: token_a_amount,
+        token_b_wanted_amount: token_b_amount,
+        token_a_remaining: offer.token_a_remaining,
+    });
+}
+
+// Function to compute the protocol fee on a Token B payment, rounded down.
+pub fn protocol_fee(token_b_amount: u64, fee_bps: u16) -> Result<u64> {
+    mul_div(token_b_amount, fee_bps as u64, BASIS_POINTS_DIVISOR)
+}
+
+// Function to compute the royalty owed on a Token B payment, rounded down.
+pub fn royalty_amount(offer: &Offer, token_b_amount: u64) -> Result<u64> {
+    mul_div(token_b_amount, offer.royalty_bps as u64, BASIS_POINTS_DIVISOR)
+}
+
+// Function to pick the account receiving the offer's royalty: the royalty recipient's associated
+// token account for Token B, which must be passed whenever the offer pays a royalty.
+pub fn royalty_recipient<'a, 'info>(take: &Take<'a, 'info>) -> Result<&'a InterfaceAccount<'info, TokenAccount>> {
+    let expected = get_associated_token_address_with_program_id(
+        &take.offer.royalty_recipient,
+        &take.token_mint_b.key(),
+        &take.token_program.key(),
+    );
+    take.royalty_token_account
+        .filter(|account| account.key() == expected)
+        .ok_or_else(|| ErrorCode::RoyaltyAccountMismatch.into())
+}
+
+// Function to pick the account receiving the maker's Token B: the offer's custom destination
+// when it names one, and the maker's associated token account otherwise.
+pub fn token_b_recipient<'a, 'info>(take: &Take<'a, 'info>) -> Result<&'a InterfaceAccount<'info, TokenAccount>> {
+    let recipient = match take.offer.token_b_destination {
+        Some(destination) => take
+            .token_b_destination
+            .filter(|account| account.key() == destination),
+        None => take.maker_token_account_b,
+    };
+    recipient.ok_or_else(|| ErrorCode::WantedDestinationMismatch.into())
+}
+
+// Function to transfer the wanted tokens (B) from the taker, sending the protocol fee
+// to the treasury, any royalty to its recipient, and the remainder to the maker.
+pub fn send_wanted_tokens_to_maker(take: &Take, cost: &TakeCost) -> Result<()> {
+    // Skip the fee transfer entirely when there is nothing to collect.
+    if cost.protocol_fee > 0 {
+        transfer_tokens(
+            take.taker_token_account_b, // Source account (taker's token B).
+            take.treasury_token_account_b, // Destination account (treasury's token B).
+            &cost.protocol_fee, // Fee amount.
+            take.token_mint_b, // Token mint for B.
+            take.taker, // Signer (taker).
+            take.token_program, // Token program.
+        )?;
+    }
 
-    // Instruction for closing the vault.
-    let accounts = CloseAccount {
-        account: ctx.accounts.vault.to_account_info(), // Vault to close.
-        destination: ctx.accounts.taker.to_account_info(), // Recipient of any remaining funds.
-        authority: ctx.accounts.offer.to_account_info(), // Authority (offer PDA).
+    // Likewise for the royalty, which offers without one never charge.
+    if cost.royalty > 0 {
+        transfer_tokens(
+            take.taker_token_account_b, // Source account (taker's token B).
+            royalty_recipient(take)?, // Destination account (royalty recipient's token B).
+            &cost.royalty, // Royalty amount.
+            take.token_mint_b, // Token mint for B.
+            take.taker, // Signer (taker).
+            take.token_program, // Token program.
+        )?;
+    }
+
+    transfer_tokens(
+        take.taker_token_account_b, // Source account (taker's token B).
+        token_b_recipient(take)?, // Destination account (maker's token B).
+        &cost.maker_amount, // Amount left for the maker after the fee and royalty.
+        take.token_mint_b, // Token mint for B.
+        take.taker, // Signer (taker).
+        take.token_program, // Token program.
+    )
+}
+
+// Function to pay the taker's rebate out of the offer's rebate reserve, already recorded with
+// `record_rebate`. Once the reserve has run dry or the offer is completely filled, whatever is left
+// of it goes back to the maker and its vault is closed, returning its rent to whoever paid for it.
+pub fn pay_taker_rebate<'info>(take: &Take<'_, 'info>, reserve: &mut [BasketLeg<'info>], taker_rebate: u64) -> Result<()> {
+    let Some(leg) = reserve.first_mut() else {
+        return Ok(());
     };
 
-    // Creating CPI context for closing the account.
-    let cpi_content = CpiContext::new_with_signer(
-        ctx.accounts.token_program.to_account_info(),
-        accounts,
+    // Seeds for signing as the offer PDA, which stay those of the offer's creator.
+    let creator = take.offer.creator;
+    let id_bytes = take.offer.id.to_le_bytes();
+    let seeds = &[OFFER_SEED, creator.as_ref(), &id_bytes[..], &[take.offer.bump]];
+    let signer_seeds = [&seeds[..]];
+
+    if taker_rebate > 0 {
+        withdraw_from_vault(
+            &leg.vault, // Source vault (the rebate reserve).
+            take.taker_token_account_b, // Destination account (taker's token B).
+            taker_rebate, // Rebate amount.
+            &leg.mint, // Mint for token B.
+            take.offer.to_account_info(), // Authority (offer PDA).
+            take.token_program, // Token program.
+            &signer_seeds,
+        )?;
+    }
+    if take.offer.rebate_reserve > 0 && take.offer.token_a_remaining > 0 {
+        return Ok(());
+    }
+
+    // The loaded balance predates the rebate.
+    leg.vault.reload()?;
+    release_basket(
+        reserve, // The rebate reserve, laid out as a basket leg.
+        take.offer, // Authority (offer PDA).
+        take.rent_payer.to_account_info(), // Recipient of the vault's rent.
+        take.token_program, // Token program.
         &signer_seeds,
-    );
+    )
+}
+
+// Function to charge the taker the config's flat SOL fee, paid to the treasury PDA. Fee-exempt
+// takers pay none.
+pub fn collect_flat_fee(take: &Take, flat_fee_lamports: u64) -> Result<()> {
+    if flat_fee_lamports == 0 || is_fee_exempt(take) {
+        return Ok(());
+    }
+    transfer_lamports(
+        take.taker, // Taker pays the SOL.
+        take.treasury.to_account_info(), // Treasury receives it.
+        flat_fee_lamports, // Flat fee amount.
+        take.system_program, // System program.
+    )
+}
+
+// Function to withdraw the filled tokens from the vault, and close the vault and the offer
+// once nothing is left. The fill must already be recorded with `record_fill`.
+pub fn withdraw_and_close_vault(
+    take: &mut Take,
+    token_a_amount: u64, // Amount of Token A the taker receives.
+    token_b_amount: u64, // Amount of Token B the taker paid.
+) -> Result<()> {
+    // Seeds for generating the vault's PDA, which stay those of the offer's creator.
+    let creator = take.offer.creator;
+    let id_bytes = take.offer.id.to_le_bytes();
+    let seeds = &[OFFER_SEED, creator.as_ref(), &id_bytes[..], &[take.offer.bump]];
+    let signer_seeds = [&seeds[..]];
+
+    // Mints with a transfer fee are refused at creation, so the withdrawal moves exactly
+    // `token_a_amount` and what it leaves behind is known up front.
+    let vault_leftover = take
+        .vault
+        .amount
+        .checked_sub(token_a_amount)
+        .ok_or(ErrorCode::VaultUnderfunded)?;
+
+    // Transferring the offered tokens from the vault to the taker.
+    withdraw_from_vault(
+        take.vault, // Source vault.
+        take.taker_token_account_a, // Destination account.
+        token_a_amount, // Amount to transfer.
+        take.token_mint_a, // Mint for token A.
+        take.offer.to_account_info(), // Authority (offer PDA).
+        take.token_program, // Token program.
+        &signer_seeds,
+    )?;
+
+    // Wrapped SOL is unwrapped back to plain SOL when the taker asks for it, closing their
+    // wrapped SOL account so none of its lamports are left stranded. Other mints are untouched.
+    if take.unwrap_sol && is_native_mint(&take.token_mint_a.key()) {
+        unwrap_sol(
+            take.taker_token_account_a, // Taker's wrapped SOL account.
+            take.taker, // Taker receives the lamports.
+            take.token_program, // Token program.
+        )?;
+    }
+
+    // Announce the trade to indexers.
+    let offer = &take.offer;
+    emit_offer_taken(offer, take.taker.key(), token_a_amount, token_b_amount);
+
+    // A partially filled offer stays open for other takers.
+    if offer.token_a_remaining > 0 {
+        return Ok(());
+    }
+
+    // The withdrawal left exactly `vault_leftover` in the vault, so it is checked without reloading
+    // the account. Anything left over (for example tokens sent to the vault directly) is refused
+    // here rather than surfacing as an opaque close failure; the maker can still recover it by cancelling.
+    require!(vault_leftover == 0, ErrorCode::VaultNotEmpty);
+
+    // Closing the vault, returning its rent to whoever paid for it.
+    close_vault(
+        take.vault, // Vault to close.
+        take.rent_payer.to_account_info(), // Recipient of the rent.
+        take.offer.to_account_info(), // Authority (offer PDA).
+        take.token_program, // Token program.
+        &signer_seeds,
+    )?;
+
+    // Handing over the extra basket tokens and closing their vaults the same way.
+    let legs = load_basket_legs(take.remaining_accounts, take.offer, take.token_program)?;
+    check_basket_balances(&legs, take.offer)?;
+    release_basket(
+        &legs, // Extra basket accounts.
+        take.offer, // Authority (offer PDA).
+        take.rent_payer.to_account_info(), // Recipient of the vaults' rent.
+        take.token_program, // Token program.
+        &signer_seeds,
+    )?;
 
-    // Closing the vault.
-    close_account(cpi_content)
+    // Closing the offer, returning its rent to whoever paid for it.
+    take.maker_stats.record_closed();
+    take.maker_stats.record_taken();
+    if let Some(pair_index) = take.pair_index.as_mut() {
+        pair_index.remove(&take.offer.key());
+    }
+    take.offer.close(take.rent_payer.to_account_info())
 }
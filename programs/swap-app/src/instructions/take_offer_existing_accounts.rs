@@ -0,0 +1,202 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling token operations.
+use anchor_spl::token_interface::{
+    Mint, // Represents the token mint (currency).
+    TokenAccount, // Represents a token account.
+    TokenInterface, // Represents the token program interface.
+};
+
+use super::Take; // The accounts a take works on.
+// Importing the state accounts.
+use crate::{Config, FeeExempt, MakerStats, Offer, PairIndex, PriceFeed, OFFER_SEED};
+use crate::error::ErrorCode; // Custom program errors.
+
+#[derive(Accounts)]
+pub struct TakeOfferExistingAccounts<'info> {
+    // The signer account representing the user taking the offer.
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: The maker (creator) of the offer, pinned by the offer's `has_one = maker`. It may be
+    /// owned by any program, so makers such as a DAO's treasury PDA can be paid; the Token B it
+    /// receives goes to `maker_token_account_b`, whose mint and authority are checked below.
+    /// Taking your own offer is pointless and only fakes volume, so it must differ from the taker.
+    #[account(
+        mut,
+        constraint = maker.key() != taker.key() @ ErrorCode::SelfTradeNotAllowed
+    )]
+    pub maker: UncheckedAccount<'info>,
+
+    // Token mint for the offered token (A).
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // Token mint for the wanted token (B).
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    // The taker's token account for the offered token (A). It must already exist.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
+    )]
+    pub taker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The taker's token account for the wanted token (B). Any token account of the taker works.
+    // A wrapped SOL account is topped up from the taker's SOL when short and is never closed,
+    // so a pre-wrapped SOL account keeps whatever is left wrapped.
+    // Its mint is compared explicitly so a wrong account fails with a clear error.
+    #[account(
+        mut,
+        constraint = taker_token_account_b.mint == token_mint_b.key() @ ErrorCode::WantedMintMismatch,
+        token::authority = taker,
+        token::token_program = token_program
+    )]
+    pub taker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The maker's token account for the wanted token (B), receiving Token B unless the offer
+    // names a custom destination. Any existing token account of the maker works, so makers whose
+    // custodian set up a non-associated account can be paid there; omit it otherwise.
+    #[account(
+        mut,
+        constraint = maker_token_account_b.mint == token_mint_b.key() @ ErrorCode::WantedMintMismatch,
+        token::authority = maker,
+        token::token_program = token_program
+    )]
+    pub maker_token_account_b: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The offer's custom Token B destination, required when the offer names one.
+    #[account(
+        mut,
+        constraint = token_b_destination.mint == token_mint_b.key() @ ErrorCode::WantedMintMismatch,
+        token::token_program = token_program
+    )]
+    pub token_b_destination: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The royalty recipient's associated token account for the wanted token (B), required when
+    // the offer pays a royalty; omit it otherwise.
+    #[account(
+        mut,
+        constraint = royalty_token_account.mint == token_mint_b.key() @ ErrorCode::WantedMintMismatch,
+        token::token_program = token_program
+    )]
+    pub royalty_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The global config holding the protocol fee and the emergency pause.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // The treasury PDA that owns the protocol's fee accounts and collects the flat SOL fee.
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: SystemAccount<'info>,
+
+    // The treasury's token account for the wanted token (B), receiving the protocol fee.
+    // It must already exist.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program
+    )]
+    pub treasury_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The maker's open-offer counter, decremented once the offer is completely filled.
+    #[account(
+        mut,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Box<Account<'info, MakerStats>>,
+
+    // The offer account containing details about the trade.
+    // Closed by `withdraw_and_close_vault` once the offer is completely filled.
+    // Its stored bump signs for the vault, so it is re-derived and checked before it is trusted.
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = token_mint_a,
+        has_one = token_mint_b,
+        constraint = offer.has_canonical_address(&offer.key()) @ ErrorCode::InvalidOfferBump,
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    /// CHECK: The account that paid the offer's rent, receiving it back once the offer is filled.
+    /// Pinned to the offer's recorded rent payer. It may be owned by any program, such as a maker's
+    /// PDA that paid its own rent, since it only ever receives lamports.
+    #[account(mut, address = offer.rent_recipient() @ ErrorCode::RentPayerMismatch)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    // The vault holding the tokens offered by the maker.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // The price feed named by an oracle-priced offer. Only needed when the offer has an oracle.
+    #[account(address = offer.oracle @ ErrorCode::OracleAccountMismatch)]
+    pub price_feed: Option<Box<Account<'info, PriceFeed>>>,
+
+    // The taker's account of the config's governance token, earning them the fee discount when it
+    // holds enough. It may live under either token program. Omit it to pay the normal fee.
+    #[account(
+        constraint = governance_token_account.mint == config.governance_mint @ ErrorCode::GovernanceMintMismatch,
+        token::authority = taker
+    )]
+    pub governance_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The admin's list of fee-exempt takers. A taker on it passes it to pay no protocol fee and no
+    // flat fee; omit it to pay both as usual.
+    #[account(seeds = [b"fee_exempt"], bump = fee_exempt.bump)]
+    pub fee_exempt: Option<Box<Account<'info, FeeExempt>>>,
+
+    // The pair's offer index, if one has been created. The offer is removed from it once filled.
+    #[account(
+        mut,
+        seeds = [b"pair", token_mint_a.key().as_ref(), token_mint_b.key().as_ref()],
+        bump = pair_index.bump
+    )]
+    pub pair_index: Option<Box<Account<'info, PairIndex>>>,
+
+    // Required Solana programs. No account is created, so the Associated Token program is not
+    // needed; the System program only collects the flat SOL fee.
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> TakeOfferExistingAccounts<'info> {
+    // Borrows the accounts a take works on, so the fill runs exactly as in `take_offer`.
+    pub fn as_take<'a>(&'a mut self, remaining_accounts: &'info [AccountInfo<'info>]) -> Take<'a, 'info> {
+        Take {
+            taker: &self.taker,
+            maker: &self.maker,
+            token_mint_a: &self.token_mint_a,
+            token_mint_b: &self.token_mint_b,
+            taker_token_account_a: &self.taker_token_account_a,
+            taker_token_account_b: &self.taker_token_account_b,
+            maker_token_account_b: self.maker_token_account_b.as_deref(),
+            token_b_destination: self.token_b_destination.as_deref(),
+            royalty_token_account: self.royalty_token_account.as_deref(),
+            config: &self.config,
+            treasury: &self.treasury,
+            treasury_token_account_b: &self.treasury_token_account_b,
+            maker_stats: &mut self.maker_stats,
+            offer: &mut self.offer,
+            rent_payer: &self.rent_payer,
+            vault: &mut self.vault,
+            price_feed: self.price_feed.as_deref().map(|price_feed| &**price_feed),
+            governance_token_account: self.governance_token_account.as_deref(),
+            fee_exempt: self.fee_exempt.as_deref().map(|fee_exempt| &**fee_exempt),
+            pair_index: self.pair_index.as_deref_mut(),
+            system_program: &self.system_program,
+            token_program: &self.token_program,
+            remaining_accounts,
+            unwrap_sol: true, // Wrapped SOL is always unwrapped here.
+            revealed: false, // A direct take, not a revealed commitment.
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+use super::{
+    check_direct_take, check_not_frozen, check_take, check_vault_balance, close_vault,
+    emit_offer_taken, fee_bps_for_taker, is_exempt_taker, record_fill, take_cost,
+    transfer_lamports, withdraw_from_vault,
+}; // Checks, pricing and transfers shared with `take_offer`.
+use crate::error::ErrorCode; // Custom program errors.
+// Importing the state accounts.
+use crate::{Config, FeeExempt, MakerStats, Offer, PairIndex, OFFER_SEED};
+
+#[derive(Accounts)]
+pub struct TakeOfferWithSol<'info> {
+    // The signer account representing the user taking the offer, paying in SOL.
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: The maker (creator) of the offer, receiving the SOL in their wallet, pinned by the
+    /// offer's `has_one = maker`. It may be owned by any program, since the system program can pay
+    /// lamports into any account. Taking your own offer is pointless and only fakes volume, so it
+    /// must differ from the taker.
+    #[account(
+        mut,
+        constraint = maker.key() != taker.key() @ ErrorCode::SelfTradeNotAllowed
+    )]
+    pub maker: UncheckedAccount<'info>,
+
+    // Token mint for the offered token (A).
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // The taker's token account for the offered token (A).
+    // It will be created if it doesn't exist.
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
+    )]
+    pub taker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The global config holding the protocol fee and the emergency pause.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // The treasury PDA, collecting the protocol fee and the flat fee in SOL.
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: SystemAccount<'info>,
+
+    // The maker's open-offer counter, decremented once the offer is completely filled.
+    #[account(
+        mut,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Box<Account<'info, MakerStats>>,
+
+    // The offer being taken, which must have been made with `make_offer_for_sol`.
+    // Closed once it is completely filled.
+    // Its stored bump signs for the vault, so it is re-derived and checked before it is trusted.
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = token_mint_a,
+        constraint = offer.wants_sol() @ ErrorCode::OfferNotForSol,
+        constraint = offer.has_canonical_address(&offer.key()) @ ErrorCode::InvalidOfferBump,
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Box<Account<'info, Offer>>,
+
+    /// CHECK: The account that paid the offer's rent, receiving it back once the offer is filled.
+    /// Pinned to the offer's recorded rent payer. It may be owned by any program, such as a maker's
+    /// PDA that paid its own rent, since it only ever receives lamports.
+    #[account(mut, address = offer.rent_recipient() @ ErrorCode::RentPayerMismatch)]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    // The vault holding the tokens offered by the maker.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // The pair's offer index, if one has been created. The offer is removed from it once filled.
+    #[account(
+        mut,
+        seeds = [b"pair", offer.token_mint_a.as_ref(), offer.token_mint_b.as_ref()],
+        bump = pair_index.bump
+    )]
+    pub pair_index: Option<Box<Account<'info, PairIndex>>>,
+
+    // The taker's account of the config's governance token, earning them the fee discount when it
+    // holds enough. Omit it to pay the normal fee.
+    #[account(
+        constraint = governance_token_account.mint == config.governance_mint @ ErrorCode::GovernanceMintMismatch,
+        token::authority = taker
+    )]
+    pub governance_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The admin's list of fee-exempt takers. A taker on it passes it to pay no protocol fee and no
+    // flat fee; omit it to pay both as usual.
+    #[account(seeds = [b"fee_exempt"], bump = fee_exempt.bump)]
+    pub fee_exempt: Option<Box<Account<'info, FeeExempt>>>,
+
+    // Required Solana programs for system operations.
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+// Function to fill `token_a_requested_amount` of a SOL offer for at most `max_lamports`, paying the
+// maker and the treasury through the system program, then closing the vault and the offer once it is
+// completely filled. The price and fee split are those of `take_offer`, counted in lamports.
+pub fn fill_with_sol(ctx: Context<TakeOfferWithSol>, token_a_requested_amount: u64, max_lamports: u64) -> Result<()> {
+    // Step 0: The checks `take_offer` applies before pricing the fill. The SOL legs move through
+    // the system program, so the taker's Token A account is the only token account paid into.
+    let accounts = ctx.accounts;
+    let now = Clock::get()?.unix_timestamp;
+    check_take(&accounts.config, &accounts.offer, &accounts.taker.key(), token_a_requested_amount, now)?;
+    check_direct_take(&accounts.offer)?;
+    check_not_frozen([&**accounts.taker_token_account_a])?;
+    check_vault_balance(&accounts.vault, &accounts.offer)?;
+
+    // Step 1: Price the fill in lamports and hold it to the taker's limit and balance.
+    let taker = accounts.taker.key();
+    let fee_exempt = accounts.fee_exempt.as_deref().map(|fee_exempt| &**fee_exempt);
+    let fee_bps = fee_bps_for_taker(
+        &accounts.config,
+        &taker,
+        accounts.governance_token_account.as_deref().map(|governance_token_account| &**governance_token_account),
+        fee_exempt,
+    );
+    let cost = take_cost(&accounts.offer, &accounts.config, fee_bps, token_a_requested_amount, now, None)?;
+    require!(cost.token_b_amount <= max_lamports, ErrorCode::SlippageExceeded);
+    let flat_fee_lamports = if is_exempt_taker(&taker, fee_exempt) { 0 } else { cost.flat_fee_lamports };
+    let treasury_lamports = cost
+        .protocol_fee
+        .checked_add(flat_fee_lamports)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(
+        accounts.taker.lamports() >= cost.token_b_amount.saturating_add(flat_fee_lamports),
+        ErrorCode::InsufficientTakerBalance
+    );
+    check_rent_exempt_after(&accounts.treasury, treasury_lamports)?;
+    check_rent_exempt_after(&accounts.maker, cost.maker_amount)?;
+
+    // Step 2: Record the fill before any lamports or tokens move.
+    record_fill(&mut accounts.offer, token_a_requested_amount, cost.token_b_amount)?;
+
+    // Step 3: Pay the treasury its fees and the maker the rest, in SOL.
+    if treasury_lamports > 0 {
+        transfer_lamports(
+            &accounts.taker, // Taker pays the SOL.
+            accounts.treasury.to_account_info(), // Treasury receives the fees.
+            treasury_lamports, // Protocol fee plus flat fee.
+            &accounts.system_program, // System program.
+        )?;
+    }
+    transfer_lamports(
+        &accounts.taker, // Taker pays the SOL.
+        accounts.maker.to_account_info(), // Maker receives it in their wallet.
+        cost.maker_amount, // Amount left for the maker after the fee.
+        &accounts.system_program, // System program.
+    )?;
+
+    // Step 4: Withdraw the requested Token A from the vault to the taker. Mints with a transfer fee
+    // are refused at creation, so the withdrawal leaves exactly `vault_leftover` behind, as in `take_offer`.
+    let vault_leftover = accounts
+        .vault
+        .amount
+        .checked_sub(token_a_requested_amount)
+        .ok_or(ErrorCode::VaultUnderfunded)?;
+    let creator = accounts.offer.creator;
+    let id_bytes = accounts.offer.id.to_le_bytes();
+    let seeds = &[OFFER_SEED, creator.as_ref(), &id_bytes[..], &[accounts.offer.bump]];
+    let signer_seeds = [&seeds[..]];
+    withdraw_from_vault(
+        &accounts.vault, // Source vault.
+        &accounts.taker_token_account_a, // Destination account.
+        token_a_requested_amount, // Amount to transfer.
+        &accounts.token_mint_a, // Mint for token A.
+        accounts.offer.to_account_info(), // Authority (offer PDA).
+        &accounts.token_program, // Token program.
+        &signer_seeds,
+    )?;
+
+    // Announce the trade to indexers, with the lamports paid as the Token B amount.
+    let offer = &accounts.offer;
+    emit_offer_taken(offer, accounts.taker.key(), token_a_requested_amount, cost.token_b_amount);
+
+    // A partially filled offer stays open for other takers.
+    if offer.token_a_remaining > 0 {
+        return Ok(());
+    }
+
+    // Anything sent to the vault directly is refused here without reloading it; the maker can still
+    // recover it by cancelling.
+    require!(vault_leftover == 0, ErrorCode::VaultNotEmpty);
+
+    // Closing the vault and the offer, returning their rent to whoever paid for them.
+    close_vault(
+        &accounts.vault, // Vault to close.
+        accounts.rent_payer.to_account_info(), // Recipient of the rent.
+        accounts.offer.to_account_info(), // Authority (offer PDA).
+        &accounts.token_program, // Token program.
+        &signer_seeds,
+    )?;
+    accounts.maker_stats.record_closed();
+    accounts.maker_stats.record_taken();
+    if let Some(pair_index) = accounts.pair_index.as_mut() {
+        pair_index.remove(&accounts.offer.key());
+    }
+    accounts.offer.close(accounts.rent_payer.to_account_info())
+}
+
+// Function to check a payment of `lamports` leaves `recipient` rent-exempt, since the system program
+// refuses to leave a wallet holding some lamports but too few to be rent-exempt. A wallet that holds
+// too little would otherwise fail the take from inside the system program. Nothing paid, nothing checked.
+pub fn check_rent_exempt_after(recipient: &AccountInfo, lamports: u64) -> Result<()> {
+    if lamports == 0 {
+        return Ok(());
+    }
+    let balance = recipient.lamports().checked_add(lamports).ok_or(ErrorCode::MathOverflow)?;
+    require!(
+        Rent::get()?.is_exempt(balance, recipient.data_len()),
+        ErrorCode::RecipientNotRentExempt
+    );
+    Ok(())
+}
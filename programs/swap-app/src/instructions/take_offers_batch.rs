@@ -0,0 +1,328 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
+    token_interface::{
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+use super::{
+    check_direct_take, close_vault, fee_bps_for_taker, is_exempt_taker, is_native_mint, take_cost,
+    transfer_lamports, transfer_tokens, unwrap_sol, withdraw_from_vault,
+}; // Utility functions for pricing and moving tokens.
+use crate::error::ErrorCode; // Custom program errors.
+// Importing the state accounts and the event emitted when an offer is taken.
+use crate::{Config, FeeExempt, MakerStats, Offer, OfferTaken, MAX_BATCH_TAKE, OFFER_SEED};
+
+// Each offer in the batch is passed through `remaining_accounts` as five accounts, in order:
+// the offer, its vault, its maker, the account receiving the maker's Token B, and the maker's stats.
+pub const BATCH_TAKE_ACCOUNTS: usize = 5;
+
+#[derive(Accounts)]
+pub struct TakeOffersBatch<'info> {
+    // The signer account representing the user taking the offers.
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    // Token mint for the offered token (A), shared by every offer in the batch.
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // Token mint for the wanted token (B), shared by every offer in the batch.
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    // The taker's token account for the offered token (A), receiving every fill.
+    // It will be created if it doesn't exist.
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
+    )]
+    pub taker_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The taker's token account for the wanted token (B), paying every fill.
+    #[account(
+        mut,
+        constraint = taker_token_account_b.mint == token_mint_b.key() @ ErrorCode::WantedMintMismatch,
+        token::authority = taker,
+        token::token_program = token_program
+    )]
+    pub taker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The global config holding the protocol fee and the emergency pause.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // The treasury PDA that owns the protocol's fee accounts and collects the flat SOL fee.
+    #[account(mut, seeds = [b"treasury"], bump)]
+    pub treasury: SystemAccount<'info>,
+
+    // The treasury's token account for the wanted token (B), receiving the fees of the whole batch.
+    // It will be created if it doesn't exist.
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program
+    )]
+    pub treasury_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The taker's account of the config's governance token, earning them the fee discount on every
+    // offer of the batch when it holds enough. Omit it to pay the normal fee.
+    #[account(
+        constraint = governance_token_account.mint == config.governance_mint @ ErrorCode::GovernanceMintMismatch,
+        token::authority = taker
+    )]
+    pub governance_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // The admin's list of fee-exempt takers. A taker on it passes it to pay no protocol fee and no
+    // flat fee on any offer of the batch.
+    #[account(seeds = [b"fee_exempt"], bump = fee_exempt.bump)]
+    pub fee_exempt: Option<Box<Account<'info, FeeExempt>>>,
+
+    // Required Solana programs for system operations.
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+/// What a batched take did, returned so clients can see which offers were skipped.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BatchTakeSummary {
+    pub offers_taken: u8,
+    pub offers_skipped: u8,
+    pub token_a_received: u64,
+    pub token_b_paid: u64,
+}
+
+// What a single filled offer of the batch moved.
+struct BatchFill {
+    token_a_amount: u64,
+    token_b_amount: u64,
+    fee: u64,
+}
+
+// Function to fully take every offer passed in `remaining_accounts`, skipping expired ones, and
+// pay the protocol fees of the whole batch to the treasury in a single transfer.
+pub fn take_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, TakeOffersBatch<'info>>,
+    max_token_b_amount: u64,
+) -> Result<BatchTakeSummary> {
+    require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+    let remaining_accounts = ctx.remaining_accounts;
+    require!(
+        !remaining_accounts.is_empty() && remaining_accounts.len().is_multiple_of(BATCH_TAKE_ACCOUNTS),
+        ErrorCode::BatchAccountsMismatch
+    );
+    require!(
+        remaining_accounts.len() / BATCH_TAKE_ACCOUNTS <= MAX_BATCH_TAKE,
+        ErrorCode::BatchTooLarge
+    );
+    require!(
+        !ctx.accounts.taker_token_account_a.is_frozen() && !ctx.accounts.taker_token_account_b.is_frozen(),
+        ErrorCode::FrozenTokenAccount
+    );
+
+    // Every offer of the batch is charged the same fee tier, the one `take_offer` would charge.
+    let taker = ctx.accounts.taker.key();
+    let fee_exempt = ctx.accounts.fee_exempt.as_deref().map(|fee_exempt| &**fee_exempt);
+    let fee_bps = fee_bps_for_taker(
+        &ctx.accounts.config,
+        &taker,
+        ctx.accounts.governance_token_account.as_deref().map(|governance_token_account| &**governance_token_account),
+        fee_exempt,
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    let mut summary = BatchTakeSummary {
+        offers_taken: 0,
+        offers_skipped: 0,
+        token_a_received: 0,
+        token_b_paid: 0,
+    };
+    let mut total_fee: u64 = 0;
+    for group in remaining_accounts.chunks(BATCH_TAKE_ACCOUNTS) {
+        let Some(fill) = take_offer_in_batch(ctx.accounts, group, fee_bps, now)? else {
+            summary.offers_skipped += 1;
+            continue;
+        };
+        summary.offers_taken += 1;
+        summary.token_a_received = summary
+            .token_a_received
+            .checked_add(fill.token_a_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        summary.token_b_paid = summary
+            .token_b_paid
+            .checked_add(fill.token_b_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        total_fee = total_fee.checked_add(fill.fee).ok_or(ErrorCode::MathOverflow)?;
+
+        // The taker's limit covers the whole batch, not each offer.
+        require!(summary.token_b_paid <= max_token_b_amount, ErrorCode::SlippageExceeded);
+    }
+
+    // Skip the fee transfer entirely when there is nothing to collect.
+    if total_fee > 0 {
+        transfer_tokens(
+            &ctx.accounts.taker_token_account_b, // Source account (taker's token B).
+            &ctx.accounts.treasury_token_account_b, // Destination account (treasury's token B).
+            &total_fee, // Fees of every offer taken.
+            &ctx.accounts.token_mint_b, // Token mint for B.
+            &ctx.accounts.taker, // Signer (taker).
+            &ctx.accounts.token_program, // Token program.
+        )?;
+    }
+
+    // The flat SOL fee is charged for every offer taken, also in a single transfer.
+    let flat_fee_lamports = ctx.accounts.config.flat_fee_lamports
+        .checked_mul(summary.offers_taken as u64)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if flat_fee_lamports > 0 && !is_exempt_taker(&taker, fee_exempt) {
+        transfer_lamports(
+            &ctx.accounts.taker, // Taker pays the SOL.
+            ctx.accounts.treasury.to_account_info(), // Treasury receives it.
+            flat_fee_lamports, // Flat fees of every offer taken.
+            &ctx.accounts.system_program, // System program.
+        )?;
+    }
+
+    // Wrapped SOL is unwrapped back to plain SOL for the taker.
+    if summary.offers_taken > 0 && is_native_mint(&ctx.accounts.token_mint_a.key()) {
+        unwrap_sol(
+            &ctx.accounts.taker_token_account_a, // Taker's wrapped SOL account.
+            &ctx.accounts.taker, // Taker receives the lamports.
+            &ctx.accounts.token_program, // Token program.
+        )?;
+    }
+    Ok(summary)
+}
+
+// Function to fully take a single offer of the batch, applying the same checks as the `TakeOffer`
+// account constraints and handler. Returns `None` for an expired offer, leaving it untouched.
+fn take_offer_in_batch<'info>(
+    accounts: &TakeOffersBatch<'info>, // Named accounts of the batch.
+    group: &'info [AccountInfo<'info>], // The offer's five remaining accounts.
+    fee_bps: u16, // Protocol fee charged to the taker.
+    now: i64, // Current validator time.
+) -> Result<Option<BatchFill>> {
+    let token_program_id = accounts.token_program.key();
+    let token_mint_a = accounts.token_mint_a.key();
+    let token_mint_b = accounts.token_mint_b.key();
+
+    // The offer must be for this pair, at its canonical PDA, hold nothing outside its Token A vault
+    // (no basket and no rebate reserve), and pay no royalty or rent to anyone but the maker, since
+    // the batch has no accounts for them.
+    let offer = Account::<Offer>::try_from(&group[0])?;
+    require_keys_eq!(offer.token_mint_a, token_mint_a, ErrorCode::BatchAccountsMismatch);
+    require_keys_eq!(offer.token_mint_b, token_mint_b, ErrorCode::BatchAccountsMismatch);
+    require!(offer.basket.is_empty(), ErrorCode::BatchAccountsMismatch);
+    require!(offer.rebate_reserve == 0, ErrorCode::BatchAccountsMismatch);
+    require!(offer.royalty_bps == 0, ErrorCode::BatchAccountsMismatch);
+    require!(!offer.is_oracle_priced(), ErrorCode::BatchAccountsMismatch);
+    require_keys_eq!(offer.rent_recipient(), offer.maker, ErrorCode::BatchAccountsMismatch);
+    require!(offer.has_canonical_address(&offer.key()), ErrorCode::InvalidOfferBump);
+    let id_bytes = offer.id.to_le_bytes();
+    let seeds = &[OFFER_SEED, offer.creator.as_ref(), &id_bytes[..], &[offer.bump]];
+    let signer_seeds = [&seeds[..]];
+
+    // An expired offer is skipped rather than aborting the rest of the batch.
+    if offer.is_expired(now) {
+        return Ok(None);
+    }
+    require!(offer.can_be_taken_by(&accounts.taker.key()), ErrorCode::UnauthorizedTaker);
+    check_direct_take(&offer)?;
+
+    // The vault, maker, Token B recipient, and maker stats must match the offer.
+    require_keys_eq!(
+        group[1].key(),
+        get_associated_token_address_with_program_id(&offer.key(), &token_mint_a, &token_program_id),
+        ErrorCode::BatchAccountsMismatch
+    );
+    require_keys_eq!(group[2].key(), offer.maker, ErrorCode::BatchAccountsMismatch);
+    require_keys_neq!(offer.maker, accounts.taker.key(), ErrorCode::SelfTradeNotAllowed);
+    let expected_recipient = offer.token_b_destination.unwrap_or_else(|| {
+        get_associated_token_address_with_program_id(&offer.maker, &token_mint_b, &token_program_id)
+    });
+    require_keys_eq!(group[3].key(), expected_recipient, ErrorCode::WantedDestinationMismatch);
+    let mut maker_stats = Account::<MakerStats>::try_from(&group[4])?;
+    let maker_stats_address = Pubkey::create_program_address(
+        &[b"maker", offer.maker.as_ref(), &[maker_stats.bump]],
+        &crate::ID,
+    )
+    .map_err(|_| ErrorCode::BatchAccountsMismatch)?;
+    require_keys_eq!(maker_stats_address, group[4].key(), ErrorCode::BatchAccountsMismatch);
+    let vault = InterfaceAccount::<TokenAccount>::try_from(&group[1])?;
+    let maker = &group[2];
+    let recipient = InterfaceAccount::<TokenAccount>::try_from(&group[3])?;
+    require!(!recipient.is_frozen(), ErrorCode::FrozenTokenAccount);
+    require_keys_neq!(
+        recipient.key(),
+        accounts.taker_token_account_b.key(),
+        ErrorCode::AccountAliasConflict
+    );
+
+    // The whole remainder is taken, so the vault must hold exactly that and nothing more.
+    let token_a_amount = offer.token_a_remaining;
+    require!(vault.amount >= token_a_amount, ErrorCode::VaultUnderfunded);
+    require!(vault.amount == token_a_amount, ErrorCode::VaultNotEmpty);
+    let cost = take_cost(&offer, &accounts.config, fee_bps, token_a_amount, now, None)?;
+
+    // Paying the maker their share; the fee is collected once for the whole batch.
+    transfer_tokens(
+        &accounts.taker_token_account_b, // Source account (taker's token B).
+        &recipient, // Destination account (maker's token B).
+        &cost.maker_amount, // Amount left for the maker after the fee.
+        &accounts.token_mint_b, // Token mint for B.
+        &accounts.taker, // Signer (taker).
+        &accounts.token_program, // Token program.
+    )?;
+
+    // Transferring the offered tokens from the vault to the taker.
+    withdraw_from_vault(
+        &vault, // Source vault.
+        &accounts.taker_token_account_a, // Destination account.
+        token_a_amount, // Amount to transfer.
+        &accounts.token_mint_a, // Mint for token A.
+        offer.to_account_info(), // Authority (offer PDA).
+        &accounts.token_program, // Token program.
+        &signer_seeds,
+    )?;
+
+    // Closing the vault, returning its rent to the maker who paid for it.
+    close_vault(
+        &vault, // Vault to close.
+        maker.clone(), // Recipient of the rent.
+        offer.to_account_info(), // Authority (offer PDA).
+        &accounts.token_program, // Token program.
+        &signer_seeds,
+    )?;
+
+    // Announce the trade to indexers.
+    emit!(OfferTaken {
+        id: offer.id,
+        maker: offer.maker,
+        taker: accounts.taker.key(),
+        token_mint_a: offer.token_mint_a,
+        token_mint_b: offer.token_mint_b,
+        token_a_offered_amount: token_a_amount,
+        token_b_wanted_amount: cost.token_b_amount,
+        token_a_remaining: 0,
+    });
+
+    // Closing the offer, returning its rent to the maker. The stats are written back right away,
+    // so a later offer of the same maker in this batch loads the updated count.
+    maker_stats.record_closed();
+    maker_stats.record_taken();
+    maker_stats.exit(&crate::ID)?;
+    offer.close(maker.clone())?;
+    Ok(Some(BatchFill {
+        token_a_amount,
+        token_b_amount: cost.token_b_amount,
+        fee: cost.protocol_fee,
+    }))
+}
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token operations.
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        Mint, // Represents the token mint (currency).
+        TokenAccount, // Represents a token account.
+        TokenInterface, // Represents the token program interface.
+    },
+};
+
+// Utility functions for moving tokens into the vault and checking they arrived.
+use super::{check_deposit, is_native_mint, transfer_tokens, wrap_sol};
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{Config, Offer, OFFER_SEED}; // Importing the state accounts.
+
+#[derive(Accounts)]
+pub struct TopUpOffer<'info> {
+    // The maker (creator) of the offer. Only the maker can add to it.
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    // Token mint for the offered token (A).
+    #[account(mint::token_program = token_program)]
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    // The maker's token account for the offered token (A), funding the top-up.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    // Global config, checked for the emergency pause and the offer amount limits.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // The offer account being topped up.
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = token_mint_a,
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // The vault holding the tokens offered by the maker.
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    // Required Solana programs for system operations.
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+// Function to move extra Token A into the vault and grow the offer by the same amount.
+// The wanted Token B is left as it is; makers re-price with `update_offer` if they want to.
+pub fn add_to_vault(ctx: Context<TopUpOffer>, token_a_amount: u64) -> Result<()> {
+    check_top_up(ctx.accounts, token_a_amount)?;
+    deposit_top_up(ctx.accounts, token_a_amount)
+}
+
+// Function to check the offer can be topped up with `token_a_amount`, staying within the config's
+// offer amount limits. Shared with `rebalance_offer`, which tops up the same way.
+pub fn check_top_up(accounts: &TopUpOffer, token_a_amount: u64) -> Result<()> {
+    require!(!accounts.config.paused, ErrorCode::ProgramPaused);
+    require!(token_a_amount > 0, ErrorCode::ZeroAmount);
+    let token_a_remaining = accounts
+        .offer
+        .token_a_remaining
+        .checked_add(token_a_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(
+        accounts.config.accepts_offer_amount(token_a_remaining),
+        ErrorCode::OfferAmountOutOfRange
+    );
+    Ok(())
+}
+
+// Function to move the top-up into the vault and record it on the offer, once checked with `check_top_up`.
+// The vault must end up holding exactly `token_a_amount` more, as `make_offer` checks its deposit.
+// The offer's `created_at` restarts, since the rebate `cancel_offer` pays is earned on everything left.
+pub fn deposit_top_up(accounts: &mut TopUpOffer, token_a_amount: u64) -> Result<()> {
+    let expected_vault_amount = accounts
+        .vault
+        .amount
+        .checked_add(token_a_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // Wrapped SOL offers are topped up straight from the maker's SOL, as in `make_offer`.
+    if is_native_mint(&accounts.token_mint_a.key()) {
+        wrap_sol(
+            &accounts.maker, // Maker pays the SOL.
+            &accounts.vault, // Vault holds it as wrapped SOL.
+            token_a_amount, // Lamports to wrap.
+            &accounts.system_program, // System program moving the lamports.
+            &accounts.token_program, // Token program syncing the vault.
+        )?;
+    } else {
+        transfer_tokens(
+            &accounts.maker_token_account_a, // Source account: Maker's token account.
+            &accounts.vault, // Destination account: Vault.
+            &token_a_amount, // Amount to transfer.
+            &accounts.token_mint_a, // Mint associated with Token A.
+            &accounts.maker, // Authority over the source account.
+            &accounts.token_program, // Token program handling the transfer.
+        )?;
+    }
+    check_deposit(&mut accounts.vault, expected_vault_amount)?;
+
+    // Recording the larger offer.
+    let offer = &mut accounts.offer;
+    offer.token_a_offered_amount = offer
+        .token_a_offered_amount
+        .checked_add(token_a_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    offer.token_a_remaining = offer
+        .token_a_remaining
+        .checked_add(token_a_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // Restarting the offer's age, so Token A added now never earns a maker rebate for time it was not open.
+    offer.created_at = Clock::get()?.unix_timestamp;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+// Importing the state accounts and the event emitted on transfer.
+use crate::{Config, MakerStats, Offer, OfferOwnershipTransferred, ANCHOR_DISCRIMINATOR, OFFER_SEED};
+
+#[derive(Accounts)]
+#[instruction(new_maker: Pubkey)]
+pub struct TransferOfferOwnership<'info> {
+    // The current maker of the offer. Only they can hand it over, and they pay for the new
+    // maker's stats account if it does not exist yet.
+    #[account(
+        mut,
+        constraint = maker.key() != new_maker @ ErrorCode::OwnershipTransferToSelf
+    )]
+    pub maker: Signer<'info>,
+
+    // Global config holding the open-offer cap the new maker is held to.
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    // The current maker's open-offer counter, giving up the offer.
+    #[account(
+        mut,
+        seeds = [b"maker", maker.key().as_ref()],
+        bump = maker_stats.bump
+    )]
+    pub maker_stats: Account<'info, MakerStats>,
+
+    // The new maker's open-offer counter, taking the offer on. Created if missing.
+    #[account(
+        init_if_needed,
+        payer = maker,
+        space = ANCHOR_DISCRIMINATOR + MakerStats::INIT_SPACE,
+        seeds = [b"maker", new_maker.as_ref()],
+        bump
+    )]
+    pub new_maker_stats: Account<'info, MakerStats>,
+
+    // The offer account changing hands.
+    #[account(
+        mut,
+        has_one = maker,
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    // Required system program for creating the new maker's stats account.
+    pub system_program: Program<'info, System>,
+}
+
+// Function to make `new_maker` the offer's maker.
+// Every instruction checks `has_one = maker`, so the new maker takes over cancelling, updating,
+// and the Token B paid out on takes, while the old maker loses them. Rent the old maker paid is
+// handed over with the offer; rent paid by a sponsor still goes back to the sponsor.
+// The offer moves between the two makers' open-offer counts, and the new maker must be under the cap.
+pub fn reassign_maker(ctx: Context<TransferOfferOwnership>, new_maker: Pubkey) -> Result<()> {
+    let new_maker_stats = &mut ctx.accounts.new_maker_stats;
+    new_maker_stats.maker = new_maker;
+    new_maker_stats.bump = ctx.bumps.new_maker_stats;
+    new_maker_stats.record_opened(ctx.accounts.config.max_open_offers)?;
+    ctx.accounts.maker_stats.record_closed();
+
+    let offer = &mut ctx.accounts.offer;
+    if offer.rent_recipient() == offer.maker {
+        offer.rent_payer = new_maker;
+    }
+    offer.maker = new_maker;
+
+    // Announce the transfer to indexers.
+    emit!(OfferOwnershipTransferred {
+        id: offer.id,
+        offer: offer.key(),
+        previous_maker: ctx.accounts.maker.key(),
+        new_maker,
+    });
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{Allowlist, Config}; // Global settings account and the mint allowlist.
+
+#[derive(Accounts)]
+pub struct UpdateAllowlist<'info> {
+    // The admin recorded in the config. Nobody else may change the allowlist.
+    pub admin: Signer<'info>,
+
+    // The global config naming the admin.
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // The allowlist PDA being updated.
+    #[account(mut, seeds = [b"allowlist"], bump = allowlist.bump)]
+    pub allowlist: Account<'info, Allowlist>,
+}
+
+// Function to permit a mint in new offers while the allowlist is enabled.
+pub fn allow_mint(ctx: Context<UpdateAllowlist>, mint: Pubkey) -> Result<()> {
+    ctx.accounts.allowlist.add(mint)
+}
+
+// Function to stop permitting a mint in new offers while the allowlist is enabled.
+pub fn disallow_mint(ctx: Context<UpdateAllowlist>, mint: Pubkey) -> Result<()> {
+    ctx.accounts.allowlist.remove(&mint);
+    Ok(())
+}
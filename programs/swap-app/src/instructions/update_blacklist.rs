@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{Blacklist, Config}; // Global settings account and the mint blacklist.
+
+#[derive(Accounts)]
+pub struct UpdateBlacklist<'info> {
+    // The admin recorded in the config. Nobody else may change the blacklist.
+    pub admin: Signer<'info>,
+
+    // The global config naming the admin.
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // The blacklist PDA being updated.
+    #[account(mut, seeds = [b"blacklist"], bump = blacklist.bump)]
+    pub blacklist: Account<'info, Blacklist>,
+}
+
+// Function to block a mint from new offers.
+pub fn block_mint(ctx: Context<UpdateBlacklist>, mint: Pubkey) -> Result<()> {
+    ctx.accounts.blacklist.add(mint)
+}
+
+// Function to let a blocked mint be offered again.
+pub fn unblock_mint(ctx: Context<UpdateBlacklist>, mint: Pubkey) -> Result<()> {
+    ctx.accounts.blacklist.remove(&mint);
+    Ok(())
+}
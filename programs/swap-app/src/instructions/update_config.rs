@@ -0,0 +1,91 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{Config, BASIS_POINTS_DIVISOR}; // Global settings account and the fee divisor.
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    // The admin recorded in the config. Nobody else may change the settings.
+    pub admin: Signer<'info>,
+
+    // The global config PDA being updated.
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// Function to overwrite the adjustable global settings.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_config(
+    ctx: Context<UpdateConfig>,
+    fee_bps: u16, // New protocol fee, in basis points.
+    paused: bool, // New pause state.
+    rebate_bps: u16, // New maker rebate, in basis points.
+    min_rebate_duration: i64, // Seconds an offer must stay open to earn the rebate.
+    flat_fee_lamports: u64, // New flat SOL fee per take.
+    max_open_offers: u32, // New cap on each maker's open offers.
+    allowlist_enabled: bool, // Whether new offers are limited to allowlisted mints.
+) -> Result<()> {
+    // A fee above 100% would take more than the taker pays, and a rebate above 100%
+    // would pay out more than the offer held.
+    require!(fee_bps as u64 <= BASIS_POINTS_DIVISOR, ErrorCode::InvalidFeeBps);
+    require!(rebate_bps as u64 <= BASIS_POINTS_DIVISOR, ErrorCode::InvalidFeeBps);
+
+    let config = &mut ctx.accounts.config;
+    config.fee_bps = fee_bps; // New protocol fee.
+    config.paused = paused; // New pause state.
+    config.rebate_bps = rebate_bps; // New maker rebate.
+    config.min_rebate_duration = min_rebate_duration; // New rebate duration.
+    config.flat_fee_lamports = flat_fee_lamports; // New flat SOL fee.
+    config.max_open_offers = max_open_offers; // New open-offer cap.
+    config.allowlist_enabled = allowlist_enabled; // New allowlist mode.
+    Ok(())
+}
+
+// Function to set the fee discount for takers holding the governance token.
+pub fn apply_fee_discount(
+    ctx: Context<UpdateConfig>,
+    governance_mint: Pubkey, // Mint earning the discount, or the default pubkey to turn it off.
+    governance_threshold: u64, // Balance a taker must hold to earn it.
+    discounted_fee_bps: u16, // Fee charged to those takers, in basis points.
+) -> Result<()> {
+    require!(discounted_fee_bps as u64 <= BASIS_POINTS_DIVISOR, ErrorCode::InvalidFeeBps);
+
+    let config = &mut ctx.accounts.config;
+    config.governance_mint = governance_mint; // New governance mint.
+    config.governance_threshold = governance_threshold; // New balance threshold.
+    config.discounted_fee_bps = discounted_fee_bps; // New discounted fee.
+    Ok(())
+}
+
+// Function to set the bounds on the Token A amount of new offers.
+pub fn apply_offer_amount_limits(
+    ctx: Context<UpdateConfig>,
+    min_offer_amount: u64, // Smallest amount offered, or 0 for no minimum.
+    max_offer_amount: u64, // Largest amount offered, or 0 for no maximum.
+) -> Result<()> {
+    require!(
+        max_offer_amount == 0 || min_offer_amount <= max_offer_amount,
+        ErrorCode::InvalidOfferAmountLimits
+    );
+
+    let config = &mut ctx.accounts.config;
+    config.min_offer_amount = min_offer_amount; // New minimum offer size.
+    config.max_offer_amount = max_offer_amount; // New maximum offer size.
+    Ok(())
+}
+
+// Function to set how long makers alone may close their expired offers before anyone may sweep them.
+pub fn apply_expiry_grace_period(
+    ctx: Context<UpdateConfig>,
+    expiry_grace_period: i64, // Seconds after expiry before a sweep is allowed.
+) -> Result<()> {
+    require!(expiry_grace_period >= 0, ErrorCode::InvalidGracePeriod);
+
+    ctx.accounts.config.expiry_grace_period = expiry_grace_period; // New grace period.
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+use crate::{Config, FeeExempt}; // Global settings account and the fee-exempt takers.
+
+#[derive(Accounts)]
+pub struct UpdateFeeExempt<'info> {
+    // The admin recorded in the config. Nobody else may change the fee-exempt takers.
+    pub admin: Signer<'info>,
+
+    // The global config naming the admin.
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // The fee-exempt list PDA being updated.
+    #[account(mut, seeds = [b"fee_exempt"], bump = fee_exempt.bump)]
+    pub fee_exempt: Account<'info, FeeExempt>,
+}
+
+// Function to let a taker wallet take offers without paying fees.
+pub fn exempt_taker(ctx: Context<UpdateFeeExempt>, taker: Pubkey) -> Result<()> {
+    ctx.accounts.fee_exempt.add(taker)
+}
+
+// Function to make a taker wallet pay the normal fees again.
+pub fn unexempt_taker(ctx: Context<UpdateFeeExempt>, taker: Pubkey) -> Result<()> {
+    ctx.accounts.fee_exempt.remove(&taker);
+    Ok(())
+}
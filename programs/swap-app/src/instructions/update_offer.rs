@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+// Importing the `Offer` struct and the event emitted on update.
+use crate::{Offer, OfferUpdated, OFFER_SEED};
+
+#[derive(Accounts)]
+pub struct UpdateOffer<'info> {
+    // The maker (creator) of the offer. Only the maker can change its terms.
+    pub maker: Signer<'info>,
+
+    // The offer account whose wanted amount is being changed.
+    #[account(
+        mut,
+        has_one = maker,
+        seeds = [OFFER_SEED, offer.creator.as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+}
+
+// Function to replace the amount of Token B the maker wants for what is left in the vault.
+// No tokens move, since only the requested side of the offer changes.
+pub fn save_wanted_amount(ctx: Context<UpdateOffer>, token_b_wanted_amount: u64) -> Result<()> {
+    check_wanted_amount(&ctx.accounts.offer, token_b_wanted_amount)?;
+    reprice(&mut ctx.accounts.offer, token_b_wanted_amount);
+    Ok(())
+}
+
+// Function to check the offer can be re-priced to `token_b_wanted_amount`.
+// Shared with `rebalance_offer`, which re-prices the same way.
+pub fn check_wanted_amount(offer: &Offer, token_b_wanted_amount: u64) -> Result<()> {
+    // Asking for nothing would turn the offer into a giveaway, and auctions and oracles set their own price.
+    require!(token_b_wanted_amount > 0, ErrorCode::ZeroAmount);
+    require!(offer.auction.is_none(), ErrorCode::AuctionOfferCannotBeRepriced);
+    require!(!offer.is_oracle_priced(), ErrorCode::OracleOfferCannotBeRepriced);
+    Ok(())
+}
+
+// Function to record the new wanted amount, once checked with `check_wanted_amount`.
+pub fn reprice(offer: &mut Offer, token_b_wanted_amount: u64) {
+    offer.token_b_wanted_amount = token_b_wanted_amount;
+
+    // Announce the new terms to indexers.
+    emit!(OfferUpdated {
+        id: offer.id,
+        maker: offer.maker,
+        token_b_wanted_amount,
+    });
+}
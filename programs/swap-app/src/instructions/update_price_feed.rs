@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode; // Custom program errors.
+use crate::PriceFeed; // The price feed account.
+
+#[derive(Accounts)]
+pub struct UpdatePriceFeed<'info> {
+    // The feed's authority. Nobody else may publish to it.
+    pub authority: Signer<'info>,
+
+    // The price feed being updated.
+    #[account(mut, has_one = authority @ ErrorCode::Unauthorized)]
+    pub price_feed: Account<'info, PriceFeed>,
+}
+
+// Function to publish a new price, refreshing the feed's publish time.
+pub fn publish_price(ctx: Context<UpdatePriceFeed>, price: u64, expo: i32) -> Result<()> {
+    // A zero price would let takers have oracle-priced offers for free.
+    require!(price > 0, ErrorCode::InvalidOracle);
+
+    let price_feed = &mut ctx.accounts.price_feed;
+    price_feed.price = price; // New price.
+    price_feed.expo = expo; // New exponent.
+    price_feed.publish_time = Clock::get()?.unix_timestamp; // Published now.
+    Ok(())
+}
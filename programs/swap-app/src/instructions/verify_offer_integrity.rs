@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling associated tokens and token accounts.
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::TokenAccount, // Represents a token account.
+};
+
+use crate::error::ErrorCode; // Custom program errors.
+use crate::Offer; // Importing the `Offer` struct, which represents the offer details.
+
+#[derive(Accounts)]
+pub struct VerifyOfferIntegrity<'info> {
+    // The offer being checked. Its address is verified in `check_offer_integrity` rather than by
+    // seeds, so a bad bump surfaces as its own error.
+    pub offer: Account<'info, Offer>,
+
+    // The account claimed to be the offer's vault. Checked against the offer in `check_offer_integrity`.
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+// Function to check the offer's invariants, failing with an error naming the first one violated.
+// Nothing is written.
+pub fn check_offer_integrity(ctx: Context<VerifyOfferIntegrity>) -> Result<()> {
+    let offer = &ctx.accounts.offer;
+    let vault = &ctx.accounts.vault;
+
+    // The stored bump must re-derive the offer's address.
+    require!(offer.has_canonical_address(&offer.key()), ErrorCode::InvalidOfferBump);
+
+    // The vault must hold the offer's Token A and be the offer's associated token account for it.
+    require_keys_eq!(vault.mint, offer.token_mint_a, ErrorCode::VaultMintMismatch);
+    let token_program_id = *vault.to_account_info().owner;
+    require_keys_eq!(
+        vault.key(),
+        get_associated_token_address_with_program_id(&offer.key(), &offer.token_mint_a, &token_program_id),
+        ErrorCode::VaultAccountMismatch
+    );
+
+    // The vault must hold exactly the Token A the offer still promises.
+    require!(vault.amount >= offer.token_a_remaining, ErrorCode::VaultBalanceMismatch);
+    require!(vault.amount == offer.token_a_remaining, ErrorCode::VaultSurplus);
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+// Importing Anchor SPL libraries for handling token operations.
+use anchor_spl::token_interface::{
+    Mint, // Represents the token mint (currency).
+    TokenAccount, // Represents a token account.
+    TokenInterface, // Represents the token program interface.
+};
+
+use super::withdraw_from_vault; // Utility function for moving tokens out of a PDA-owned account.
+use crate::error::ErrorCode; // Custom program errors.
+use crate::Config; // Global settings account naming the admin.
+
+#[derive(Accounts)]
+pub struct WithdrawTreasury<'info> {
+    // The admin recorded in the config. Nobody else may move the treasury's fees.
+    pub admin: Signer<'info>,
+
+    // The global config naming the admin.
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, Config>,
+
+    // The treasury PDA that owns the protocol's fee accounts. Only the program can sign for it.
+    #[account(seeds = [b"treasury"], bump)]
+    pub treasury: SystemAccount<'info>,
+
+    // Mint of the fees being withdrawn.
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    // The treasury's token account holding the accumulated fees.
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = treasury,
+        associated_token::token_program = token_program
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    // Any token account of the same mint chosen by the admin to receive the fees.
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    // Token program governing the fee accounts.
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Function to move `amount` of the treasury's fees, or all of them when `amount` is 0, to the destination.
+pub fn withdraw_fees(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+    let balance = ctx.accounts.treasury_token_account.amount;
+    let amount = if amount == 0 { balance } else { amount };
+    require!(amount > 0, ErrorCode::ZeroAmount);
+    require!(amount <= balance, ErrorCode::InsufficientTreasuryBalance);
+
+    // Seeds for signing as the treasury PDA.
+    let seeds = &[b"treasury".as_ref(), &[ctx.bumps.treasury]];
+    let signer_seeds = [&seeds[..]];
+
+    withdraw_from_vault(
+        &ctx.accounts.treasury_token_account, // Source account (treasury's fees).
+        &ctx.accounts.destination, // Destination account chosen by the admin.
+        amount, // Amount to withdraw.
+        &ctx.accounts.mint, // Mint of the fees.
+        ctx.accounts.treasury.to_account_info(), // Authority (treasury PDA).
+        &ctx.accounts.token_program, // Token program.
+        &signer_seeds,
+    )
+}
@@ -1,12 +1,15 @@
 pub mod constants; // Module for storing reusable constants.
 pub mod error; // Module for custom error definitions.
+pub mod events; // Module for events emitted to indexers.
 pub mod instructions; // Module containing the logic for program instructions.
 pub mod state; // Module defining program-specific data structures and states.
 
 use anchor_lang::prelude::*; // Importing Anchor framework essentials.
+use error::ErrorCode; // Importing the program's custom errors.
 
 // Re-exporting modules for easy access in the program.
 pub use constants::*;
+pub use events::*;
 pub use instructions::*;
 pub use state::*;
 
@@ -24,29 +27,929 @@ pub mod swap_app {
     /// - `id`: Unique identifier for the offer.
     /// - `token_a_offered_amount`: Amount of Token A being offered.
     /// - `token_b_offered_amount`: Amount of Token B being requested in return.
-    pub fn make_offer(
-        ctx: Context<MakeOffer>,
+    /// - `expiry`: Unix timestamp after which the offer can no longer be taken, or 0 for no expiry.
+    ///   A non-zero expiry must be at least `MIN_OFFER_DURATION` seconds away.
+    /// - `allowed_taker`: The only wallet allowed to take the offer, or `None` to let anyone take
+    ///   it.
+    /// - `basket_amounts`: Amounts of up to two extra tokens offered alongside Token A. Each extra
+    ///   token is passed in `remaining_accounts` as its mint, the offer's vault for it, and the
+    ///   maker's token account for it.
+    /// - `min_fill_amount`: Smallest amount of Token A a partial fill may take, or 0 to allow any.
+    /// - `token_b_destination`: Token B account to pay instead of the maker's associated token
+    ///   account, or `None` to use the default. Its mint is checked against Token B on each take.
+    /// - `label`: Short description for UIs (e.g. "Selling USDC for BONK"), at most 32 bytes.
+    /// - `auction`: Dutch-auction terms under which the Token B wanted for the whole offer decays
+    ///   linearly over time, or `None` for a fixed price of `token_b_offered_amount`.
+    /// - `royalty_recipient`: Wallet (e.g. the token's issuer) paid a royalty out of each take.
+    /// - `royalty_bps`: Share of each Token B payment paid to `royalty_recipient`'s associated
+    ///   token account, in basis points, or 0 for no royalty.
+    /// - `oracle`: `PriceFeed` account pricing the offer on every take instead of
+    ///   `token_b_offered_amount`, or the default pubkey for a fixed price. Cannot be combined with
+    ///   an auction.
+    /// - `spread_bps`: Premium over the oracle price the maker asks for, in basis points.
+    /// - `taker_rebate_bps`: Share of each taker's protocol fee paid back to them out of
+    ///   `rebate_reserve`, in basis points, or 0 for none.
+    /// - `commit_only`: Refuses every take but `reveal_take`, so takers must commit with
+    ///   `commit_take` first and a searcher cannot snipe the fill from the mempool.
+    /// - `rebate_reserve`: Token B the maker escrows in a second vault owned by the offer to pay
+    ///   the rebates, required exactly when `taker_rebate_bps` is set. Each take pays the full
+    ///   price, so the treasury gets its full fee and the maker their full proceeds, and the
+    ///   reserve then sends the taker their rebate, capped at what is left of it. Once it runs dry
+    ///   later takes earn none and the vault is closed; whatever is left when the offer fills, is
+    ///   cancelled or is swept goes back to the maker. The maker thus commits to a budget rather
+    ///   than a rate, at the cost of funding Token B up front and the rent of one more vault. The
+    ///   reserve is passed after any basket accounts as Token B's mint, the offer's vault for it
+    ///   and the maker's Token B account, and must be passed the same way to every take and refund
+    ///   while the offer holds one.
+    ///
+    /// When the maker's `MakerIndex` or the pair's `PairIndex` is passed, the new offer is appended
+    /// to it in the same transaction. A maker or rent payer short of the SOL for the deposit or the
+    /// extra vaults fails with `InsufficientRent`; placing `check_offer_funding` ahead of this
+    /// instruction extends that to the rent of the accounts created before the handler runs.
+    #[allow(clippy::too_many_arguments)]
+    pub fn make_offer<'info>(
+        mut ctx: Context<'_, '_, 'info, 'info, MakeOffer<'info>>,
         id: u64,
         token_a_offered_amount: u64,
         token_b_offered_amount: u64,
+        expiry: i64,
+        allowed_taker: Option<Pubkey>,
+        basket_amounts: Vec<u64>,
+        min_fill_amount: u64,
+        token_b_destination: Option<Pubkey>,
+        label: String,
+        auction: Option<DutchAuction>,
+        royalty_recipient: Pubkey,
+        royalty_bps: u16,
+        oracle: Pubkey,
+        spread_bps: u16,
+        taker_rebate_bps: u16,
+        commit_only: bool,
+        rebate_reserve: u64,
     ) -> Result<()> {
+        // Step 0: Refuse new offers while the program is paused, and reject empty or out-of-range
+        // offers, imminent expiries, oversized labels, malformed auctions, royalties or rebates
+        // above 100%, or makers at their open-offer cap or with a full offer index before any
+        // tokens move.
+        require!(!ctx.accounts.config.paused, ErrorCode::ProgramPaused);
+        require!(token_a_offered_amount > 0, ErrorCode::ZeroAmount);
+        require!(token_b_offered_amount > 0, ErrorCode::ZeroAmount);
+        require!(
+            ctx.accounts.config.accepts_offer_amount(token_a_offered_amount),
+            ErrorCode::OfferAmountOutOfRange
+        );
+        require!(
+            expiry == 0 || expiry >= Clock::get()?.unix_timestamp + MIN_OFFER_DURATION,
+            ErrorCode::ExpiryTooSoon
+        );
+        require!(label.len() <= MAX_LABEL_LEN, ErrorCode::LabelTooLong);
+        require!(
+            auction.as_ref().is_none_or(DutchAuction::is_valid),
+            ErrorCode::InvalidAuction
+        );
+        require!(royalty_bps as u64 <= BASIS_POINTS_DIVISOR, ErrorCode::InvalidFeeBps);
+        require!(
+            oracle == Pubkey::default() || auction.is_none(),
+            ErrorCode::InvalidOracle
+        );
+        require!(spread_bps as u64 <= BASIS_POINTS_DIVISOR, ErrorCode::InvalidFeeBps);
+        require!(taker_rebate_bps as u64 <= BASIS_POINTS_DIVISOR, ErrorCode::InvalidFeeBps);
+        require!(
+            (taker_rebate_bps == 0) == (rebate_reserve == 0),
+            ErrorCode::RebateReserveMismatch
+        );
+        instructions::make_offer::reject_unsupported_mints(&ctx)?;
+        let extra_vaults = basket_amounts.len() + usize::from(rebate_reserve > 0);
+        instructions::make_offer::check_deposit_funding(
+            &ctx,
+            token_a_offered_amount,
+            extra_vaults,
+        )?;
+        instructions::make_offer::count_open_offer(&mut ctx)?;
+        instructions::make_offer::index_offer(&mut ctx)?;
+        instructions::log_compute_units("make_offer checked");
+
         // Step 1: Transfer offered tokens (Token A) from the maker's account to the program vault.
+        // Any extra basket tokens go into their own vaults in the same way, as does any rebate
+        // reserve.
         instructions::make_offer::send_offered_tokens_to_vault(&ctx, token_a_offered_amount)?;
+        instructions::make_offer::check_vault_deposit(&mut ctx, token_a_offered_amount)?;
+        let (basket_accounts, reserve_accounts) = instructions::rebate_reserve::split_rebate_reserve(
+            ctx.remaining_accounts,
+            rebate_reserve > 0,
+        )?;
+        let basket =
+            instructions::basket::open_basket_vaults(&ctx, basket_accounts, &basket_amounts)?;
+        instructions::rebate_reserve::open_rebate_reserve(&ctx, reserve_accounts, rebate_reserve)?;
+        instructions::log_compute_units("make_offer deposited");
+
+        // Step 2: Save the details of the offer (id, offered and requested amounts, etc.) in the
+        // program state.
+        instructions::make_offer::save_offer(
+            ctx,
+            id,
+            token_a_offered_amount,
+            token_b_offered_amount,
+            expiry,
+            allowed_taker,
+            basket,
+            min_fill_amount,
+            token_b_destination,
+            label,
+            auction,
+            royalty_recipient,
+            royalty_bps,
+            oracle,
+            spread_bps,
+            taker_rebate_bps,
+            commit_only,
+            rebate_reserve,
+        )
+    }
+
+    /// Creates an offer like `make_offer`, using the maker's next auto id from their `MakerStats`
+    /// so it can never collide with another auto-id offer of theirs. The offer's address is
+    /// derived from its id, so the id is still passed, and it must equal `maker_stats.next_id`.
+    /// Ids chosen through `make_offer` are not tracked and may still collide with auto ids.
+    ///
+    /// # Arguments
+    /// The same as `make_offer`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn make_offer_auto<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MakeOffer<'info>>,
+        id: u64,
+        token_a_offered_amount: u64,
+        token_b_offered_amount: u64,
+        expiry: i64,
+        allowed_taker: Option<Pubkey>,
+        basket_amounts: Vec<u64>,
+        min_fill_amount: u64,
+        token_b_destination: Option<Pubkey>,
+        label: String,
+        auction: Option<DutchAuction>,
+        royalty_recipient: Pubkey,
+        royalty_bps: u16,
+        oracle: Pubkey,
+        spread_bps: u16,
+        taker_rebate_bps: u16,
+        commit_only: bool,
+        rebate_reserve: u64,
+    ) -> Result<()> {
+        ctx.accounts.maker_stats.claim_next_id(id)?;
+        make_offer(
+            ctx,
+            id,
+            token_a_offered_amount,
+            token_b_offered_amount,
+            expiry,
+            allowed_taker,
+            basket_amounts,
+            min_fill_amount,
+            token_b_destination,
+            label,
+            auction,
+            royalty_recipient,
+            royalty_bps,
+            oracle,
+            spread_bps,
+            taker_rebate_bps,
+            commit_only,
+            rebate_reserve,
+        )
+    }
+
+    /// Posts a ladder of offers for the same pair in one transaction, one per rung, each at its own
+    /// price. Every rung is a plain offer, made and checked as `make_offer` would with no optional
+    /// features, and sharing `expiry`. Each rung is passed in `remaining_accounts` as its offer PDA
+    /// (derived from the maker and its id) and the offer's vault for Token A. A single
+    /// `LadderCreated` event lists the ladder's ids in place of an `OfferMade` per rung. A maker
+    /// short of the SOL for every rung's rent, or for the SOL wrapped into the vaults when Token A
+    /// is native, fails with `InsufficientRent` before any rung is created; placing
+    /// `check_offer_funding` ahead of the ladder extends that to the rent of the maker's stats.
+    /// When the maker's `MakerIndex` or the pair's `PairIndex` is passed, every rung is appended to
+    /// it.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `ids`: Id of each rung's offer, at most `MAX_LADDER_RUNGS`.
+    /// - `token_a_offered_amounts`: Token A each rung offers.
+    /// - `token_b_wanted_amounts`: Token B each rung wants in return.
+    /// - `expiry`: Unix timestamp after which every rung expires, or 0 for no expiry.
+    pub fn make_offers_ladder<'info>(
+        ctx: Context<'_, '_, 'info, 'info, MakeOffersLadder<'info>>,
+        ids: Vec<u64>,
+        token_a_offered_amounts: Vec<u64>,
+        token_b_wanted_amounts: Vec<u64>,
+        expiry: i64,
+    ) -> Result<()> {
+        instructions::make_offers_ladder::open_ladder(
+            ctx,
+            ids,
+            token_a_offered_amounts,
+            token_b_wanted_amounts,
+            expiry,
+        )
+    }
+
+    /// Creates an offer of Token A for native SOL instead of a Token B mint. The Token A is vaulted
+    /// as with `make_offer`, and the offer records `SOL_WANTED_MINT` as its Token B mint, so it can
+    /// only be taken with `take_offer_with_sol`; it is cancelled like any other offer. It gets the
+    /// checks `make_offer` applies to Token A, with no optional features. Wrapped SOL cannot be
+    /// offered this way. Its accounts are all created before the handler runs, so a maker short of
+    /// their rent gets `InsufficientRent` from `check_offer_funding` placed ahead of it.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `id`: Id of the offer, deriving its address as for `make_offer`.
+    /// - `token_a_offered_amount`: Amount of Token A offered.
+    /// - `lamports_wanted`: Lamports of SOL wanted for all of it, stored as
+    ///   `token_b_wanted_amount`.
+    /// - `expiry`: Unix timestamp after which the offer expires, or 0 for no expiry.
+    pub fn make_offer_for_sol(
+        ctx: Context<MakeOfferForSol>,
+        id: u64,
+        token_a_offered_amount: u64,
+        lamports_wanted: u64,
+        expiry: i64,
+    ) -> Result<()> {
+        instructions::make_offer_for_sol::open_sol_offer(
+            ctx,
+            id,
+            token_a_offered_amount,
+            lamports_wanted,
+            expiry,
+        )
+    }
+
+    /// Checks the maker and the rent payer hold the SOL about to be spent creating offers, failing
+    /// with `InsufficientRent` naming the amount. `make_offer`, `make_offer_auto`,
+    /// `make_offer_for_sol` and `make_offers_ladder` create their accounts before their handlers
+    /// run, so a short wallet would otherwise fail inside the system program; placing this
+    /// instruction ahead of them in the same transaction fails first with the clear error. Each
+    /// offer is passed in `remaining_accounts` as its offer PDA and vault, as for a ladder rung.
+    /// Nothing is written.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `ids`: Id of each offer about to be created, at most `MAX_LADDER_RUNGS`.
+    /// - `token_a_offered_amount`: Token A the offers deposit in total, wrapped from the maker's
+    ///   SOL when Token A is native.
+    /// - `basket_legs`: Extra basket mints the offer vaults, each in a vault of its own.
+    pub fn check_offer_funding(
+        ctx: Context<CheckOfferFunding>,
+        ids: Vec<u64>,
+        token_a_offered_amount: u64,
+        basket_legs: u8,
+    ) -> Result<()> {
+        instructions::check_offer_funding::check_funding_for_offers(
+            ctx,
+            ids,
+            token_a_offered_amount,
+            basket_legs,
+        )
+    }
+
+    /// Commits the taker to taking an offer without revealing the commitment's secret, as the
+    /// first step of a commit-reveal take. A new commitment replaces the taker's unrevealed one.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `hash`: SHA-256 of the taker's public key followed by a secret 32-byte nonce.
+    pub fn commit_take(ctx: Context<CommitTake>, hash: [u8; 32]) -> Result<()> {
+        instructions::commit_take::save_commitment(ctx, hash)
+    }
+
+    /// Reveals the nonce behind the taker's commitment and, if it matches, at least
+    /// `MIN_REVEAL_DELAY_SLOTS` slots have passed and no more than `MAX_REVEAL_DELAY_SLOTS`, takes
+    /// the offer exactly as `take_offer` would. The commitment is closed and its rent returned to
+    /// the taker. A commitment left too long to reveal can only be closed with `close_commitment`.
+    /// This is the only way to take an offer made `commit_only`.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing the accounts of `take_offer` under `take`, plus the commitment.
+    /// - `nonce`: The secret nonce committed to.
+    /// - `token_a_requested_amount`: Amount of Token A the taker wants out of the offer.
+    /// - `max_token_b_amount`: Most Token B the taker is willing to pay for this fill.
+    /// - `unwrap`: Closes the taker's wrapped SOL accounts after the take, as in `take_offer`.
+    pub fn reveal_take<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RevealTake<'info>>,
+        nonce: [u8; 32],
+        token_a_requested_amount: u64,
+        max_token_b_amount: u64,
+        unwrap: bool,
+    ) -> Result<()> {
+        instructions::reveal_take::reveal_and_fill(
+            ctx,
+            nonce,
+            token_a_requested_amount,
+            max_token_b_amount,
+            unwrap,
+        )
+    }
+
+    /// Closes the taker's commitment and returns its rent to them, for a commitment they no longer
+    /// mean to reveal or that is too old to. The offer it was made for need not still exist.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn close_commitment(_ctx: Context<CloseCommitment>) -> Result<()> {
+        // The `close` constraint on the commitment does all the work.
+        Ok(())
+    }
+
+    /// Accepts all or part of an existing offer by transferring tokens, closing the vault once the
+    /// offer is completely filled. All rent goes back to the offer's rent payer. The maker's Token
+    /// B account, when used, must already exist but need not be their associated token account.
+    /// When the pair's `PairIndex` is passed, a filled offer is removed from it. Offers made
+    /// `commit_only` fail with `CommitRevealRequired` here, as with every other direct take, and
+    /// can only be taken through `reveal_take`.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `token_a_requested_amount`: Amount of Token A the taker wants out of the offer.
+    ///   Basket offers must be taken in full, with each extra token passed in `remaining_accounts`
+    ///   as its mint, the offer's vault for it, and the taker's token account for it. An offer
+    ///   holding a rebate reserve has it passed next, as described under `make_offer`.
+    /// - `max_token_b_amount`: Most Token B the taker is willing to pay for this fill, protecting
+    ///   them from the maker raising the price before the transaction lands. Any rebate is paid
+    ///   back after it, so it is not taken off this amount.
+    /// - `memo`: Note attached to the trade through the SPL Memo program, at most `MAX_MEMO_LEN`
+    ///   bytes, or empty for none. With a memo, the Memo program is passed as the last of the
+    ///   `remaining_accounts`, after any basket and rebate reserve accounts.
+    /// - `unwrap`: When Token A is wrapped SOL, closes the taker's wrapped SOL account after the
+    ///   withdrawal so they receive plain SOL, rent included. When Token B is wrapped SOL, closes
+    ///   the taker's Token B account after paying in the same way. Ignored for every other mint.
+    ///
+    /// When Token B is wrapped SOL, takers can pay in plain SOL: whatever their Token B account
+    /// is short of the price is wrapped into it from their SOL before paying.
+    pub fn take_offer<'info>(
+        ctx: Context<'_, '_, 'info, 'info, TakeOffer<'info>>,
+        token_a_requested_amount: u64,
+        max_token_b_amount: u64,
+        memo: String,
+        unwrap: bool,
+    ) -> Result<()> {
+        let (memo_program, remaining_accounts) =
+            instructions::take_offer::split_memo_program(ctx.remaining_accounts, &memo)?;
+        if let Some(memo_program) = memo_program {
+            instructions::take_offer::log_memo(memo_program, &memo)?;
+        }
+        let take = ctx.accounts.as_take(remaining_accounts, unwrap);
+        instructions::take_offer::fill_offer(take, token_a_requested_amount, max_token_b_amount)?;
+        if unwrap {
+            instructions::take_offer::unwrap_taker_token_b(ctx.accounts)?;
+        }
+        Ok(())
+    }
+
+    /// Same as `take_offer`, for takers who manage their associated token accounts themselves. The
+    /// taker's Token A account and the treasury's Token B account must already exist, which lets
+    /// the transaction leave out the Associated Token program.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `token_a_requested_amount`: Amount of Token A the taker wants out of the offer.
+    /// - `max_token_b_amount`: Most Token B the taker is willing to pay for this fill.
+    pub fn take_offer_existing_accounts<'info>(
+        ctx: Context<'_, '_, 'info, 'info, TakeOfferExistingAccounts<'info>>,
+        token_a_requested_amount: u64,
+        max_token_b_amount: u64,
+    ) -> Result<()> {
+        let take = ctx.accounts.as_take(ctx.remaining_accounts);
+        instructions::take_offer::fill_offer(take, token_a_requested_amount, max_token_b_amount)
+    }
+
+    /// Accepts all or part of an offer made with `make_offer_for_sol`, paying in SOL through the
+    /// system program: the maker receives their share in their wallet, and the treasury PDA the
+    /// protocol fee and the flat fee. The price and fee split are those of `take_offer`, in
+    /// lamports. A payment that would leave the maker's wallet or the treasury holding less than
+    /// the rent-exempt minimum fails with `RecipientNotRentExempt`, so a fee below that minimum can
+    /// only be paid into a treasury that already holds it. The taker's fee tier is that of
+    /// `take_offer` too, from their governance token account and the fee-exempt list when passed.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `token_a_requested_amount`: Amount of Token A the taker wants out of the offer.
+    /// - `max_lamports`: Most lamports the taker is willing to pay for this fill, the flat fee
+    ///   aside.
+    pub fn take_offer_with_sol(
+        ctx: Context<TakeOfferWithSol>,
+        token_a_requested_amount: u64,
+        max_lamports: u64,
+    ) -> Result<()> {
+        instructions::take_offer_with_sol::fill_with_sol(
+            ctx,
+            token_a_requested_amount,
+            max_lamports,
+        )
+    }
+
+    /// Fully takes several offers of the same pair in one transaction. Each offer is passed in
+    /// `remaining_accounts` as the offer, its vault, its maker, the account receiving the maker's
+    /// Token B (the offer's custom destination, or the maker's existing associated token account),
+    /// and the maker's stats PDA. Expired offers are skipped instead of failing the batch; basket
+    /// and royalty offers must be taken individually with `take_offer`, and commit-only ones
+    /// through `reveal_take`. Returns what was taken and skipped. Every offer is charged the
+    /// taker's fee tier, as `take_offer` picks it from their governance token account and the
+    /// fee-exempt list when passed.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `max_token_b_amount`: Most Token B the taker is willing to pay for the whole batch.
+    pub fn take_offers_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, TakeOffersBatch<'info>>,
+        max_token_b_amount: u64,
+    ) -> Result<BatchTakeSummary> {
+        instructions::take_offers_batch::take_batch(ctx, max_token_b_amount)
+    }
+
+    /// Cancels an existing offer, returning the vaulted tokens to the maker and all rent to whoever
+    /// paid it. The maker may cancel at any time, signing as the `canceller`. Once the offer has
+    /// expired its rent payer may cancel it too, such as a sponsor reclaiming their rent from an
+    /// inactive maker; before then they are refused with `OfferNotExpired`.
+    /// Deliberately allowed while the program is paused so makers can always recover funds.
+    /// Pays a maker rebate from the treasury's Token A account, when passed, if the offer stayed
+    /// open for the configured duration. Each extra basket token is passed in `remaining_accounts`
+    /// as its mint, the offer's vault for it, and the maker's own token account for it, followed by
+    /// any rebate reserve as described under `make_offer`. When the pair's `PairIndex` is passed,
+    /// the offer is removed from it.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `close_token_account_a`: Also closes the maker's Token A account after the refund,
+    ///   returning its rent to them, if it is then empty. A wrapped SOL account is always closed,
+    ///   unwrapping the refund to plain SOL. Only the maker may set it.
+    pub fn cancel_offer<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelOffer<'info>>,
+        close_token_account_a: bool,
+    ) -> Result<()> {
+        // Step 0: Only the maker, or the rent payer once the offer has expired, may cancel it.
+        let now = Clock::get()?.unix_timestamp;
+        instructions::cancel_offer::check_canceller(ctx.accounts, close_token_account_a, now)?;
+
+        // Step 1: Pay the maker's rebate from the treasury if the offer stayed open long enough.
+        instructions::cancel_offer::pay_maker_rebate(&ctx)?;
+
+        // Step 2: Refund the offered tokens (Token A and any basket tokens) from the vaults to the
+        // maker's accounts and close the vault accounts. The offer account is closed by its
+        // `close` constraint. The maker's Token A account is closed too if they asked for it.
+        instructions::cancel_offer::refund_and_close_vault(ctx, close_token_account_a)
+    }
+
+    /// Lets the config admin unwind an offer (for example one on a blacklisted mint), refunding the
+    /// vaulted tokens and all rent to the offer's current maker. The admin can never redirect them:
+    /// every refund goes to the maker's own accounts. Allowed while the program is paused. Each
+    /// extra basket token is passed in `remaining_accounts` as its mint, the offer's vault for it,
+    /// and the maker's token account for it, followed by any rebate reserve as described under
+    /// `make_offer`. When the pair's `PairIndex` is passed, the offer is removed from it.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn admin_close_offer<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AdminCloseOffer<'info>>,
+    ) -> Result<()> {
+        instructions::admin_close_offer::force_close(ctx)
+    }
+
+    /// Cancels several of the maker's offers in one transaction, refunding every vault and
+    /// returning all rent to the maker. Each offer is passed in `remaining_accounts` as the offer,
+    /// its Token A mint, its vault, and the maker's Token A account. Basket offers must be
+    /// cancelled individually with `cancel_offer`, and batch cancellations earn no maker rebate.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn cancel_offers_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelOffersBatch<'info>>,
+    ) -> Result<()> {
+        instructions::cancel_offers_batch::refund_and_close_batch(ctx)
+    }
+
+    /// Closes an offer whose vault no longer exists or holds nothing, returning its rent to the
+    /// maker. Offers with tokens still in their vault must be cancelled or taken instead. When the
+    /// pair's `PairIndex` is passed, the offer is removed from it.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn close_stale_offer(ctx: Context<CloseStaleOffer>) -> Result<()> {
+        instructions::close_stale_offer::close_if_stale(ctx)
+    }
+
+    /// Lets anyone close an expired offer once the config's `expiry_grace_period` has passed since
+    /// its expiry, refunding the vaulted tokens to the offer's current maker and the rent to
+    /// whoever paid it, as `admin_close_offer` would. Until then only the maker, and the rent payer
+    /// once the offer has expired, may close it, with `cancel_offer`. Allowed while the program is
+    /// paused. Each extra basket token is passed in `remaining_accounts` as its mint, the offer's
+    /// vault for it, and the maker's token account for it, followed by any rebate reserve as
+    /// described under `make_offer`.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn sweep_expired_offer<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SweepExpiredOffer<'info>>,
+    ) -> Result<()> {
+        instructions::sweep_expired_offer::sweep(ctx)
+    }
 
-        // Step 2: Save the details of the offer (id, requested amount, etc.) in the program state.
-        instructions::make_offer::save_offer(ctx, id, token_b_offered_amount)
+    /// Adds more Token A to an open offer, growing both its original and remaining size. Restarts
+    /// the offer's `created_at`, and with it the wait before a cancel earns a maker rebate. What
+    /// the offer has left afterwards must be within the config's offer amount limits, and the vault
+    /// must receive exactly `token_a_amount`, failing with `DepositMismatch` otherwise.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `token_a_amount`: Amount of Token A to add to the vault.
+    pub fn top_up_offer(ctx: Context<TopUpOffer>, token_a_amount: u64) -> Result<()> {
+        instructions::top_up_offer::add_to_vault(ctx, token_a_amount)
     }
 
-    /// Accepts an existing offer by transferring tokens and closing the vault.
+    /// Withdraws some Token A from an open offer back to the maker, shrinking both its original
+    /// and remaining size. Allowed while paused, like `cancel_offer`. What the offer has left
+    /// afterwards must be within the config's offer amount limits.
     ///
     /// # Arguments
     /// - `ctx`: Context containing accounts required to execute the instruction.
-    pub fn take_offer(ctx: Context<TakeOffer>) -> Result<()> {
-        // Step 1: Transfer the requested amount of Token B from the taker's account to the maker's account.
-        instructions::take_offer::send_wanted_tokens_to_maker(&ctx)?;
+    /// - `token_a_amount`: Amount of Token A to withdraw from the vault.
+    pub fn reduce_offer(ctx: Context<ReduceOffer>, token_a_amount: u64) -> Result<()> {
+        instructions::reduce_offer::withdraw_from_offer(ctx, token_a_amount)
+    }
 
-        // Step 2: Withdraw the offered tokens (Token A) from the vault to the taker's account
-        // and close the vault account.
-        instructions::take_offer::withdraw_and_close_vault(ctx)
+    /// Changes the amount of Token B the maker wants for what is left on an offer.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `token_b_wanted_amount`: New amount of Token B requested in return.
+    pub fn update_offer(ctx: Context<UpdateOffer>, token_b_wanted_amount: u64) -> Result<()> {
+        instructions::update_offer::save_wanted_amount(ctx, token_b_wanted_amount)
+    }
+
+    /// Adds more Token A to an open offer and changes the amount of Token B wanted for it in one
+    /// call, combining `top_up_offer` and `update_offer`. Either both changes apply or neither
+    /// does. Restarts the offer's `created_at` and checks the offer amount limits like
+    /// `top_up_offer`.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `token_a_amount`: Amount of Token A to add to the vault.
+    /// - `token_b_wanted_amount`: New amount of Token B requested in return for everything left.
+    pub fn rebalance_offer(
+        ctx: Context<TopUpOffer>,
+        token_a_amount: u64,
+        token_b_wanted_amount: u64,
+    ) -> Result<()> {
+        instructions::rebalance_offer::top_up_and_reprice(
+            ctx,
+            token_a_amount,
+            token_b_wanted_amount,
+        )
+    }
+
+    /// Pushes back the expiry of an offer so it stays open for longer, without recreating it.
+    /// Offers without an expiry never expire and cannot be extended.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `expiry`: New expiry, later than the current one and at least `MIN_OFFER_DURATION` seconds
+    ///   away.
+    pub fn extend_expiry(ctx: Context<ExtendExpiry>, expiry: i64) -> Result<()> {
+        instructions::extend_expiry::save_expiry(ctx, expiry)
+    }
+
+    /// Hands an open offer over to `new_maker`, who from then on is the only one able to update
+    /// or cancel it and receives its Token B, and its rent unless a sponsor paid that. No tokens
+    /// move, since the vaults belong to the offer PDA. The offer keeps its address, which stays
+    /// derived from the original maker's key and `id`; that id therefore remains used in the
+    /// original maker's namespace.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `new_maker`: Wallet taking over the offer.
+    pub fn transfer_offer_ownership(
+        ctx: Context<TransferOfferOwnership>,
+        new_maker: Pubkey,
+    ) -> Result<()> {
+        instructions::transfer_offer_ownership::reassign_maker(ctx, new_maker)
+    }
+
+    /// Returns the offer's current terms without changing anything. Meant to be simulated by
+    /// clients (e.g. Anchor's `.view()`) so they don't need to decode the account themselves.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn get_offer(ctx: Context<GetOffer>) -> Result<Offer> {
+        instructions::get_offer::read_offer(ctx)
+    }
+
+    /// Checks an offer's invariants for monitoring: its bump re-derives its address, the vault is
+    /// its associated token account for Token A, and the vault holds exactly `token_a_remaining`.
+    /// Fails with an error naming the first invariant violated. Nothing is written, so it can be
+    /// simulated.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn verify_offer_integrity(ctx: Context<VerifyOfferIntegrity>) -> Result<()> {
+        instructions::verify_offer_integrity::check_offer_integrity(ctx)
+    }
+
+    /// Upgrades an offer written by an older version of the program to the current layout,
+    /// growing the account (the maker pays the extra rent) and defaulting any new fields.
+    /// Offers already at the current version are refused.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn migrate_offer(ctx: Context<MigrateOffer>) -> Result<()> {
+        instructions::migrate_offer::upgrade_offer(ctx)
+    }
+
+    /// Creates a price feed with its first price, making the signer its authority. Oracle-priced
+    /// offers name the feed and read it on every take.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `price`: Value of one whole Token A in whole Token B, before applying `expo`.
+    /// - `expo`: Power of ten applied to `price`, as in a Pyth price.
+    pub fn create_price_feed(ctx: Context<CreatePriceFeed>, price: u64, expo: i32) -> Result<()> {
+        instructions::create_price_feed::save_price_feed(ctx, price, expo)
+    }
+
+    /// Publishes a new price to a price feed. Only the feed's authority may call this.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `price`: Value of one whole Token A in whole Token B, before applying `expo`.
+    /// - `expo`: Power of ten applied to `price`.
+    pub fn update_price_feed(ctx: Context<UpdatePriceFeed>, price: u64, expo: i32) -> Result<()> {
+        instructions::update_price_feed::publish_price(ctx, price, expo)
+    }
+
+    /// Prices taking `token_a_amount` of an offer right now, exactly as `take_offer` would, and
+    /// returns it without moving any tokens, so wallets can preview a trade by simulating this.
+    /// Fails the same way a take would for amounts the offer cannot fill. Pass the taker, with
+    /// their governance token account or the fee-exempt list, to price the take at their fee tier.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `token_a_amount`: Amount of Token A the taker would take.
+    pub fn quote_take(ctx: Context<QuoteTake>, token_a_amount: u64) -> Result<TakeQuote> {
+        instructions::quote_take::price_take(ctx, token_a_amount)
+    }
+
+    /// Returns the Token B it would take right now to fill everything left on an offer, following
+    /// a Dutch auction's decay or an oracle offer's price feed, without moving any tokens. Meant to
+    /// be simulated like `quote_take`, with the same accounts.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn current_price(ctx: Context<QuoteTake>) -> Result<u64> {
+        instructions::quote_take::price_offer(ctx)
+    }
+
+    /// Grows an offer account made before fields such as `label` existed to the current size,
+    /// zeroing the new bytes. The maker pays the extra rent. Offers already at the current size
+    /// are left untouched, and larger ones are refused rather than shrunk.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn resize_offer(ctx: Context<ResizeOffer>) -> Result<()> {
+        instructions::resize_offer::grow_offer(ctx)
+    }
+
+    /// Creates an empty offer index for the signing maker, which `make_offer` appends to when it is
+    /// passed.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn create_maker_index(ctx: Context<CreateMakerIndex>) -> Result<()> {
+        instructions::create_maker_index::save_maker_index(ctx)
+    }
+
+    /// Drops offers from the maker's index that have been closed or transferred to another maker.
+    /// Each offer to check is passed in `remaining_accounts`.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn prune_maker_index<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PruneMakerIndex<'info>>,
+    ) -> Result<()> {
+        instructions::prune_maker_index::prune(ctx)
+    }
+
+    /// Creates an empty offer index for a pair of mints, listing offers of `token_mint_a` for
+    /// `token_mint_b`. Anyone may create it. `make_offer` appends to it, and `cancel_offer` and
+    /// the take that fills an offer remove from it, whenever it is passed.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn create_pair_index(ctx: Context<CreatePairIndex>) -> Result<()> {
+        instructions::create_pair_index::save_pair_index(ctx)
+    }
+
+    /// Drops offers from a pair's index that have been closed without it, for example by a batch
+    /// take or cancel. Anyone may call this. Each offer to check is passed in `remaining_accounts`.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn prune_pair_index<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PrunePairIndex<'info>>,
+    ) -> Result<()> {
+        instructions::prune_pair_index::drop_closed_offers(ctx)
+    }
+
+    /// Creates the global config account, making the signer its admin, along with an empty mint
+    /// blacklist. Can only succeed once.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `fee_bps`: Protocol fee charged on the Token B side of each take, in basis points.
+    pub fn initialize_config(ctx: Context<InitializeConfig>, fee_bps: u16) -> Result<()> {
+        instructions::initialize_config::save_config(ctx, fee_bps)
+    }
+
+    /// Closes the global config, blacklist, allowlist and fee-exempt list, returning their rent to
+    /// the admin, when winding down a deployment. This is unconditional: open offers are not
+    /// counted, and until `initialize_config` is called again no offer can be made, taken or
+    /// cancelled, so offers should be cancelled (or force-closed with `admin_close_offer`) first.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn close_config(_ctx: Context<CloseConfig>) -> Result<()> {
+        // The `close` constraints on the accounts do all the work.
+        Ok(())
+    }
+
+    /// Moves protocol fees out of a treasury token account to a destination chosen by the admin.
+    /// Only the config admin may call this; the treasury PDA signs for the transfer.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `amount`: Amount of the mint to withdraw, or 0 for the treasury's whole balance.
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        instructions::withdraw_treasury::withdraw_fees(ctx, amount)
+    }
+
+    /// Sets the fee discount for takers holding a governance token. Takes that pass the taker's
+    /// account of `governance_mint` holding at least `governance_threshold` pay
+    /// `discounted_fee_bps` instead of `fee_bps`. Only the config admin may call this.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `governance_mint`: Mint earning the discount, or the default pubkey to turn it off.
+    /// - `governance_threshold`: Smallest balance of the mint a taker's account must hold.
+    /// - `discounted_fee_bps`: Protocol fee charged to those takers, in basis points. It never
+    ///   raises the fee above `fee_bps`.
+    pub fn set_fee_discount(
+        ctx: Context<UpdateConfig>,
+        governance_mint: Pubkey,
+        governance_threshold: u64,
+        discounted_fee_bps: u16,
+    ) -> Result<()> {
+        instructions::update_config::apply_fee_discount(
+            ctx,
+            governance_mint,
+            governance_threshold,
+            discounted_fee_bps,
+        )
+    }
+
+    /// Sets the range of Token A amounts new offers may offer, to keep out both dust offers and
+    /// offers beyond the operator's risk limit. Both bounds are inclusive, in Token A base units
+    /// whatever the mint, and apply to `make_offer`, `make_offer_auto` and every rung of
+    /// `make_offers_ladder`. Offers already open are unaffected. Only the config admin may call
+    /// this.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `min_offer_amount`: Smallest amount a new offer may offer, or 0 for no minimum.
+    /// - `max_offer_amount`: Largest amount a new offer may offer, or 0 for no maximum.
+    pub fn set_offer_amount_limits(
+        ctx: Context<UpdateConfig>,
+        min_offer_amount: u64,
+        max_offer_amount: u64,
+    ) -> Result<()> {
+        instructions::update_config::apply_offer_amount_limits(
+            ctx,
+            min_offer_amount,
+            max_offer_amount,
+        )
+    }
+
+    /// Sets how many seconds after an offer's expiry only its maker may close it. Once that grace
+    /// period is over, anyone may close it with `sweep_expired_offer`, refunding the maker. 0 lets
+    /// expired offers be swept straight away. Only the config admin may call this.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `expiry_grace_period`: Seconds after expiry before a sweep is allowed. Cannot be negative.
+    pub fn set_expiry_grace_period(
+        ctx: Context<UpdateConfig>,
+        expiry_grace_period: i64,
+    ) -> Result<()> {
+        instructions::update_config::apply_expiry_grace_period(ctx, expiry_grace_period)
+    }
+
+    /// Zeroes a maker's `offers_created` and `offers_taken` analytics counters, for example at the
+    /// start of a reporting period. The open-offer count and next auto id are kept. Only the
+    /// config admin may call this.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    pub fn reset_maker_stats(ctx: Context<ResetMakerStats>) -> Result<()> {
+        instructions::reset_maker_stats::clear_counters(ctx)
+    }
+
+    /// Blocks a mint from new offers, as either the offered or the wanted token. Only the config
+    /// admin may call this. Offers already open on the mint stay open.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `mint`: The mint to block.
+    pub fn add_blacklisted_mint(ctx: Context<UpdateBlacklist>, mint: Pubkey) -> Result<()> {
+        instructions::update_blacklist::block_mint(ctx, mint)
+    }
+
+    /// Lets a blocked mint be used in new offers again. Only the config admin may call this.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `mint`: The mint to unblock.
+    pub fn remove_blacklisted_mint(ctx: Context<UpdateBlacklist>, mint: Pubkey) -> Result<()> {
+        instructions::update_blacklist::unblock_mint(ctx, mint)
+    }
+
+    /// Permits a mint in new offers while the allowlist is enabled. Only the config admin may call
+    /// this.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `mint`: The mint to permit.
+    pub fn add_allowed_mint(ctx: Context<UpdateAllowlist>, mint: Pubkey) -> Result<()> {
+        instructions::update_allowlist::allow_mint(ctx, mint)
+    }
+
+    /// Stops permitting a mint in new offers while the allowlist is enabled. Only the config admin
+    /// may call this. Offers already open on the mint stay open.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `mint`: The mint to stop permitting.
+    pub fn remove_allowed_mint(ctx: Context<UpdateAllowlist>, mint: Pubkey) -> Result<()> {
+        instructions::update_allowlist::disallow_mint(ctx, mint)
+    }
+
+    /// Lets a taker wallet take offers without paying the protocol fee or the flat fee, as long
+    /// as it passes the fee-exempt list. Only the config admin may call this.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `taker`: The taker wallet to exempt.
+    pub fn add_fee_exempt_taker(ctx: Context<UpdateFeeExempt>, taker: Pubkey) -> Result<()> {
+        instructions::update_fee_exempt::exempt_taker(ctx, taker)
+    }
+
+    /// Makes a taker wallet pay the normal fees again. Only the config admin may call this.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `taker`: The taker wallet to stop exempting.
+    pub fn remove_fee_exempt_taker(ctx: Context<UpdateFeeExempt>, taker: Pubkey) -> Result<()> {
+        instructions::update_fee_exempt::unexempt_taker(ctx, taker)
+    }
+
+    /// Updates the global settings. Only the config admin may call this.
+    ///
+    /// # Arguments
+    /// - `ctx`: Context containing accounts required to execute the instruction.
+    /// - `fee_bps`: New protocol fee, in basis points.
+    /// - `paused`: Whether the program should be paused.
+    /// - `rebate_bps`: Rebate paid from the treasury to makers cancelling long-lived offers, in
+    ///   basis points of the unfilled Token A. 0 disables rebates.
+    /// - `min_rebate_duration`: Seconds an offer must stay open before its cancellation earns the
+    ///   rebate.
+    /// - `flat_fee_lamports`: SOL fee, in lamports, paid by the taker to the treasury on every
+    ///   take, independently of `fee_bps`. 0 disables it. The treasury PDA must hold at least the
+    ///   rent-exempt minimum before a fee below that minimum can be paid into it.
+    /// - `max_open_offers`: Most offers a single maker may have open at once, or 0 for no limit.
+    ///   Lowering it never closes offers; it only blocks new ones until makers are back under it.
+    /// - `allowlist_enabled`: Whether new offers may only use mints on the allowlist.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        fee_bps: u16,
+        paused: bool,
+        rebate_bps: u16,
+        min_rebate_duration: i64,
+        flat_fee_lamports: u64,
+        max_open_offers: u32,
+        allowlist_enabled: bool,
+    ) -> Result<()> {
+        instructions::update_config::apply_config(
+            ctx,
+            fee_bps,
+            paused,
+            rebate_bps,
+            min_rebate_duration,
+            flat_fee_lamports,
+            max_open_offers,
+            allowlist_enabled,
+        )
     }
 }
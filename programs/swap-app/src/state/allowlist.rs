@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::MAX_ALLOWLISTED_MINTS;
+
+/// Mints the admin permits in new offers while `Config::allowlist_enabled` is set, created
+/// alongside the config.
+#[account]
+#[derive(InitSpace)]
+pub struct Allowlist {
+    pub bump: u8,
+    #[max_len(MAX_ALLOWLISTED_MINTS)]
+    pub mints: Vec<Pubkey>,
+}
+
+impl Allowlist {
+    /// Whether `mint` is permitted.
+    pub fn contains(&self, mint: &Pubkey) -> bool {
+        self.mints.contains(mint)
+    }
+
+    /// Permits `mint`. Permitting an already permitted mint does nothing.
+    pub fn add(&mut self, mint: Pubkey) -> Result<()> {
+        if self.contains(&mint) {
+            return Ok(());
+        }
+        require!(self.mints.len() < MAX_ALLOWLISTED_MINTS, ErrorCode::AllowlistFull);
+        self.mints.push(mint);
+        Ok(())
+    }
+
+    /// Stops permitting `mint`. Removing a mint that is not on the list does nothing.
+    /// Offers already open on the mint are unaffected either way.
+    pub fn remove(&mut self, mint: &Pubkey) {
+        self.mints.retain(|allowed| allowed != mint);
+    }
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::MAX_BLACKLISTED_MINTS;
+
+/// Mints the admin has blocked from being offered or asked for, created alongside the config.
+#[account]
+#[derive(InitSpace)]
+pub struct Blacklist {
+    pub bump: u8,
+    #[max_len(MAX_BLACKLISTED_MINTS)]
+    pub mints: Vec<Pubkey>,
+}
+
+impl Blacklist {
+    /// Whether `mint` is blocked.
+    pub fn contains(&self, mint: &Pubkey) -> bool {
+        self.mints.contains(mint)
+    }
+
+    /// Blocks `mint`. Blocking an already blocked mint does nothing.
+    pub fn add(&mut self, mint: Pubkey) -> Result<()> {
+        if self.contains(&mint) {
+            return Ok(());
+        }
+        require!(self.mints.len() < MAX_BLACKLISTED_MINTS, ErrorCode::BlacklistFull);
+        self.mints.push(mint);
+        Ok(())
+    }
+
+    /// Unblocks `mint`. Unblocking a mint that is not blocked does nothing.
+    /// Offers already open on the mint are unaffected either way.
+    pub fn remove(&mut self, mint: &Pubkey) {
+        self.mints.retain(|blocked| blocked != mint);
+    }
+}
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,
+    pub fee_bps: u16,
+    pub bump: u8,
+    pub paused: bool,
+    pub rebate_bps: u16,
+    pub min_rebate_duration: i64,
+    /// Flat fee in lamports paid by the taker to the treasury on every take, on top of `fee_bps`.
+    pub flat_fee_lamports: u64,
+    /// Most offers a single maker may have open at once, or 0 for no limit.
+    pub max_open_offers: u32,
+    /// When set, new offers may only use mints on the `Allowlist`.
+    pub allowlist_enabled: bool,
+    /// Mint whose holders pay `discounted_fee_bps` instead of `fee_bps`, or the default pubkey for no discount.
+    pub governance_mint: Pubkey,
+    /// Smallest balance of `governance_mint` a taker's account must hold to earn the discount.
+    pub governance_threshold: u64,
+    pub discounted_fee_bps: u16,
+    /// Smallest Token A amount a new offer may offer, in base units, or 0 for no minimum.
+    pub min_offer_amount: u64,
+    /// Largest Token A amount a new offer may offer, in base units, or 0 for no maximum.
+    pub max_offer_amount: u64,
+    /// Seconds after an offer's expiry during which only its maker may close it, before anyone may sweep it.
+    pub expiry_grace_period: i64,
+}
+
+impl Config {
+    /// Returns the protocol fee charged to a taker whose governance token account holds
+    /// `governance_balance`. The discount never raises the fee above `fee_bps`.
+    pub fn taker_fee_bps(&self, governance_balance: u64) -> u16 {
+        if self.governance_mint != Pubkey::default() && governance_balance >= self.governance_threshold {
+            self.discounted_fee_bps.min(self.fee_bps)
+        } else {
+            self.fee_bps
+        }
+    }
+
+    /// Returns true if a new offer may offer `token_a_amount`, both bounds included.
+    pub fn accepts_offer_amount(&self, token_a_amount: u64) -> bool {
+        (self.min_offer_amount == 0 || token_a_amount >= self.min_offer_amount)
+            && (self.max_offer_amount == 0 || token_a_amount <= self.max_offer_amount)
+    }
+}
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::MAX_FEE_EXEMPT_TAKERS;
+
+/// Taker wallets the admin lets take offers without paying the protocol fee or the flat fee,
+/// such as partners' routing wallets. Created alongside the config.
+#[account]
+#[derive(InitSpace)]
+pub struct FeeExempt {
+    pub bump: u8,
+    #[max_len(MAX_FEE_EXEMPT_TAKERS)]
+    pub takers: Vec<Pubkey>,
+}
+
+impl FeeExempt {
+    /// Whether `taker` is exempt from fees.
+    pub fn contains(&self, taker: &Pubkey) -> bool {
+        self.takers.contains(taker)
+    }
+
+    /// Exempts `taker`. Exempting an already exempt taker does nothing.
+    pub fn add(&mut self, taker: Pubkey) -> Result<()> {
+        if self.contains(&taker) {
+            return Ok(());
+        }
+        require!(self.takers.len() < MAX_FEE_EXEMPT_TAKERS, ErrorCode::FeeExemptListFull);
+        self.takers.push(taker);
+        Ok(())
+    }
+
+    /// Stops exempting `taker`. Removing a taker that is not on the list does nothing.
+    pub fn remove(&mut self, taker: &Pubkey) {
+        self.takers.retain(|exempt| exempt != taker);
+    }
+}
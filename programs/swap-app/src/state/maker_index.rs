@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::MAX_MAKER_INDEX_OFFERS;
+
+/// Opt-in list of a maker's offers, so that clients can enumerate them without scanning every
+/// program account. Offers are appended by `make_offer` when the index is passed, and closed or
+/// transferred ones are dropped by `prune_maker_index`.
+#[account]
+#[derive(InitSpace)]
+pub struct MakerIndex {
+    pub maker: Pubkey,
+    pub bump: u8,
+    #[max_len(MAX_MAKER_INDEX_OFFERS)]
+    pub offers: Vec<Pubkey>,
+}
+
+impl MakerIndex {
+    /// Appends a newly made offer, refusing it once the index is full.
+    pub fn push(&mut self, offer: Pubkey) -> Result<()> {
+        require!(self.offers.len() < MAX_MAKER_INDEX_OFFERS, ErrorCode::MakerIndexFull);
+        self.offers.push(offer);
+        Ok(())
+    }
+}
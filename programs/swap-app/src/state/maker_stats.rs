@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+/// Per-maker bookkeeping, created the first time a maker makes an offer.
+#[account]
+#[derive(InitSpace)]
+pub struct MakerStats {
+    pub maker: Pubkey,
+    pub bump: u8,
+    /// Offers currently open with this maker as `offer.maker`, counting partially filled ones.
+    pub open_offers: u32,
+    /// Id the maker's next `make_offer_auto` offer takes. Only ever increases, so auto ids never repeat.
+    pub next_id: u64,
+    /// Offers the maker has made, for analytics. Transferred offers do not count. Reset by the admin.
+    pub offers_created: u64,
+    /// Offers of the maker that were taken in full, for analytics. Reset by the admin.
+    pub offers_taken: u64,
+}
+
+impl MakerStats {
+    /// Counts a newly opened offer, refusing it once `max_open_offers` are open. A cap of 0 means no limit.
+    pub fn record_opened(&mut self, max_open_offers: u32) -> Result<()> {
+        require!(
+            max_open_offers == 0 || self.open_offers < max_open_offers,
+            ErrorCode::TooManyOpenOffers
+        );
+        self.open_offers = self.open_offers.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Counts an offer the maker made, for analytics.
+    pub fn record_created(&mut self) {
+        self.offers_created = self.offers_created.saturating_add(1);
+    }
+
+    /// Counts an offer of the maker that was taken in full, for analytics.
+    pub fn record_taken(&mut self) {
+        self.offers_taken = self.offers_taken.saturating_add(1);
+    }
+
+    /// Hands out `id` as the maker's next auto id, refusing any other id.
+    pub fn claim_next_id(&mut self, id: u64) -> Result<()> {
+        require!(id == self.next_id, ErrorCode::OfferIdMismatch);
+        self.next_id = id.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Counts an offer that was closed. Saturates so offers made before stats were tracked
+    /// can still be closed.
+    pub fn record_closed(&mut self) {
+        self.open_offers = self.open_offers.saturating_sub(1);
+    }
+}
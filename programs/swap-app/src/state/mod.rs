@@ -1,3 +1,39 @@
 pub mod offer;
 
-pub use offer::*;
\ No newline at end of file
+pub use offer::*;
+
+pub mod config;
+
+pub use config::*;
+
+pub mod maker_stats;
+
+pub use maker_stats::*;
+
+pub mod blacklist;
+
+pub use blacklist::*;
+
+pub mod allowlist;
+
+pub use allowlist::*;
+
+pub mod fee_exempt;
+
+pub use fee_exempt::*;
+
+pub mod maker_index;
+
+pub use maker_index::*;
+
+pub mod price_feed;
+
+pub use price_feed::*;
+
+pub mod take_commitment;
+
+pub use take_commitment::*;
+
+pub mod pair_index;
+
+pub use pair_index::*;
@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+use crate::{MAX_BASKET_EXTRA_MINTS, MAX_LABEL_LEN, OFFER_SEED, SOL_WANTED_MINT};
+
 #[account]
 #[derive(InitSpace)]
 pub struct Offer {
@@ -9,4 +11,141 @@ pub struct Offer {
     pub token_mint_b: Pubkey,
     pub token_b_wanted_amount: u64,
     pub bump: u8,
-}
\ No newline at end of file
+    pub token_a_offered_amount: u64,
+    pub expiry: i64,
+    pub allowed_taker: Option<Pubkey>,
+    #[max_len(MAX_BASKET_EXTRA_MINTS)]
+    pub basket: Vec<BasketEntry>,
+    pub min_fill_amount: u64,
+    /// Token A still available to takers. `token_a_offered_amount` keeps the original size.
+    pub token_a_remaining: u64,
+    /// When the offer was made, or last topped up. Maker rebates count the offer's age from here.
+    pub created_at: i64,
+    pub token_b_destination: Option<Pubkey>,
+    #[max_len(MAX_LABEL_LEN)]
+    pub label: String,
+    pub auction: Option<DutchAuction>,
+    /// The maker who made the offer. Its address is derived from this key, not `maker`,
+    /// so the offer keeps its address when ownership is transferred.
+    pub creator: Pubkey,
+    /// Receives `royalty_bps` of every Token B payment. Ignored when `royalty_bps` is 0.
+    pub royalty_recipient: Pubkey,
+    pub royalty_bps: u16,
+    /// Decimals of the two mints, copied at creation so clients can display amounts from one fetch.
+    pub token_a_decimals: u8,
+    pub token_b_decimals: u8,
+    /// Layout version the account was written with; see `OFFER_VERSION`.
+    pub version: u8,
+    /// Price feed pricing the offer instead of `token_b_wanted_amount`, or the default pubkey for none.
+    pub oracle: Pubkey,
+    /// Premium over the oracle price the maker asks for, in basis points. Ignored without an oracle.
+    pub spread_bps: u16,
+    /// Paid the rent of the offer and its vaults, and gets it back when they close.
+    /// The default pubkey, as read from older offers, means the maker paid.
+    pub rent_payer: Pubkey,
+    /// Share of the protocol fee paid back to each taker out of `rebate_reserve`, in basis points.
+    pub taker_rebate_bps: u16,
+    /// Only fills through `reveal_take` may take the offer, so a take cannot be sniped in the mempool.
+    pub commit_only: bool,
+    /// Token B the maker escrowed to pay taker rebates, still left in the offer's reserve vault.
+    /// The vault is closed once it runs dry, so it only exists while this is above zero.
+    pub rebate_reserve: u64,
+}
+
+/// An extra token offered alongside Token A, held in its own vault owned by the offer.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct BasketEntry {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+impl Offer {
+    /// Returns true once `now` is past the offer's expiry. An expiry of 0 never expires.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expiry != 0 && now > self.expiry
+    }
+
+    /// Returns true once `now` is past the offer's expiry plus `grace_period`, when anyone may sweep it.
+    /// An expiry of 0 is never sweepable.
+    pub fn is_sweepable(&self, grace_period: i64, now: i64) -> bool {
+        self.expiry != 0 && now > self.expiry.saturating_add(grace_period)
+    }
+
+    /// Returns true if the offer wants native SOL, paid in lamports, rather than a Token B mint.
+    pub fn wants_sol(&self) -> bool {
+        self.token_mint_b == SOL_WANTED_MINT
+    }
+
+    /// Returns true if `taker` may fill the offer. Offers without an allowed taker are open to anyone.
+    pub fn can_be_taken_by(&self, taker: &Pubkey) -> bool {
+        match self.allowed_taker {
+            Some(allowed_taker) => allowed_taker == *taker,
+            None => true,
+        }
+    }
+
+    /// Returns true if the offer is priced by a price feed.
+    pub fn is_oracle_priced(&self) -> bool {
+        self.oracle != Pubkey::default()
+    }
+
+    /// Returns the account the offer's rent goes back to when it closes.
+    pub fn rent_recipient(&self) -> Pubkey {
+        if self.rent_payer == Pubkey::default() {
+            self.maker
+        } else {
+            self.rent_payer
+        }
+    }
+
+    /// Returns true if `address` is the offer's PDA derived with the canonical bump, which is
+    /// the only bump `make_offer` stores. Re-derives the address, so it costs a few thousand CUs.
+    pub fn has_canonical_address(&self, address: &Pubkey) -> bool {
+        derive_offer_address(&self.creator, self.id) == (*address, self.bump)
+    }
+}
+
+/// Dutch-auction terms: the Token B wanted for the whole offer falls linearly from `start_amount`
+/// at `start_ts` to `floor_amount` at `end_ts`, and stays at the floor afterwards.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct DutchAuction {
+    pub start_amount: u64,
+    pub floor_amount: u64,
+    pub start_ts: i64,
+    pub end_ts: i64,
+}
+
+impl DutchAuction {
+    /// Returns true if the price only ever falls, over a non-empty period, to a non-zero floor.
+    pub fn is_valid(&self) -> bool {
+        self.floor_amount > 0 && self.start_amount >= self.floor_amount && self.end_ts > self.start_ts
+    }
+
+    /// Returns the Token B wanted for the whole offer at `now`, rounded down.
+    /// Only meaningful for auctions that pass `is_valid`.
+    pub fn current_amount(&self, now: i64) -> u64 {
+        if now <= self.start_ts {
+            return self.start_amount;
+        }
+        if now >= self.end_ts {
+            return self.floor_amount;
+        }
+
+        // The decay so far can never exceed the full drop, so the narrowing back to u64 is lossless.
+        let elapsed = (now - self.start_ts) as u128;
+        let duration = (self.end_ts - self.start_ts) as u128;
+        let drop = (self.start_amount - self.floor_amount) as u128;
+        self.start_amount - (drop * elapsed / duration) as u64
+    }
+}
+
+/// Derives the address and bump of the offer `creator` made with `id`, using the same seeds
+/// as `MakeOffer`. Clients should call this rather than rebuilding the seeds themselves.
+/// Offers keep this address after a transfer, so pass the original maker, not the current one.
+///
+/// The seeds are, byte for byte: `OFFER_SEED` (the 5 bytes `b"offer"`), the creator's 32-byte
+/// public key, and `id` as exactly 8 little-endian bytes. Every `u64` id, from 0 to `u64::MAX`,
+/// encodes to 8 bytes, so no id is truncated or padded; clients must encode ids the same way.
+pub fn derive_offer_address(creator: &Pubkey, id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[OFFER_SEED, creator.as_ref(), &id.to_le_bytes()], &crate::ID)
+}
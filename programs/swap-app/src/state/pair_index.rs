@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::MAX_PAIR_INDEX_OFFERS;
+
+/// Opt-in list of the open offers of one pair, so that order books can be built without scanning
+/// every program account. Offers are appended by `make_offer` and removed by `cancel_offer` and
+/// the take that fills them when the index is passed; offers closed any other way are dropped by
+/// `prune_pair_index`.
+#[account]
+#[derive(InitSpace)]
+pub struct PairIndex {
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub bump: u8,
+    #[max_len(MAX_PAIR_INDEX_OFFERS)]
+    pub offers: Vec<Pubkey>,
+}
+
+impl PairIndex {
+    /// Appends a newly made offer, refusing it once the index is full.
+    pub fn push(&mut self, offer: Pubkey) -> Result<()> {
+        require!(self.offers.len() < MAX_PAIR_INDEX_OFFERS, ErrorCode::PairIndexFull);
+        self.offers.push(offer);
+        Ok(())
+    }
+
+    /// Removes a closed offer. Offers that are not listed are ignored.
+    pub fn remove(&mut self, offer: &Pubkey) {
+        self.offers.retain(|indexed| indexed != offer);
+    }
+}
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::{Offer, BASIS_POINTS_DIVISOR, MAX_ORACLE_AGE};
+
+/// A minimal push oracle read by oracle-priced offers. Its authority publishes the value of one
+/// whole Token A in whole Token B as `price * 10^expo`, in the style of a Pyth price. An adapter
+/// for a Pyth or Switchboard feed would fill the same role.
+#[account]
+#[derive(InitSpace)]
+pub struct PriceFeed {
+    pub authority: Pubkey,
+    pub price: u64,
+    pub expo: i32,
+    /// When `price` was last published.
+    pub publish_time: i64,
+}
+
+impl PriceFeed {
+    /// Returns the Token B wanted for `token_a_amount` of the offer's Token A at the published
+    /// price plus the offer's `spread_bps`, rounded up at each step so that a fill is never
+    /// undercharged. Refuses prices older than `MAX_ORACLE_AGE`.
+    pub fn token_b_amount(&self, offer: &Offer, token_a_amount: u64, now: i64) -> Result<u64> {
+        require!(
+            now.saturating_sub(self.publish_time) <= MAX_ORACLE_AGE,
+            ErrorCode::StaleOracle
+        );
+
+        // Convert whole-token prices into base units using the decimals copied onto the offer.
+        let exponent = self.expo + offer.token_b_decimals as i32 - offer.token_a_decimals as i32;
+        let scale = 10u128
+            .checked_pow(exponent.unsigned_abs())
+            .ok_or(ErrorCode::MathOverflow)?;
+        let value = (token_a_amount as u128)
+            .checked_mul(self.price as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let value = if exponent >= 0 {
+            value.checked_mul(scale).ok_or(ErrorCode::MathOverflow)?
+        } else {
+            value
+                .checked_add(scale - 1)
+                .ok_or(ErrorCode::MathOverflow)?
+                / scale
+        };
+
+        // The spread is the maker's premium over the oracle price.
+        let value = value
+            .checked_mul(BASIS_POINTS_DIVISOR as u128 + offer.spread_bps as u128)
+            .and_then(|value| value.checked_add(BASIS_POINTS_DIVISOR as u128 - 1))
+            .ok_or(ErrorCode::MathOverflow)?
+            / BASIS_POINTS_DIVISOR as u128;
+        u64::try_from(value).map_err(|_| error!(ErrorCode::MathOverflow))
+    }
+}
@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+
+/// A taker's hidden commitment to take an offer, made by `commit_take` and consumed by `reveal_take`.
+#[account]
+#[derive(InitSpace)]
+pub struct TakeCommitment {
+    pub taker: Pubkey,
+    pub offer: Pubkey,
+    /// SHA-256 of the taker's key followed by their secret nonce.
+    pub hash: [u8; 32],
+    /// Slot the commitment was made in.
+    pub slot: u64,
+    pub bump: u8,
+}
+
+impl TakeCommitment {
+    /// Returns the hash a taker commits to for `nonce`.
+    pub fn hash_of(taker: &Pubkey, nonce: &[u8; 32]) -> [u8; 32] {
+        hashv(&[taker.as_ref(), nonce]).to_bytes()
+    }
+
+    /// Returns true if `nonce` is the preimage the taker committed to.
+    pub fn is_revealed_by(&self, nonce: &[u8; 32]) -> bool {
+        Self::hash_of(&self.taker, nonce) == self.hash
+    }
+}
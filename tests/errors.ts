@@ -0,0 +1,166 @@
+// Importing Anchor framework to interact with Solana programs.
+import * as anchor from "@coral-xyz/anchor";
+import type { Program } from "@coral-xyz/anchor";
+
+// Assertion library for test validations.
+import { assert } from "chai";
+
+import type { SwapApp } from "../target/types/swap_app";
+
+// Every custom error of the program with the code clients see for it. Anchor numbers the variants
+// of `ErrorCode` from 6000 in declaration order, so a variant inserted, removed or reordered anywhere
+// but the end renumbers every error after it. New variants are appended here and in `error.rs`.
+const ERROR_CODES: [string, number][] = [
+  ["SameMintNotAllowed", 6000],
+  ["ZeroAmount", 6001],
+  ["OfferExpired", 6002],
+  ["FillExceedsOffer", 6003],
+  ["FillTooSmall", 6004],
+  ["InvalidFeeBps", 6005],
+  ["Unauthorized", 6006],
+  ["ProgramPaused", 6007],
+  ["SelfTradeNotAllowed", 6008],
+  ["TransferFeeMintUnsupported", 6009],
+  ["MathOverflow", 6010],
+  ["UnauthorizedTaker", 6011],
+  ["VaultBalanceMismatch", 6012],
+  ["BasketTooLarge", 6013],
+  ["BasketAccountsMismatch", 6014],
+  ["DuplicateBasketMint", 6015],
+  ["BasketRequiresFullFill", 6016],
+  ["BelowMinimumFill", 6017],
+  ["WantedMintMismatch", 6018],
+  ["SlippageExceeded", 6019],
+  ["BatchTooLarge", 6020],
+  ["BatchAccountsMismatch", 6021],
+  ["VaultNotEmpty", 6022],
+  ["WantedDestinationMismatch", 6023],
+  ["OfferNotStale", 6024],
+  ["LabelTooLong", 6025],
+  ["InsufficientVaultBalance", 6026],
+  ["FrozenTokenAccount", 6027],
+  ["InvalidAuction", 6028],
+  ["AuctionOfferCannotBeRepriced", 6029],
+  ["InvalidOfferBump", 6030],
+  ["RoyaltyAccountMismatch", 6031],
+  ["InsufficientTakerBalance", 6032],
+  ["TooManyOpenOffers", 6033],
+  ["OwnershipTransferToSelf", 6034],
+  ["MintBlacklisted", 6035],
+  ["BlacklistFull", 6036],
+  ["MintNotAllowed", 6037],
+  ["AllowlistFull", 6038],
+  ["PermanentDelegateNotAllowed", 6039],
+  ["MakerIndexFull", 6040],
+  ["ExpiryTooSoon", 6041],
+  ["InvalidOfferVersion", 6042],
+  ["OfferShrinkNotAllowed", 6043],
+  ["InvalidTokenProgram", 6044],
+  ["StaleOracle", 6045],
+  ["OracleAccountMismatch", 6046],
+  ["InvalidOracle", 6047],
+  ["OracleOfferCannotBeRepriced", 6048],
+  ["CommitmentMismatch", 6049],
+  ["RevealTooEarly", 6050],
+  ["AccountAliasConflict", 6051],
+  ["RentPayerMismatch", 6052],
+  ["OfferIdMismatch", 6053],
+  ["InsufficientTreasuryBalance", 6054],
+  ["VaultMintMismatch", 6055],
+  ["VaultAccountMismatch", 6056],
+  ["VaultSurplus", 6057],
+  ["InvalidExpiry", 6058],
+  ["MemoTooLong", 6059],
+  ["GovernanceMintMismatch", 6060],
+  ["LadderTooLarge", 6061],
+  ["InvalidVaultAuthority", 6062],
+  ["OfferAmountOutOfRange", 6063],
+  ["InvalidOfferAmountLimits", 6064],
+  ["PairIndexFull", 6065],
+  ["MakerMismatch", 6066],
+  ["OfferNotSweepable", 6067],
+  ["InvalidGracePeriod", 6068],
+  ["OfferNotForSol", 6069],
+  ["FeeExemptListFull", 6070],
+  ["DepositMismatch", 6071],
+  ["InsufficientRent", 6072],
+  ["RevealTooLate", 6073],
+  ["FillRoundsToZero", 6074],
+  ["VaultUnderfunded", 6075],
+  ["CommitRevealRequired", 6076],
+  ["RecipientNotRentExempt", 6077],
+  ["OfferNotExpired", 6078],
+  ["RebateReserveMismatch", 6079],
+];
+
+// Errors no transaction can trigger, which are the only ones no test raises. Every other error is
+// raised by a test building its failing precondition, checked once the whole suite has run.
+const UNREACHABLE_ERRORS = new Set([
+  // Only raised by `check_token_program`, whose callers take the token program as an
+  // `Interface<TokenInterface>`, which refuses any other program before the handler runs.
+  "InvalidTokenProgram",
+  // No longer raised: the vault's `associated_token` constraints reject a foreign vault with
+  // `ConstraintTokenOwner` first.
+  "InvalidVaultAuthority",
+  // No longer raised: the offer's `has_one = maker` rejects another maker with `ConstraintHasOne`
+  // first.
+  "MakerMismatch",
+]);
+
+// Names of the errors the tests have seen the program raise, recorded by `assertAnchorError`.
+const raisedErrors = new Set<string>();
+
+// Assert that `error` is the given Anchor error, at its pinned code when it is one of the program's
+// own, and record it as raised. Returns the error so callers can inspect its logs.
+export const assertAnchorError = (error: unknown, code: string): anchor.AnchorError => {
+  assert.instanceOf(error, anchor.AnchorError);
+  const anchorError = error as anchor.AnchorError;
+  assert.equal(anchorError.error.errorCode.code, code);
+  const pinned = ERROR_CODES.find(([name]) => name === code);
+  if (pinned) {
+    assert.equal(anchorError.error.errorCode.number, pinned[1]);
+  }
+  raisedErrors.add(code);
+  return anchorError;
+};
+
+// Assert that a transaction fails with the given Anchor error.
+export const expectAnchorError = async (promise: Promise<unknown>, code: string) => {
+  try {
+    await promise;
+  } catch (error) {
+    assertAnchorError(error, code);
+    return;
+  }
+  assert.fail(`Expected the transaction to fail with ${code}`);
+};
+
+// Describe the checks that pin the program's error codes.
+describe("errors", () => {
+  // Set up the Anchor environment using configuration from Anchor.toml.
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  // Reference to the compiled SwapApp program.
+  const program = anchor.workspace.SwapApp as Program<SwapApp>;
+
+  it("Keeps every error at the code clients match on", () => {
+    // The raw IDL keeps the Rust variant names that `AnchorError` reports.
+    const idlErrors = (program.rawIdl.errors ?? []).map(({ name, code }) => [name, code]);
+    assert.deepEqual(idlErrors, ERROR_CODES);
+  });
+});
+
+// Once every test file has run, each reachable error must have been raised at its code by a test
+// building its failing precondition, and no error listed as unreachable may have been. Running only
+// part of the suite leaves errors unraised, so this only holds for a full run.
+after("Checks every reachable error was raised by a test", () => {
+  const names = ERROR_CODES.map(([name]) => name);
+  assert.deepEqual(
+    names.filter((name) => !UNREACHABLE_ERRORS.has(name) && !raisedErrors.has(name)),
+    []
+  );
+  assert.deepEqual(
+    names.filter((name) => UNREACHABLE_ERRORS.has(name) && raisedErrors.has(name)),
+    []
+  );
+});
@@ -1,5 +1,5 @@
-// Import cryptographic utilities to generate random bytes.
-import { randomBytes } from "node:crypto";
+// Import cryptographic utilities to generate random bytes and hash commitments.
+import { createHash, randomBytes } from "node:crypto";
 
 // Importing Anchor framework to interact with Solana programs.
 import * as anchor from "@coral-xyz/anchor";
@@ -10,14 +10,39 @@ import { BN, type Program } from "@coral-xyz/anchor";
 // Importing token utilities and constants from the Solana SPL Token library.
 import {
   TOKEN_2022_PROGRAM_ID, // Identifier for the 2022 Token Program.
-  type TOKEN_PROGRAM_ID, // Default Token Program Identifier type.
+  TOKEN_PROGRAM_ID, // Default Token Program Identifier.
+  ASSOCIATED_TOKEN_PROGRAM_ID, // Associated Token Program Identifier.
+  ACCOUNT_SIZE, // Size of a token account without extensions.
+  NATIVE_MINT, // Wrapped SOL mint of the default Token Program.
+  ExtensionType, // Token-2022 mint extensions.
+  createAccount, // Function to create a token account at a new keypair address.
+  createMint, // Function to create a new mint.
+  createWrappedNativeAccount, // Function to create and fund a wrapped SOL account.
+  freezeAccount, // Function to freeze a token account.
+  createInitializeMintInstruction, // Instruction to initialize a mint.
+  createInitializePermanentDelegateInstruction, // Instruction to add a permanent delegate to a mint.
+  createInitializeTransferFeeConfigInstruction, // Instruction to add a transfer fee to a mint.
   getAssociatedTokenAddressSync, // Function to derive associated token addresses.
+  getMint, // Function to fetch a mint account.
+  getMintLen, // Function to size a mint account with extensions.
+  getOrCreateAssociatedTokenAccount, // Function to create a token account if it is missing.
+  mintTo, // Function to mint tokens to an account.
+  transfer, // Function to transfer tokens between accounts.
 } from "@solana/spl-token";
 
 // Import Solana web3.js utilities, like lamports and public keys.
-import { LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+  type TransactionInstruction,
+  sendAndConfirmTransaction,
+} from "@solana/web3.js";
 
 // Assertion library for test validations.
+import * as chai from "chai";
 import { assert } from "chai";
 
 // Importing custom helper utilities for testing and keypair generation.
@@ -29,6 +54,9 @@ import {
 
 import type { SwapApp } from "../target/types/swap_app";
 
+// Error assertions, which record each error raised for the coverage check in errors.ts.
+import { assertAnchorError, expectAnchorError } from "./errors";
+
 // Use either the legacy Token Program or the 2022 Token Extensions Program.
 const TOKEN_PROGRAM: typeof TOKEN_2022_PROGRAM_ID | typeof TOKEN_PROGRAM_ID =
   TOKEN_2022_PROGRAM_ID;
@@ -78,6 +106,270 @@ describe("swap", async () => {
   const tokenAOfferedAmount = new BN(1_000_000);
   const tokenBWantedAmount = new BN(1_000_000);
 
+  // An expiry of 0 means the offer never expires.
+  const NO_EXPIRY = new BN(0);
+
+  // A minimum fill of 0 lets takers fill any part of an offer.
+  const NO_MIN_FILL = new BN(0);
+
+  // A rebate reserve of 0 escrows no Token B, for offers without a taker rebate.
+  const NO_REBATE_RESERVE = new BN(0);
+
+  // A Token B limit of u64::MAX accepts whatever the offer currently asks.
+  const NO_PRICE_LIMIT = new BN("18446744073709551615");
+
+  // Protocol fee charged on Token B, in basis points (0.25%).
+  const FEE_BPS = 25;
+
+  // Compute the protocol fee the program takes from a Token B payment.
+  const protocolFee = (amount: BN, feeBps = FEE_BPS) =>
+    amount.muln(feeBps).divn(10_000);
+
+  // Derive the offer PDA and its vault for a given offer ID.
+  const deriveOfferAccounts = (
+    offerId: BN,
+    maker = accounts.maker,
+    tokenMintA = accounts.tokenMintA
+  ) => {
+    const offer = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("offer"), // Prefix for seeds.
+        maker.toBuffer(), // Maker's public key.
+        offerId.toArrayLike(Buffer, "le", 8), // Offer ID as little-endian.
+      ],
+      program.programId
+    )[0];
+
+    const vault = getAssociatedTokenAddressSync(
+      tokenMintA, // Token A mint.
+      offer, // Offer account as the owner.
+      true, // Allow the owner to be off-curve (a PDA).
+      TOKEN_PROGRAM
+    );
+
+    return { offer, vault };
+  };
+
+  // Derive the stats PDA counting a maker's open offers.
+  const deriveMakerStats = (maker: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("maker"), maker.toBuffer()],
+      program.programId
+    )[0];
+
+  // Build the remaining accounts passing an offer's rebate reserve: Token B's mint, the offer's
+  // reserve vault for it, and the maker's Token B account.
+  const rebateReserveAccounts = (offer: PublicKey) => [
+    { pubkey: accounts.tokenMintB, isSigner: false, isWritable: false },
+    {
+      pubkey: getAssociatedTokenAddressSync(accounts.tokenMintB, offer, true, TOKEN_PROGRAM),
+      isSigner: false,
+      isWritable: true,
+    },
+    { pubkey: accounts.makerTokenAccountB, isSigner: false, isWritable: true },
+  ];
+
+  // Have Alice make an offer paying takers `takerRebateBps` of their fee back out of `rebateReserve`
+  // of her Token B, passing the reserve's accounts unless `withReserve` is unset.
+  const makeRebateOffer = (
+    offerId: BN,
+    takerRebateBps: number,
+    rebateReserve: BN,
+    withReserve = true
+  ) => {
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    return program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        takerRebateBps,
+        false,
+        rebateReserve
+      )
+      .accounts({ ...accounts, offer, vault })
+      .remainingAccounts(withReserve ? rebateReserveAccounts(offer) : [])
+      .signers([alice])
+      .rpc();
+  };
+
+  // Give Alice `amount` of Bob's Token B, for her to escrow as a rebate reserve.
+  const fundAliceTokenB = (amount: BN) =>
+    transfer(
+      connection,
+      payer,
+      accounts.takerTokenAccountB,
+      accounts.makerTokenAccountB,
+      bob,
+      amount.toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+  // Build the `check_offer_funding` instruction placed ahead of creating the offers with `offerIds`,
+  // which deposit `tokenAOffered` of `tokenMintA` in total and whose rent `rentPayer` pays.
+  const checkOfferFunding = (
+    maker: PublicKey,
+    rentPayer: PublicKey,
+    offerIds: BN[],
+    tokenAOffered: BN,
+    tokenMintA = accounts.tokenMintA,
+    tokenProgram = TOKEN_PROGRAM
+  ) =>
+    program.methods
+      .checkOfferFunding(offerIds, tokenAOffered, 0)
+      .accounts({ maker, rentPayer, tokenMintA, tokenProgram })
+      .remainingAccounts(
+        offerIds.flatMap((offerId) => {
+          const offer = PublicKey.findProgramAddressSync(
+            [Buffer.from("offer"), maker.toBuffer(), offerId.toArrayLike(Buffer, "le", 8)],
+            program.programId
+          )[0];
+          const vault = getAssociatedTokenAddressSync(tokenMintA, offer, true, tokenProgram);
+          return [offer, vault].map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }));
+        })
+      )
+      .instruction();
+
+  // Have Alice make a fresh offer and return its derived accounts.
+  const createOffer = async (
+    offerId: BN,
+    offeredAmount = tokenAOfferedAmount,
+    wantedAmount = tokenBWantedAmount,
+    expiry = NO_EXPIRY,
+    allowedTaker: PublicKey | null = null,
+    minFillAmount = NO_MIN_FILL,
+    label = "",
+    auction: {
+      startAmount: BN;
+      floorAmount: BN;
+      startTs: BN;
+      endTs: BN;
+    } | null = null
+  ) => {
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const signature = await program.methods
+      .makeOffer(
+        offerId,
+        offeredAmount,
+        wantedAmount,
+        expiry,
+        allowedTaker,
+        [],
+        minFillAmount,
+        null,
+        label,
+        auction,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, signature);
+    return { offer, vault };
+  };
+
+  // Create a Token-2022 mint with the given extensions, initialized by `extensionInstructions`.
+  const createToken2022Mint = async (
+    extensions: ExtensionType[],
+    extensionInstructions: (mint: PublicKey) => TransactionInstruction[],
+    decimals = 6
+  ) => {
+    const mint = Keypair.generate();
+    const mintLen = getMintLen(extensions);
+    const lamports = await connection.getMinimumBalanceForRentExemption(mintLen);
+    const transaction = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mint.publicKey,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      ...extensionInstructions(mint.publicKey),
+      createInitializeMintInstruction(
+        mint.publicKey,
+        decimals,
+        payer.publicKey,
+        null,
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    await sendAndConfirmTransaction(connection, transaction, [payer, mint]);
+    return mint.publicKey;
+  };
+
+  // Create a wallet funded with SOL but holding no tokens.
+  const createFundedWallet = async (lamports = 2 * LAMPORTS_PER_SOL) => {
+    const wallet = Keypair.generate();
+    const signature = await connection.requestAirdrop(wallet.publicKey, lamports);
+    await confirmTransaction(connection, signature);
+    return wallet;
+  };
+
+  // Read the token balance of a token account as a big number.
+  const getTokenBalance = async (tokenAccount: PublicKey) => {
+    const response = await connection.getTokenAccountBalance(tokenAccount);
+    return new BN(response.value.amount);
+  };
+
+  // Read the validator's current unix timestamp.
+  const getValidatorTime = async () => {
+    const slot = await connection.getSlot();
+    return new BN(await connection.getBlockTime(slot));
+  };
+
+  // Shortest lifetime the program allows an offer with an expiry.
+  const MIN_OFFER_DURATION = 60;
+
+  // Oldest price, in seconds, an oracle-priced offer accepts from its feed.
+  const MAX_ORACLE_AGE = 60;
+
+  // Most compute units a complete take of a plain offer may consume, with all accounts already open.
+  const TAKE_COMPUTE_BUDGET = 100_000;
+
+  // Longest memo, in bytes, a taker can attach to a take, and the SPL Memo program that logs it.
+  const MAX_MEMO_LEN = 128;
+  const MEMO_PROGRAM_ID = new PublicKey("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+  // Wait until the validator's clock has passed `timestamp`.
+  const waitForValidatorTime = async (timestamp: BN) => {
+    while ((await getValidatorTime()).lte(timestamp)) {
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    }
+  };
+
+  // Parse the Anchor events emitted by a confirmed transaction.
+  const getEvents = async (signature: string) => {
+    const transaction = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const eventParser = new anchor.EventParser(
+      program.programId,
+      new anchor.BorshCoder(program.idl)
+    );
+    return Array.from(eventParser.parseLogs(transaction.meta.logMessages));
+  };
+
   // Before all tests, create accounts, mints, and token accounts.
   before(
     "Creates Alice and Bob accounts, 2 token mints, and associated token accounts for both users",
@@ -118,6 +410,8 @@ describe("swap", async () => {
       const bobTokenAccountB = tokenAccounts[1][1];
 
       accounts.maker = alice.publicKey;
+      accounts.canceller = alice.publicKey;
+      accounts.rentPayer = alice.publicKey;
       accounts.taker = bob.publicKey;
       accounts.tokenMintA = tokenMintA.publicKey;
       accounts.makerTokenAccountA = aliceTokenAccountA;
@@ -125,6 +419,30 @@ describe("swap", async () => {
       accounts.tokenMintB = tokenMintB.publicKey;
       accounts.makerTokenAccountB = aliceTokenAccountB;
       accounts.takerTokenAccountB = bobTokenAccountB;
+
+      // Derive the global config and the treasury that collects protocol fees.
+      accounts.config = PublicKey.findProgramAddressSync(
+        [Buffer.from("config")],
+        program.programId
+      )[0];
+      accounts.treasury = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury")],
+        program.programId
+      )[0];
+      accounts.treasuryTokenAccountB = getAssociatedTokenAddressSync(
+        accounts.tokenMintB,
+        accounts.treasury,
+        true,
+        TOKEN_PROGRAM
+      );
+
+      // Create the global config with the protocol fee, making the payer its admin.
+      const initializeConfigSignature = await program.methods
+        .initializeConfig(FEE_BPS)
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, initializeConfigSignature);
     }
   );
 
@@ -155,7 +473,25 @@ describe("swap", async () => {
 
     // Call the `makeOffer` method on the program.
     const transactionSignature = await program.methods
-      .makeOffer(offerId, tokenAOfferedAmount, tokenBWantedAmount)
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
       .accounts({ ...accounts })
       .signers([alice]) // Alice signs the transaction.
       .rpc();
@@ -173,12 +509,25 @@ describe("swap", async () => {
     assert(offerAccount.tokenMintA.equals(accounts.tokenMintA));
     assert(offerAccount.tokenMintB.equals(accounts.tokenMintB));
     assert(offerAccount.tokenBWantedAmount.eq(tokenBWantedAmount));
+    assert(offerAccount.tokenAOfferedAmount.eq(tokenAOfferedAmount));
+    assert(offerAccount.tokenARemaining.eq(tokenAOfferedAmount));
+
+    // Validate the OfferMade event describes the new offer.
+    const [offerMade] = (await getEvents(transactionSignature)).filter(
+      (event) => event.name === "offerMade"
+    );
+    assert(offerMade.data.id.eq(offerId));
+    assert(offerMade.data.maker.equals(alice.publicKey));
+    assert(offerMade.data.tokenMintA.equals(accounts.tokenMintA));
+    assert(offerMade.data.tokenMintB.equals(accounts.tokenMintB));
+    assert(offerMade.data.tokenAOfferedAmount.eq(tokenAOfferedAmount));
+    assert(offerMade.data.tokenBWantedAmount.eq(tokenBWantedAmount));
   }).slow(ANCHOR_SLOW_TEST_THRESHOLD); // Mark this test as slow if it exceeds the threshold.
 
   // Test for Bob taking an offer.
   it("Puts the tokens from the vault into Bob's account, and gives Alice Bob's tokens, when Bob takes an offer", async () => {
     const transactionSignature = await program.methods
-      .takeOffer()
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
       .accounts({ ...accounts })
       .signers([bob]) // Bob signs the transaction.
       .rpc();
@@ -193,12 +542,6750 @@ describe("swap", async () => {
     );
     assert(bobTokenAccountBalanceAfter.eq(tokenAOfferedAmount));
 
-    // Validate that Alice's account received the wanted tokens.
+    // Validate that Alice's account received the wanted tokens, minus the protocol fee.
     const aliceTokenAccountBalanceAfterResponse =
       await connection.getTokenAccountBalance(accounts.makerTokenAccountB);
     const aliceTokenAccountBalanceAfter = new BN(
       aliceTokenAccountBalanceAfterResponse.value.amount
     );
-    assert(aliceTokenAccountBalanceAfter.eq(tokenBWantedAmount));
+    const fee = protocolFee(tokenBWantedAmount);
+    assert(aliceTokenAccountBalanceAfter.eq(tokenBWantedAmount.sub(fee)));
+
+    // Validate that the treasury received the protocol fee.
+    const treasuryBalanceAfter = await getTokenBalance(
+      accounts.treasuryTokenAccountB
+    );
+    assert(treasuryBalanceAfter.eq(fee));
+
+    // Validate the OfferTaken event describes the completed trade.
+    const [offerTaken] = (await getEvents(transactionSignature)).filter(
+      (event) => event.name === "offerTaken"
+    );
+    assert(offerTaken.data.maker.equals(alice.publicKey));
+    assert(offerTaken.data.taker.equals(bob.publicKey));
+    assert(offerTaken.data.tokenMintA.equals(accounts.tokenMintA));
+    assert(offerTaken.data.tokenMintB.equals(accounts.tokenMintB));
+    assert(offerTaken.data.tokenAOfferedAmount.eq(tokenAOfferedAmount));
+    assert(offerTaken.data.tokenBWantedAmount.eq(tokenBWantedAmount));
   }).slow(ANCHOR_SLOW_TEST_THRESHOLD); // Mark this test as slow if it exceeds the threshold.
+
+  // Test for Alice cancelling an offer nobody has taken.
+  it("Returns the vaulted tokens to Alice and closes the offer when Alice cancels an offer", async () => {
+    const offerId = getRandomBigNumber(); // Generate a random offer ID.
+    const { offer, vault } = deriveOfferAccounts(offerId);
+
+    const aliceBalanceBefore = await getTokenBalance(accounts.makerTokenAccountA);
+
+    // Alice makes a fresh offer so there is something to cancel.
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    // Alice cancels it again.
+    const cancelSignature = await program.methods
+      .cancelOffer(false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, cancelSignature);
+
+    // Validate Alice got all of her Token A back.
+    const aliceBalanceAfter = await getTokenBalance(accounts.makerTokenAccountA);
+    assert(aliceBalanceAfter.eq(aliceBalanceBefore));
+
+    // Validate both the offer and the vault are gone.
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert.isNull(await connection.getAccountInfo(vault));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer cannot swap a mint for itself.
+  it("Rejects an offer where Token A and Token B are the same mint", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+
+    await expectAnchorError(
+      program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({
+          ...accounts,
+          tokenMintB: accounts.tokenMintA, // Ask for the same mint that is offered.
+          offer,
+          vault,
+        })
+        .signers([alice])
+        .rpc(),
+      "SameMintNotAllowed"
+    );
+
+    // Validate nothing was created for the rejected offer.
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer giving away Token A for nothing is rejected.
+  it("Rejects an offer with a zero offered amount", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+
+    await expectAnchorError(
+      program.methods
+        .makeOffer(
+          offerId,
+          new BN(0),
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc(),
+      "ZeroAmount"
+    );
+
+    // Validate no vault was left behind for the rejected offer.
+    assert.isNull(await connection.getAccountInfo(vault));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer asking for nothing in return is rejected.
+  it("Rejects an offer with a zero wanted amount", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+
+    await expectAnchorError(
+      program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          new BN(0),
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc(),
+      "ZeroAmount"
+    );
+
+    // Validate no vault was left behind for the rejected offer.
+    assert.isNull(await connection.getAccountInfo(vault));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an expired offer cannot be taken.
+  it("Rejects taking an offer after its expiry has passed", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+
+    // Alice makes an offer expiring as soon as the program allows.
+    const expiry = (await getValidatorTime()).addn(MIN_OFFER_DURATION + 5);
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        expiry,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    // Validate the expiry was saved on the offer.
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.expiry.eq(expiry));
+
+    // Once the deadline has passed, Bob can no longer take it.
+    await waitForValidatorTime(expiry);
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "OfferExpired"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test for Bob filling an offer in two halves.
+  it("Lets Bob take half of an offer, keeping the rest open, and then the other half", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const halfOfferedAmount = tokenAOfferedAmount.divn(2);
+    const halfWantedAmount = tokenBWantedAmount.divn(2);
+
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    const bobBalanceABefore = await getTokenBalance(accounts.takerTokenAccountA);
+    const aliceBalanceBBefore = await getTokenBalance(accounts.makerTokenAccountB);
+
+    // Bob takes the first half.
+    const firstTakeSignature = await program.methods
+      .takeOffer(halfOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, firstTakeSignature);
+
+    // Validate the offer now tracks what is left, while keeping its original size.
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.tokenAOfferedAmount.eq(tokenAOfferedAmount));
+    assert(offerAccount.tokenARemaining.eq(halfOfferedAmount));
+    assert(offerAccount.tokenBWantedAmount.eq(halfWantedAmount));
+    assert((await getTokenBalance(vault)).eq(halfOfferedAmount));
+    assert(
+      (await getTokenBalance(accounts.makerTokenAccountB)).eq(
+        aliceBalanceBBefore.add(halfWantedAmount).sub(protocolFee(halfWantedAmount))
+      )
+    );
+
+    // Bob takes the second half, which completes the offer.
+    const secondTakeSignature = await program.methods
+      .takeOffer(halfOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, secondTakeSignature);
+
+    // Validate the final fill reported nothing remaining.
+    const [offerTaken] = (await getEvents(secondTakeSignature)).filter(
+      (event) => event.name === "offerTaken"
+    );
+    assert(offerTaken.data.tokenARemaining.isZero());
+
+    // Validate Bob received everything and both accounts are closed.
+    assert(
+      (await getTokenBalance(accounts.takerTokenAccountA)).eq(
+        bobBalanceABefore.add(tokenAOfferedAmount)
+      )
+    );
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert.isNull(await connection.getAccountInfo(vault));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the smallest partial fill still pays for its Token A, and that nothing is taken for free.
+  it("Rounds a tiny partial fill's Token B price up to one base unit instead of zero", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+
+    // Alice asks for two base units of Token B for the whole offer.
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        new BN(2),
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    // Taking a single unit of Token A is worth 2 / 1_000_000 Token B, which rounds up to one unit.
+    const bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+    const signature = await program.methods
+      .takeOffer(new BN(1), NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, signature);
+    assert(bobBalanceBBefore.sub(await getTokenBalance(accounts.takerTokenAccountB)).eqn(1));
+    assert((await program.account.offer.fetch(offer)).tokenBWantedAmount.eqn(1));
+
+    // Another single unit would also round up to the last unit wanted, leaving the rest of
+    // Alice's Token A free, so it is refused.
+    await expectAnchorError(
+      program.methods
+        .takeOffer(new BN(1), NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "FillRoundsToZero"
+    );
+
+    // A fill of no Token A costs nothing and is refused too.
+    await expectAnchorError(
+      program.methods
+        .takeOffer(new BN(0), NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "FillTooSmall"
+    );
+
+    // Taking everything that is left pays the last unit and closes the offer.
+    const remaining = (await program.account.offer.fetch(offer)).tokenARemaining;
+    const takeRestSignature = await program.methods
+      .takeOffer(remaining, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeRestSignature);
+    assert(bobBalanceBBefore.sub(await getTokenBalance(accounts.takerTokenAccountB)).eqn(2));
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the config was initialized with the payer as admin.
+  it("Initializes the config once with the signer as admin", async () => {
+    const configAccount = await program.account.config.fetch(accounts.config);
+    assert(configAccount.admin.equals(payer.publicKey));
+    assert.equal(configAccount.feeBps, FEE_BPS);
+    assert.isFalse(configAccount.paused);
+
+    // A second initialization must fail because the config PDA already exists.
+    try {
+      await program.methods
+        .initializeConfig(0)
+        .accounts({ admin: bob.publicKey, config: accounts.config })
+        .signers([bob])
+        .rpc();
+      assert.fail("Expected the second initialization to fail");
+    } catch (error) {
+      assert.notInstanceOf(error, chai.AssertionError);
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that only the admin can change the config.
+  it("Rejects a config update from someone other than the admin", async () => {
+    await expectAnchorError(
+      program.methods
+        .updateConfig(0, true, 0, new BN(0), new BN(0), 0, false)
+        .accounts({ admin: bob.publicKey, config: accounts.config })
+        .signers([bob])
+        .rpc(),
+      "Unauthorized"
+    );
+
+    // Validate the settings are unchanged.
+    const configAccount = await program.account.config.fetch(accounts.config);
+    assert.equal(configAccount.feeBps, FEE_BPS);
+    assert.isFalse(configAccount.paused);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the admin can change the config.
+  it("Lets the admin update the config", async () => {
+    const updateSignature = await program.methods
+      .updateConfig(50, false, 0, new BN(0), new BN(0), 0, false)
+      .accounts({ admin: payer.publicKey, config: accounts.config })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, updateSignature);
+
+    // Validate the new fee was saved.
+    const configAccount = await program.account.config.fetch(accounts.config);
+    assert.equal(configAccount.feeBps, 50);
+
+    // Restore the fee used by the other tests.
+    const restoreSignature = await program.methods
+      .updateConfig(FEE_BPS, false, 0, new BN(0), new BN(0), 0, false)
+      .accounts({ admin: payer.publicKey, config: accounts.config })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, restoreSignature);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test the emergency pause.
+  it("Blocks making and taking offers while paused, but still allows cancelling", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+
+    // Alice makes an offer before the pause.
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    // The admin pauses the program.
+    const pauseSignature = await program.methods
+      .updateConfig(FEE_BPS, true, 0, new BN(0), new BN(0), 0, false)
+      .accounts({ admin: payer.publicKey, config: accounts.config })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, pauseSignature);
+
+    try {
+      // Making a new offer fails.
+      const newOfferId = getRandomBigNumber();
+      const newOfferAccounts = deriveOfferAccounts(newOfferId);
+      await expectAnchorError(
+        program.methods
+          .makeOffer(
+            newOfferId,
+            tokenAOfferedAmount,
+            tokenBWantedAmount,
+            NO_EXPIRY,
+            null,
+            [],
+            NO_MIN_FILL,
+            null,
+            "",
+            null,
+            PublicKey.default,
+            0,
+            PublicKey.default,
+            0,
+            0,
+            false,
+            NO_REBATE_RESERVE
+          )
+          .accounts({ ...accounts, ...newOfferAccounts })
+          .signers([alice])
+          .rpc(),
+        "ProgramPaused"
+      );
+
+      // Taking the existing offer fails.
+      await expectAnchorError(
+        program.methods
+          .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+          .accounts({ ...accounts, offer, vault })
+          .signers([bob])
+          .rpc(),
+        "ProgramPaused"
+      );
+
+      // Cancelling the existing offer still works.
+      const cancelSignature = await program.methods
+        .cancelOffer(false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc();
+      await confirmTransaction(connection, cancelSignature);
+      assert.isNull(await connection.getAccountInfo(offer));
+    } finally {
+      // Unpause so the remaining tests can run.
+      const unpauseSignature = await program.methods
+        .updateConfig(FEE_BPS, false, 0, new BN(0), new BN(0), 0, false)
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, unpauseSignature);
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test for Alice re-pricing a live offer.
+  it("Lets Alice change the wanted amount of her offer", async () => {
+    const { offer } = await createOffer(getRandomBigNumber());
+    const newWantedAmount = tokenBWantedAmount.muln(2);
+
+    const updateSignature = await program.methods
+      .updateOffer(newWantedAmount)
+      .accounts({ maker: alice.publicKey, offer })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, updateSignature);
+
+    // Validate the new price persisted and the offered side is untouched.
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.tokenBWantedAmount.eq(newWantedAmount));
+    assert(offerAccount.tokenAOfferedAmount.eq(tokenAOfferedAmount));
+
+    // A zero wanted amount is rejected just like in make_offer.
+    await expectAnchorError(
+      program.methods
+        .updateOffer(new BN(0))
+        .accounts({ maker: alice.publicKey, offer })
+        .signers([alice])
+        .rpc(),
+      "ZeroAmount"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that only the maker can re-price an offer.
+  it("Rejects an offer update signed by someone other than the maker", async () => {
+    const { offer } = await createOffer(getRandomBigNumber());
+
+    await expectAnchorError(
+      program.methods
+        .updateOffer(new BN(1))
+        .accounts({ maker: bob.publicKey, offer })
+        .signers([bob])
+        .rpc(),
+      "ConstraintHasOne"
+    );
+
+    // Validate the price is unchanged.
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.tokenBWantedAmount.eq(tokenBWantedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a maker cannot fill their own offer.
+  it("Rejects Alice taking her own offer", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({
+          ...accounts,
+          taker: alice.publicKey,
+          takerTokenAccountA: accounts.makerTokenAccountA,
+          takerTokenAccountB: accounts.makerTokenAccountB,
+          offer,
+          vault,
+        })
+        .signers([alice])
+        .rpc(),
+      "SelfTradeNotAllowed"
+    );
+
+    // Validate the offer is still open and fully funded.
+    assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that fee-bearing Token-2022 mints are refused.
+  it("Rejects an offer involving a mint with a transfer fee", async () => {
+    // Create a Token-2022 mint that charges 1% on every transfer.
+    const feeMint = await createToken2022Mint(
+      [ExtensionType.TransferFeeConfig],
+      (mint) => [
+        createInitializeTransferFeeConfigInstruction(
+          mint,
+          payer.publicKey,
+          payer.publicKey,
+          100, // 1% transfer fee.
+          BigInt(1_000_000), // Maximum fee per transfer.
+          TOKEN_2022_PROGRAM_ID
+        ),
+      ]
+    );
+
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+
+    // Alice asks for the fee-bearing mint in return for Token A.
+    await expectAnchorError(
+      program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, tokenMintB: feeMint, offer, vault })
+        .signers([alice])
+        .rpc(),
+      "TransferFeeMintUnsupported"
+    );
+
+    // Validate nothing was created for the rejected offer.
+    assert.isNull(await connection.getAccountInfo(offer));
+
+    // Offering the fee-bearing mint is refused too, before the deposit could reach the vault short
+    // of the offered amount. Token B is a plain Token-2022 mint so both mints share a token program.
+    const plainMintB = await createToken2022Mint([], () => []);
+    const aliceFeeAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      feeMint,
+      alice.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const feeVault = getAssociatedTokenAddressSync(feeMint, offer, true, TOKEN_2022_PROGRAM_ID);
+    await expectAnchorError(
+      program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({
+          ...accounts,
+          tokenMintA: feeMint,
+          tokenMintB: plainMintB,
+          makerTokenAccountA: aliceFeeAccount.address,
+          offer,
+          vault: feeVault,
+          tokenProgram: TOKEN_2022_PROGRAM_ID,
+        })
+        .signers([alice])
+        .rpc(),
+      "TransferFeeMintUnsupported"
+    );
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that amount math near u64::MAX fails gracefully instead of panicking.
+  it("Handles a wanted amount near u64::MAX without overflowing", async () => {
+    const maxWantedAmount = new BN("18446744073709551615"); // u64::MAX
+    const { offer, vault } = await createOffer(
+      getRandomBigNumber(),
+      tokenAOfferedAmount,
+      maxWantedAmount
+    );
+
+    // The proportional price and the fee on it fit in u64, so the program gets as far as the
+    // Token B transfer, which Bob cannot afford.
+    try {
+      await program.methods
+        .takeOffer(tokenAOfferedAmount.divn(2), NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc();
+      assert.fail("Expected the take to fail");
+    } catch (error) {
+      const logs: string[] = error.logs ?? [];
+      assert.isFalse(logs.some((log) => log.includes("panicked")));
+      assert.isTrue(logs.some((log) => log.includes("insufficient funds")));
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test offering native SOL, which is wrapped on the way in and unwrapped on the way out.
+  it("Wraps Carol's SOL into the vault and unwraps it for Bob when Carol offers native SOL", async () => {
+    // Carol only holds SOL, with no token accounts at all.
+    const carol = await createFundedWallet();
+    const solOfferedAmount = new BN(LAMPORTS_PER_SOL / 2);
+
+    // The native mint lives under the legacy Token Program, so Token B must as well.
+    const legacyMintB = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      null,
+      6,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    const bobLegacyTokenAccountB = await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      legacyMintB,
+      bob.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    await mintTo(
+      connection,
+      payer,
+      legacyMintB,
+      bobLegacyTokenAccountB.address,
+      payer,
+      1_000_000_000,
+      [],
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+
+    // The maker's Token B account must exist before a take, so Carol opens hers up front.
+    await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      legacyMintB,
+      carol.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+
+    const offerId = getRandomBigNumber();
+    const offer = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("offer"),
+        carol.publicKey.toBuffer(),
+        offerId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+    const ata = (mint: PublicKey, owner: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, owner, true, TOKEN_PROGRAM_ID);
+    const nativeAccounts = {
+      ...accounts,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      maker: carol.publicKey,
+      rentPayer: carol.publicKey,
+      tokenMintA: NATIVE_MINT,
+      tokenMintB: legacyMintB,
+      makerTokenAccountA: ata(NATIVE_MINT, carol.publicKey),
+      makerTokenAccountB: ata(legacyMintB, carol.publicKey),
+      takerTokenAccountA: ata(NATIVE_MINT, bob.publicKey),
+      takerTokenAccountB: bobLegacyTokenAccountB.address,
+      treasuryTokenAccountB: ata(legacyMintB, accounts.treasury),
+      offer,
+      vault: ata(NATIVE_MINT, offer),
+    };
+
+    // Carol offers half a SOL.
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        solOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts(nativeAccounts)
+      .signers([carol])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    // Validate the vault holds the SOL as wrapped SOL.
+    assert((await getTokenBalance(nativeAccounts.vault)).eq(solOfferedAmount));
+
+    // Bob takes the whole offer, asking for plain SOL.
+    const bobLamportsBefore = await connection.getBalance(bob.publicKey);
+    const takeSignature = await program.methods
+      .takeOffer(solOfferedAmount, NO_PRICE_LIMIT, "", true)
+      .accounts(nativeAccounts)
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+
+    // Validate Bob received plain SOL: his wrapped SOL account is closed and his balance grew
+    // by the offered amount, less the rent he paid for Carol's and the treasury's token accounts.
+    assert.isNull(await connection.getAccountInfo(nativeAccounts.takerTokenAccountA));
+    const bobLamportsAfter = await connection.getBalance(bob.publicKey);
+    assert.isAbove(
+      bobLamportsAfter - bobLamportsBefore,
+      solOfferedAmount.toNumber() - 0.01 * LAMPORTS_PER_SOL
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test a private offer reserved for Bob.
+  it("Lets the allowed taker fill a private offer", async () => {
+    const { offer, vault } = await createOffer(
+      getRandomBigNumber(),
+      tokenAOfferedAmount,
+      tokenBWantedAmount,
+      NO_EXPIRY,
+      bob.publicKey
+    );
+
+    // Validate the allowed taker was saved.
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.allowedTaker.equals(bob.publicKey));
+
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test a private offer reserved for someone else.
+  it("Rejects a taker who is not the allowed taker of a private offer", async () => {
+    const otcDesk = Keypair.generate();
+    const { offer, vault } = await createOffer(
+      getRandomBigNumber(),
+      tokenAOfferedAmount,
+      tokenBWantedAmount,
+      NO_EXPIRY,
+      otcDesk.publicKey
+    );
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "UnauthorizedTaker"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offered mint whose permanent delegate could drain the vault is refused.
+  it("Rejects an offer of a mint with a permanent delegate", async () => {
+    // A permanent delegate can move tokens out of any account, including the vault.
+    const drainableMint = await createToken2022Mint(
+      [ExtensionType.PermanentDelegate],
+      (mint) => [
+        createInitializePermanentDelegateInstruction(
+          mint,
+          payer.publicKey,
+          TOKEN_2022_PROGRAM_ID
+        ),
+      ]
+    );
+    const aliceDrainableAccount = await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      drainableMint,
+      alice.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await mintTo(
+      connection,
+      payer,
+      drainableMint,
+      aliceDrainableAccount.address,
+      payer,
+      tokenAOfferedAmount.toNumber(),
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId, accounts.maker, drainableMint);
+    await expectAnchorError(
+      program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({
+          ...accounts,
+          tokenMintA: drainableMint,
+          makerTokenAccountA: aliceDrainableAccount.address,
+          offer,
+          vault,
+        })
+        .signers([alice])
+        .rpc(),
+      "PermanentDelegateNotAllowed"
+    );
+
+    // Validate nothing was created and Alice kept her tokens.
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert((await getTokenBalance(aliceDrainableAccount.address)).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the offer view mirrors the account stored at the derived offer address.
+  it("Returns an offer's terms from get_offer at the address derived from the maker and ID", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer } = await createOffer(offerId);
+
+    // The seeds the program uses: "offer", the maker, and the ID as little-endian bytes.
+    const [derivedOffer] = PublicKey.findProgramAddressSync(
+      [Buffer.from("offer"), alice.publicKey.toBuffer(), offerId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    assert(derivedOffer.equals(offer));
+
+    // Read the terms through the view instruction and compare them with the decoded account.
+    const viewed = await program.methods.getOffer().accounts({ offer }).view();
+    const stored = await program.account.offer.fetch(offer);
+    assert(viewed.id.eq(offerId));
+    assert(viewed.maker.equals(stored.maker));
+    assert(viewed.tokenMintA.equals(stored.tokenMintA));
+    assert(viewed.tokenMintB.equals(stored.tokenMintB));
+    assert(viewed.tokenAOfferedAmount.eq(stored.tokenAOfferedAmount));
+    assert(viewed.tokenBWantedAmount.eq(stored.tokenBWantedAmount));
+    assert.equal(viewed.bump, stored.bump);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test an offer that bundles a second token alongside Token A.
+  it("Swaps a two-token basket for Token B in a single take", async () => {
+    // A third mint, held by Alice, to bundle with Token A.
+    const tokenMintC = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      null,
+      6,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const [aliceTokenAccountC, bobTokenAccountC] = await Promise.all(
+      [alice, bob].map(async (user) =>
+        (
+          await getOrCreateAssociatedTokenAccount(
+            connection,
+            payer,
+            tokenMintC,
+            user.publicKey,
+            false,
+            undefined,
+            undefined,
+            TOKEN_PROGRAM
+          )
+        ).address
+      )
+    );
+    const tokenCOfferedAmount = new BN(500_000);
+    await mintTo(
+      connection,
+      payer,
+      tokenMintC,
+      aliceTokenAccountC,
+      payer,
+      tokenCOfferedAmount.toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const vaultC = getAssociatedTokenAddressSync(tokenMintC, offer, true, TOKEN_PROGRAM);
+
+    // Extra basket tokens are passed as (mint, vault, user token account).
+    const basketAccounts = (userTokenAccount: PublicKey) => [
+      { pubkey: tokenMintC, isSigner: false, isWritable: false },
+      { pubkey: vaultC, isSigner: false, isWritable: true },
+      { pubkey: userTokenAccount, isSigner: false, isWritable: true },
+    ];
+
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [tokenCOfferedAmount],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, offer, vault })
+      .remainingAccounts(basketAccounts(aliceTokenAccountC))
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    // Validate the bundled token sits in its own vault and is recorded on the offer.
+    assert((await getTokenBalance(vaultC)).eq(tokenCOfferedAmount));
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert.equal(offerAccount.basket.length, 1);
+    assert(offerAccount.basket[0].mint.equals(tokenMintC));
+    assert(offerAccount.basket[0].amount.eq(tokenCOfferedAmount));
+
+    // A basket cannot be split between takers.
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount.divn(2), NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .remainingAccounts(basketAccounts(bobTokenAccountC))
+        .signers([bob])
+        .rpc(),
+      "BasketRequiresFullFill"
+    );
+
+    const bobBalanceABefore = await getTokenBalance(accounts.takerTokenAccountA);
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .remainingAccounts(basketAccounts(bobTokenAccountC))
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+
+    // Validate Bob received both tokens and every vault and the offer are closed.
+    assert(
+      (await getTokenBalance(accounts.takerTokenAccountA)).eq(
+        bobBalanceABefore.add(tokenAOfferedAmount)
+      )
+    );
+    assert((await getTokenBalance(bobTokenAccountC)).eq(tokenCOfferedAmount));
+    assert.isNull(await connection.getAccountInfo(vault));
+    assert.isNull(await connection.getAccountInfo(vaultC));
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that makers can refuse dust fills.
+  it("Rejects a partial fill below the offer's minimum fill amount but accepts one at it", async () => {
+    const minFillAmount = tokenAOfferedAmount.divn(4);
+    const { offer, vault } = await createOffer(
+      getRandomBigNumber(),
+      tokenAOfferedAmount,
+      tokenBWantedAmount,
+      NO_EXPIRY,
+      null,
+      minFillAmount
+    );
+
+    // Validate the minimum was saved.
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.minFillAmount.eq(minFillAmount));
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(minFillAmount.subn(1), NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "BelowMinimumFill"
+    );
+
+    const takeSignature = await program.methods
+      .takeOffer(minFillAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+
+    // Validate the fill went through and the rest of the offer is still open.
+    const offerAfter = await program.account.offer.fetch(offer);
+    assert(offerAfter.tokenARemaining.eq(tokenAOfferedAmount.sub(minFillAmount)));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a fill is recorded before tokens move, so it cannot be repeated in one transaction.
+  it("Rejects a second take of the same tokens within a single transaction", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const fillAmount = tokenAOfferedAmount.muln(3).divn(5);
+
+    const bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+
+    // Two takes of 60% each: the second must see the first one already recorded.
+    const firstTake = await program.methods
+      .takeOffer(fillAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .instruction();
+    await expectAnchorError(
+      program.methods
+        .takeOffer(fillAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .preInstructions([firstTake])
+        .signers([bob])
+        .rpc(),
+      "FillExceedsOffer"
+    );
+
+    // Validate the whole transaction was rolled back.
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.tokenARemaining.eq(tokenAOfferedAmount));
+    assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount));
+    assert((await getTokenBalance(accounts.takerTokenAccountB)).eq(bobBalanceBBefore));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that offers record when they were made, so they can be sorted by age.
+  it("Records the validator time an offer was made", async () => {
+    const timeBefore = await getValidatorTime();
+    const { offer } = await createOffer(getRandomBigNumber());
+    const timeAfter = await getValidatorTime();
+
+    // Block times are approximate, so allow a few seconds either side.
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.createdAt.gte(timeBefore.subn(5)));
+    assert(offerAccount.createdAt.lte(timeAfter.addn(5)));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that paying from an account of the wrong mint fails with a clear error.
+  it("Rejects a take paid from a token account that does not hold the wanted mint", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({
+          ...accounts,
+          offer,
+          vault,
+          takerTokenAccountB: accounts.takerTokenAccountA, // Bob's Token A account.
+        })
+        .signers([bob])
+        .rpc(),
+      "WantedMintMismatch"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a taker's price limit protects them from a last-moment price rise.
+  it("Rejects a take when the maker raised the price above the taker's limit", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+
+    // Bob saw the original price, but Alice doubles it before his take lands.
+    const updateSignature = await program.methods
+      .updateOffer(tokenBWantedAmount.muln(2))
+      .accounts({ maker: alice.publicKey, offer })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, updateSignature);
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, tokenBWantedAmount, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "SlippageExceeded"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a maker can clear several offers in one transaction.
+  it("Cancels three of Alice's offers in a single batch", async () => {
+    const aliceBalanceBefore = await getTokenBalance(accounts.makerTokenAccountA);
+    const offers = await Promise.all(
+      [0, 1, 2].map(() => createOffer(getRandomBigNumber()))
+    );
+
+    // Each offer is passed as (offer, Token A mint, vault, Alice's Token A account).
+    const remainingAccounts = offers.flatMap(({ offer, vault }) => [
+      { pubkey: offer, isSigner: false, isWritable: true },
+      { pubkey: accounts.tokenMintA, isSigner: false, isWritable: false },
+      { pubkey: vault, isSigner: false, isWritable: true },
+      { pubkey: accounts.makerTokenAccountA, isSigner: false, isWritable: true },
+    ]);
+
+    const cancelSignature = await program.methods
+      .cancelOffersBatch()
+      .accounts({ maker: alice.publicKey, tokenProgram: TOKEN_PROGRAM })
+      .remainingAccounts(remainingAccounts)
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, cancelSignature);
+
+    // Validate every vault was drained back to Alice and every account closed.
+    assert((await getTokenBalance(accounts.makerTokenAccountA)).eq(aliceBalanceBefore));
+    for (const { offer, vault } of offers) {
+      assert.isNull(await connection.getAccountInfo(offer));
+      assert.isNull(await connection.getAccountInfo(vault));
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the final take refuses to close a vault that still holds tokens.
+  it("Rejects the final take when the vault holds more than the offer", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+
+    // Someone sends one extra unit of Token A straight into the vault.
+    await transfer(
+      connection,
+      payer,
+      accounts.makerTokenAccountA,
+      vault,
+      alice,
+      1,
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "VaultNotEmpty"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that Token B can be routed to an account the maker names when making the offer.
+  it("Sends Token B to the offer's custom destination instead of Alice's account", async () => {
+    // A separate wallet (e.g. Alice's multisig) holding the destination account.
+    const multisig = Keypair.generate();
+    const destination = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintB,
+        multisig.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        destination,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    // Paying Alice's own account is refused once a destination is set.
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault, tokenBDestination: null })
+        .signers([bob])
+        .rpc(),
+      "WantedDestinationMismatch"
+    );
+
+    const aliceBalanceBBefore = await getTokenBalance(accounts.makerTokenAccountB);
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({
+        ...accounts,
+        offer,
+        vault,
+        makerTokenAccountB: null,
+        tokenBDestination: destination,
+      })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+
+    // Validate the destination received the payment and Alice's account did not.
+    assert(
+      (await getTokenBalance(destination)).eq(
+        tokenBWantedAmount.sub(protocolFee(tokenBWantedAmount))
+      )
+    );
+    assert((await getTokenBalance(accounts.makerTokenAccountB)).eq(aliceBalanceBBefore));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer still backed by its vault cannot be closed as stale.
+  it("Refuses to close an offer as stale while its vault still holds tokens", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+
+    // The vault still holds Alice's Token A, so the offer is not stale.
+    await expectAnchorError(
+      program.methods
+        .closeStaleOffer()
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc(),
+      "OfferNotStale"
+    );
+    assert.isNotNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that makers earn a treasury rebate only for offers that stayed open long enough.
+  it("Pays a maker rebate on cancel only once the offer has been open long enough", async () => {
+    const rebateBps = 100; // 1% of the unfilled Token A.
+    const rebate = tokenAOfferedAmount.muln(rebateBps).divn(10_000);
+
+    // Fund the treasury's Token A account so it can pay rebates.
+    const treasuryTokenAccountA = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintA,
+        accounts.treasury,
+        true,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    await transfer(
+      connection,
+      payer,
+      accounts.makerTokenAccountA,
+      treasuryTokenAccountA,
+      alice,
+      rebate.muln(2).toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+    const setRebate = async (minRebateDuration: BN) => {
+      const signature = await program.methods
+        .updateConfig(FEE_BPS, false, rebateBps, minRebateDuration, new BN(0), 0, false)
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, signature);
+    };
+    const makeAndCancel = async () => {
+      const { offer, vault } = await createOffer(getRandomBigNumber());
+      const aliceBalanceBefore = await getTokenBalance(accounts.makerTokenAccountA);
+      const signature = await program.methods
+        .cancelOffer(false)
+        .accounts({ ...accounts, offer, vault, treasuryTokenAccountA })
+        .signers([alice])
+        .rpc();
+      await confirmTransaction(connection, signature);
+      return (await getTokenBalance(accounts.makerTokenAccountA)).sub(aliceBalanceBefore);
+    };
+
+    // An offer cancelled within the hour earns nothing beyond its refund.
+    await setRebate(new BN(3600));
+    assert((await makeAndCancel()).eq(tokenAOfferedAmount));
+
+    // Once the required duration has passed (here: none), the cancel pays the rebate too.
+    await setRebate(new BN(0));
+    assert((await makeAndCancel()).eq(tokenAOfferedAmount.add(rebate)));
+
+    // Switch rebates back off for the remaining tests.
+    const resetSignature = await program.methods
+      .updateConfig(FEE_BPS, false, 0, new BN(0), new BN(0), 0, false)
+      .accounts({ admin: payer.publicKey, config: accounts.config })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, resetSignature);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that topping an offer up restarts its age, so a top-up right before a cancel earns no rebate.
+  it("Pays no maker rebate on an offer topped up in the same transaction as its cancel", async () => {
+    const rebateBps = 100; // 1% of the unfilled Token A.
+    const minRebateDuration = new BN(2);
+    const smallAmount = tokenAOfferedAmount.divn(10);
+
+    // Fund the treasury well enough that a rebate on the topped-up offer could be paid in full.
+    const treasuryTokenAccountA = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintA,
+        accounts.treasury,
+        true,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    await transfer(
+      connection,
+      payer,
+      accounts.makerTokenAccountA,
+      treasuryTokenAccountA,
+      alice,
+      tokenAOfferedAmount.muln(2).muln(rebateBps).divn(10_000).toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const rebateSignature = await program.methods
+      .updateConfig(FEE_BPS, false, rebateBps, minRebateDuration, new BN(0), 0, false)
+      .accounts({ admin: payer.publicKey, config: accounts.config })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, rebateSignature);
+
+    // A small offer outlives the rebate duration, then is topped up and cancelled at once.
+    const { offer, vault } = await createOffer(getRandomBigNumber(), smallAmount);
+    const { createdAt } = await program.account.offer.fetch(offer);
+    await waitForValidatorTime(createdAt.add(minRebateDuration));
+    const aliceBalanceBefore = await getTokenBalance(accounts.makerTokenAccountA);
+    const topUp = await program.methods
+      .topUpOffer(tokenAOfferedAmount)
+      .accounts({ ...accounts, offer, vault })
+      .instruction();
+    const signature = await program.methods
+      .cancelOffer(false)
+      .accounts({ ...accounts, offer, vault, treasuryTokenAccountA })
+      .preInstructions([topUp])
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // Alice gets back exactly what she put in, with no rebate on the freshly added Token A.
+    assert((await getTokenBalance(accounts.makerTokenAccountA)).eq(aliceBalanceBefore.add(smallAmount)));
+
+    // Switch rebates back off for the remaining tests.
+    const resetSignature = await program.methods
+      .updateConfig(FEE_BPS, false, 0, new BN(0), new BN(0), 0, false)
+      .accounts({ admin: payer.publicKey, config: accounts.config })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, resetSignature);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that offers carry an optional human-readable label of at most 32 bytes.
+  it("Stores a label of up to 32 bytes, including an empty one, and rejects longer labels", async () => {
+    const label = "Selling USDC for BONK";
+    const { offer } = await createOffer(
+      getRandomBigNumber(),
+      tokenAOfferedAmount,
+      tokenBWantedAmount,
+      NO_EXPIRY,
+      null,
+      NO_MIN_FILL,
+      label
+    );
+    assert.equal((await program.account.offer.fetch(offer)).label, label);
+
+    // An empty label is the default.
+    const { offer: unlabelledOffer } = await createOffer(getRandomBigNumber());
+    assert.equal((await program.account.offer.fetch(unlabelledOffer)).label, "");
+
+    await expectAnchorError(
+      createOffer(
+        getRandomBigNumber(),
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        NO_MIN_FILL,
+        "x".repeat(33)
+      ),
+      "LabelTooLong"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a maker can add Token A to an open offer.
+  it("Lets Alice top up the vault of an open offer", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const topUpAmount = tokenAOfferedAmount.divn(2);
+
+    const topUpSignature = await program.methods
+      .topUpOffer(topUpAmount)
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, topUpSignature);
+
+    // Validate the vault and both offer amounts grew by the top-up.
+    const toppedUpAmount = tokenAOfferedAmount.add(topUpAmount);
+    assert((await getTokenBalance(vault)).eq(toppedUpAmount));
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.tokenAOfferedAmount.eq(toppedUpAmount));
+    assert(offerAccount.tokenARemaining.eq(toppedUpAmount));
+
+    // An empty top-up is rejected.
+    await expectAnchorError(
+      program.methods
+        .topUpOffer(new BN(0))
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc(),
+      "ZeroAmount"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a maker can pull part of their Token A back out of an open offer.
+  it("Lets Alice reduce an open offer, but not by everything it has left", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const reduction = tokenAOfferedAmount.divn(4);
+    const aliceBalanceBefore = await getTokenBalance(accounts.makerTokenAccountA);
+
+    const reduceSignature = await program.methods
+      .reduceOffer(reduction)
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, reduceSignature);
+
+    // Validate the tokens came back and the offer shrank by the same amount.
+    const reducedAmount = tokenAOfferedAmount.sub(reduction);
+    assert((await getTokenBalance(accounts.makerTokenAccountA)).eq(aliceBalanceBefore.add(reduction)));
+    assert((await getTokenBalance(vault)).eq(reducedAmount));
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.tokenAOfferedAmount.eq(reducedAmount));
+    assert(offerAccount.tokenARemaining.eq(reducedAmount));
+
+    // Withdrawing everything that is left (or more) is refused.
+    await expectAnchorError(
+      program.methods
+        .reduceOffer(reducedAmount)
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc(),
+      "InsufficientVaultBalance"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a frozen account is reported clearly instead of failing inside the token program.
+  it("Rejects a take when Alice's Token B account is frozen", async () => {
+    // A Token B mint with a freeze authority, so Alice's account can be frozen.
+    const freezableMintB = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      payer.publicKey,
+      6,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const [aliceFreezableAccount, bobFreezableAccount] = await Promise.all(
+      [alice, bob].map(async (user) =>
+        (
+          await getOrCreateAssociatedTokenAccount(
+            connection,
+            payer,
+            freezableMintB,
+            user.publicKey,
+            false,
+            undefined,
+            undefined,
+            TOKEN_PROGRAM
+          )
+        ).address
+      )
+    );
+    await mintTo(
+      connection,
+      payer,
+      freezableMintB,
+      bobFreezableAccount,
+      payer,
+      tokenBWantedAmount.toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const freezableAccounts = {
+      ...accounts,
+      tokenMintB: freezableMintB,
+      makerTokenAccountB: aliceFreezableAccount,
+      takerTokenAccountB: bobFreezableAccount,
+      treasuryTokenAccountB: getAssociatedTokenAddressSync(
+        freezableMintB,
+        accounts.treasury,
+        true,
+        TOKEN_PROGRAM
+      ),
+      offer,
+      vault,
+    };
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts(freezableAccounts)
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    await freezeAccount(
+      connection,
+      payer,
+      aliceFreezableAccount,
+      freezableMintB,
+      payer,
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts(freezableAccounts)
+        .signers([bob])
+        .rpc(),
+      "FrozenTokenAccount"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that Dutch-auction offers are priced from the auction rather than the fixed amount.
+  it("Prices a Dutch-auction offer at its start amount before the auction and its floor after", async () => {
+    const now = await getValidatorTime();
+    const startAmount = tokenBWantedAmount.muln(2);
+    const floorAmount = tokenBWantedAmount.divn(2);
+
+    const takeAndMeasure = async (startTs: BN, endTs: BN) => {
+      const { offer, vault } = await createOffer(
+        getRandomBigNumber(),
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        NO_MIN_FILL,
+        "",
+        { startAmount, floorAmount, startTs, endTs }
+      );
+      const bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+      const signature = await program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc();
+      await confirmTransaction(connection, signature);
+      return bobBalanceBBefore.sub(await getTokenBalance(accounts.takerTokenAccountB));
+    };
+
+    // An auction that has not started yet asks for its start amount.
+    const notStarted = await takeAndMeasure(now.addn(3600), now.addn(7200));
+    assert(notStarted.eq(startAmount));
+
+    // An auction that has already ended asks for its floor.
+    const ended = await takeAndMeasure(now.subn(7200), now.subn(3600));
+    assert(ended.eq(floorAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that malformed auctions and repricing of auction offers are rejected.
+  it("Rejects a rising auction and repricing of a Dutch-auction offer", async () => {
+    const now = await getValidatorTime();
+    await expectAnchorError(
+      createOffer(
+        getRandomBigNumber(),
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        NO_MIN_FILL,
+        "",
+        {
+          startAmount: tokenBWantedAmount,
+          floorAmount: tokenBWantedAmount.muln(2),
+          startTs: now,
+          endTs: now.addn(3600),
+        }
+      ),
+      "InvalidAuction"
+    );
+
+    const { offer } = await createOffer(
+      getRandomBigNumber(),
+      tokenAOfferedAmount,
+      tokenBWantedAmount,
+      NO_EXPIRY,
+      null,
+      NO_MIN_FILL,
+      "",
+      {
+        startAmount: tokenBWantedAmount.muln(2),
+        floorAmount: tokenBWantedAmount,
+        startTs: now,
+        endTs: now.addn(3600),
+      }
+    );
+    await expectAnchorError(
+      program.methods
+        .updateOffer(tokenBWantedAmount.muln(3))
+        .accounts({ maker: alice.publicKey, offer })
+        .signers([alice])
+        .rpc(),
+      "AuctionOfferCannotBeRepriced"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a transferred offer follows its new maker and no longer answers to the old one.
+  it("Lets the new maker of a transferred offer cancel it, but not the old one", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const desk = await createFundedWallet();
+    const deskTokenAccountA = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintA,
+        desk.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+
+    const transferSignature = await program.methods
+      .transferOfferOwnership(desk.publicKey)
+      .accounts({ maker: alice.publicKey, offer })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, transferSignature);
+
+    // The offer keeps its address and remembers who made it, and the rent Alice paid moves with it.
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.maker.equals(desk.publicKey));
+    assert(offerAccount.creator.equals(alice.publicKey));
+    assert(offerAccount.rentPayer.equals(desk.publicKey));
+
+    // Alice can no longer cancel the offer.
+    await expectAnchorError(
+      program.methods
+        .cancelOffer(false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc(),
+      "ConstraintHasOne"
+    );
+
+    // Alice no longer gets the rent back either.
+    await expectAnchorError(
+      program.methods
+        .cancelOffer(false)
+        .accounts({
+          ...accounts,
+          canceller: desk.publicKey,
+          maker: desk.publicKey,
+          makerTokenAccountA: deskTokenAccountA,
+          offer,
+          vault,
+        })
+        .signers([desk])
+        .rpc(),
+      "RentPayerMismatch"
+    );
+
+    // The desk can, and receives the vaulted Token A and the rent of the offer and its vault.
+    const rent = (await connection.getBalance(offer)) + (await connection.getBalance(vault));
+    const deskLamportsBefore = await connection.getBalance(desk.publicKey);
+    const cancelSignature = await program.methods
+      .cancelOffer(false)
+      .accounts({
+        ...accounts,
+        canceller: desk.publicKey,
+        maker: desk.publicKey,
+        rentPayer: desk.publicKey,
+        makerTokenAccountA: deskTokenAccountA,
+        offer,
+        vault,
+      })
+      .signers([desk])
+      .rpc();
+    await confirmTransaction(connection, cancelSignature);
+    assert((await getTokenBalance(deskTokenAccountA)).eq(tokenAOfferedAmount));
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert.equal(await connection.getBalance(desk.publicKey), deskLamportsBefore + rent);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a sweep of several offers takes the live ones and skips the expired one.
+  it("Takes two of three offers in a single batch, skipping the expired one", async () => {
+    const expired = (await getValidatorTime()).addn(MIN_OFFER_DURATION + 5);
+    const offers = [
+      await createOffer(getRandomBigNumber()),
+      await createOffer(getRandomBigNumber(), tokenAOfferedAmount, tokenBWantedAmount, expired),
+      await createOffer(getRandomBigNumber()),
+    ];
+    await waitForValidatorTime(expired);
+    const bobBalanceABefore = await getTokenBalance(accounts.takerTokenAccountA);
+    const bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+
+    // Each offer is passed as (offer, vault, Alice, Alice's Token B account, Alice's stats).
+    const remainingAccounts = offers.flatMap(({ offer, vault }) => [
+      { pubkey: offer, isSigner: false, isWritable: true },
+      { pubkey: vault, isSigner: false, isWritable: true },
+      { pubkey: alice.publicKey, isSigner: false, isWritable: true },
+      { pubkey: accounts.makerTokenAccountB, isSigner: false, isWritable: true },
+      { pubkey: deriveMakerStats(alice.publicKey), isSigner: false, isWritable: true },
+    ]);
+
+    const signature = await program.methods
+      .takeOffersBatch(NO_PRICE_LIMIT)
+      .accounts({ ...accounts })
+      .remainingAccounts(remainingAccounts)
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // Only the two live offers were announced as taken.
+    const offersTaken = (await getEvents(signature)).filter(
+      (event) => event.name === "offerTaken"
+    );
+    assert.equal(offersTaken.length, 2);
+
+    // Validate Bob received and paid for exactly the two live offers.
+    const bobBalanceAAfter = await getTokenBalance(accounts.takerTokenAccountA);
+    const bobBalanceBAfter = await getTokenBalance(accounts.takerTokenAccountB);
+    assert(bobBalanceAAfter.sub(bobBalanceABefore).eq(tokenAOfferedAmount.muln(2)));
+    assert(bobBalanceBBefore.sub(bobBalanceBAfter).eq(tokenBWantedAmount.muln(2)));
+
+    // The live offers are closed, while the expired one is left for Alice to cancel.
+    assert.isNull(await connection.getAccountInfo(offers[0].offer));
+    assert.isNull(await connection.getAccountInfo(offers[2].offer));
+    assert.isNotNull(await connection.getAccountInfo(offers[1].offer));
+    assert((await getTokenBalance(offers[1].vault)).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that offers store the canonical bump that take_offer re-derives and checks.
+  // An offer account is owned by the program, so its stored bump cannot be tampered with on a
+  // live validator; this pins the invariant the InvalidOfferBump check relies on.
+  it("Stores the canonical bump of the offer's address", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer } = await createOffer(offerId);
+    const [expectedOffer, canonicalBump] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("offer"),
+        alice.publicKey.toBuffer(),
+        offerId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offer.equals(expectedOffer));
+    assert.equal(offerAccount.bump, canonicalBump);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a royalty offer splits each Token B payment between the treasury, the royalty
+  // recipient, and the maker.
+  it("Pays the offer's royalty to the royalty recipient on a take", async () => {
+    const royaltyBps = 500;
+    const issuer = Keypair.generate();
+    const royaltyTokenAccount = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintB,
+        issuer.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        issuer.publicKey,
+        royaltyBps,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    // Without the royalty recipient's account the take is refused.
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "RoyaltyAccountMismatch"
+    );
+
+    const aliceBalanceBBefore = await getTokenBalance(accounts.makerTokenAccountB);
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault, royaltyTokenAccount })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+
+    // Validate the issuer got its royalty and Alice the rest after the protocol fee.
+    const royalty = tokenBWantedAmount.muln(royaltyBps).divn(10_000);
+    const fee = protocolFee(tokenBWantedAmount);
+    assert((await getTokenBalance(royaltyTokenAccount)).eq(royalty));
+    const aliceBalanceBAfter = await getTokenBalance(accounts.makerTokenAccountB);
+    assert(aliceBalanceBAfter.sub(aliceBalanceBBefore).eq(tokenBWantedAmount.sub(fee).sub(royalty)));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that offers carry both mints' decimals so clients can display amounts from one fetch.
+  it("Stores the decimals of both mints on the offer", async () => {
+    const { offer } = await createOffer(getRandomBigNumber());
+    const mintA = await getMint(connection, accounts.tokenMintA, undefined, TOKEN_PROGRAM);
+    const mintB = await getMint(connection, accounts.tokenMintB, undefined, TOKEN_PROGRAM);
+
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert.equal(offerAccount.tokenADecimals, mintA.decimals);
+    assert.equal(offerAccount.tokenBDecimals, mintB.decimals);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a taker without enough Token B is refused before any tokens move.
+  it("Rejects a take from a taker who cannot cover the Token B payment", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const pauper = await createFundedWallet();
+    const pauperTokenAccountB = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintB,
+        pauper.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({
+          ...accounts,
+          taker: pauper.publicKey,
+          takerTokenAccountA: getAssociatedTokenAddressSync(
+            accounts.tokenMintA,
+            pauper.publicKey,
+            false,
+            TOKEN_PROGRAM
+          ),
+          takerTokenAccountB: pauperTokenAccountB,
+          offer,
+          vault,
+        })
+        .signers([pauper])
+        .rpc(),
+      "InsufficientTakerBalance"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test the lean take path for takers who manage their own associated token accounts.
+  it("Lets a taker with existing token accounts take without the ATA program", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const bobBalanceABefore = await getTokenBalance(accounts.takerTokenAccountA);
+
+    const instruction = await program.methods
+      .takeOfferExistingAccounts(tokenAOfferedAmount, NO_PRICE_LIMIT)
+      .accounts({ ...accounts, offer, vault })
+      .instruction();
+    assert.isFalse(
+      instruction.keys.some(({ pubkey }) => pubkey.equals(ASSOCIATED_TOKEN_PROGRAM_ID))
+    );
+
+    const signature = await program.methods
+      .takeOfferExistingAccounts(tokenAOfferedAmount, NO_PRICE_LIMIT)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // Validate Bob received the Token A and the offer is closed as with `take_offer`.
+    const bobBalanceAAfter = await getTokenBalance(accounts.takerTokenAccountA);
+    assert(bobBalanceAAfter.sub(bobBalanceABefore).eq(tokenAOfferedAmount));
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that only `take_offer` creates a missing taker account.
+  it("Creates a new taker's Token A account on take_offer but not on the lean path", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const newTaker = await createFundedWallet();
+    const newTakerTokenAccountA = getAssociatedTokenAddressSync(
+      accounts.tokenMintA,
+      newTaker.publicKey,
+      false,
+      TOKEN_PROGRAM
+    );
+    const newTakerTokenAccountB = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintB,
+        newTaker.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    await transfer(
+      connection,
+      payer,
+      accounts.takerTokenAccountB,
+      newTakerTokenAccountB,
+      bob,
+      tokenBWantedAmount.toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const takeAccounts = {
+      ...accounts,
+      taker: newTaker.publicKey,
+      takerTokenAccountA: newTakerTokenAccountA,
+      takerTokenAccountB: newTakerTokenAccountB,
+      offer,
+      vault,
+    };
+
+    // The lean path refuses to run without the taker's Token A account.
+    await expectAnchorError(
+      program.methods
+        .takeOfferExistingAccounts(tokenAOfferedAmount, NO_PRICE_LIMIT)
+        .accounts(takeAccounts)
+        .signers([newTaker])
+        .rpc(),
+      "AccountNotInitialized"
+    );
+
+    // `take_offer` creates it and completes the trade.
+    const signature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts(takeAccounts)
+      .signers([newTaker])
+      .rpc();
+    await confirmTransaction(connection, signature);
+    assert((await getTokenBalance(newTakerTokenAccountA)).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that indexers can follow updates and cancellations through events.
+  it("Emits OfferUpdated and OfferCancelled events", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = await createOffer(offerId);
+    const newWantedAmount = tokenBWantedAmount.muln(2);
+
+    const updateSignature = await program.methods
+      .updateOffer(newWantedAmount)
+      .accounts({ maker: alice.publicKey, offer })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, updateSignature);
+    const [offerUpdated] = (await getEvents(updateSignature)).filter(
+      (event) => event.name === "offerUpdated"
+    );
+    assert(offerUpdated.data.id.eq(offerId));
+    assert(offerUpdated.data.maker.equals(alice.publicKey));
+    assert(offerUpdated.data.tokenBWantedAmount.eq(newWantedAmount));
+
+    const cancelSignature = await program.methods
+      .cancelOffer(false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, cancelSignature);
+    const [offerCancelled] = (await getEvents(cancelSignature)).filter(
+      (event) => event.name === "offerCancelled"
+    );
+    assert(offerCancelled.data.id.eq(offerId));
+    assert(offerCancelled.data.maker.equals(alice.publicKey));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the flat SOL fee is collected by the treasury on top of the token fee.
+  it("Charges the taker the config's flat SOL fee on a take", async () => {
+    const flatFeeLamports = new BN(5_000);
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+
+    // Fund the treasury to rent-exemption so it can receive a fee smaller than that minimum.
+    const airdropSignature = await connection.requestAirdrop(accounts.treasury, LAMPORTS_PER_SOL);
+    await confirmTransaction(connection, airdropSignature);
+
+    const setFeeSignature = await program.methods
+      .updateConfig(FEE_BPS, false, 0, new BN(0), flatFeeLamports, 0, false)
+      .accounts({ admin: payer.publicKey, config: accounts.config })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, setFeeSignature);
+    try {
+      const treasuryBalanceBefore = await connection.getBalance(accounts.treasury);
+      const signature = await program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc();
+      await confirmTransaction(connection, signature);
+
+      const treasuryBalanceAfter = await connection.getBalance(accounts.treasury);
+      assert.equal(treasuryBalanceAfter - treasuryBalanceBefore, flatFeeLamports.toNumber());
+    } finally {
+      // Disable the flat fee so the remaining tests are unaffected.
+      const resetSignature = await program.methods
+        .updateConfig(FEE_BPS, false, 0, new BN(0), new BN(0), 0, false)
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, resetSignature);
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the config's open-offer cap stops a maker from spamming offers.
+  it("Rejects Alice's offer beyond the configured maximum of open offers", async () => {
+    const aliceStats = await program.account.makerStats.fetch(deriveMakerStats(alice.publicKey));
+    const maxOpenOffers = aliceStats.openOffers + 2;
+    const setCap = async (cap: number) => {
+      const signature = await program.methods
+        .updateConfig(FEE_BPS, false, 0, new BN(0), new BN(0), cap, false)
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, signature);
+    };
+
+    await setCap(maxOpenOffers);
+    try {
+      // Alice can open offers up to the cap.
+      await createOffer(getRandomBigNumber());
+      const { offer, vault } = await createOffer(getRandomBigNumber());
+      const statsAtCap = await program.account.makerStats.fetch(deriveMakerStats(alice.publicKey));
+      assert.equal(statsAtCap.openOffers, maxOpenOffers);
+
+      // The next one is refused.
+      await expectAnchorError(createOffer(getRandomBigNumber()), "TooManyOpenOffers");
+
+      // Cancelling an offer frees a slot again.
+      const cancelSignature = await program.methods
+        .cancelOffer(false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc();
+      await confirmTransaction(connection, cancelSignature);
+      await createOffer(getRandomBigNumber());
+    } finally {
+      // Lift the cap so the remaining tests are unaffected.
+      await setCap(0);
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test the take cost for each combination of the token fee and the flat SOL fee.
+  it("Splits a take's cost correctly under bps-only, flat-only, and combined fees", async () => {
+    const flatFeeLamports = 5_000;
+    const airdropSignature = await connection.requestAirdrop(accounts.treasury, LAMPORTS_PER_SOL);
+    await confirmTransaction(connection, airdropSignature);
+
+    const setFees = async (feeBps: number, flatFee: number) => {
+      const signature = await program.methods
+        .updateConfig(feeBps, false, 0, new BN(0), new BN(flatFee), 0, false)
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, signature);
+    };
+
+    try {
+      for (const [feeBps, flatFee] of [
+        [50, 0],
+        [0, flatFeeLamports],
+        [50, flatFeeLamports],
+      ]) {
+        await setFees(feeBps, flatFee);
+        const { offer, vault } = await createOffer(getRandomBigNumber());
+        const bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+        const aliceBalanceBBefore = await getTokenBalance(accounts.makerTokenAccountB);
+        const treasuryBalanceBBefore = await getTokenBalance(accounts.treasuryTokenAccountB);
+        const treasuryLamportsBefore = await connection.getBalance(accounts.treasury);
+
+        const signature = await program.methods
+          .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+          .accounts({ ...accounts, offer, vault })
+          .signers([bob])
+          .rpc();
+        await confirmTransaction(connection, signature);
+
+        // The taker pays exactly the wanted amount in Token B; the fee comes out of it.
+        const fee = protocolFee(tokenBWantedAmount, feeBps);
+        const bobPaid = bobBalanceBBefore.sub(await getTokenBalance(accounts.takerTokenAccountB));
+        const aliceReceived = (await getTokenBalance(accounts.makerTokenAccountB)).sub(
+          aliceBalanceBBefore
+        );
+        const treasuryReceived = (await getTokenBalance(accounts.treasuryTokenAccountB)).sub(
+          treasuryBalanceBBefore
+        );
+        assert(bobPaid.eq(tokenBWantedAmount));
+        assert(aliceReceived.eq(tokenBWantedAmount.sub(fee)));
+        assert(treasuryReceived.eq(fee));
+
+        // The flat fee is paid separately, in SOL.
+        const treasuryLamportsAfter = await connection.getBalance(accounts.treasury);
+        assert.equal(treasuryLamportsAfter - treasuryLamportsBefore, flatFee);
+      }
+    } finally {
+      // Restore the default fees so the remaining tests are unaffected.
+      await setFees(FEE_BPS, 0);
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that completing an offer refunds the vault's rent to the maker who paid for it.
+  it("Returns the vault's and the offer's rent to Alice when Bob takes the whole offer", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const vaultRent = (await connection.getAccountInfo(vault)).lamports;
+    const offerRent = (await connection.getAccountInfo(offer)).lamports;
+    const aliceLamportsBefore = await connection.getBalance(alice.publicKey);
+
+    const signature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // Alice does not sign the take, so her balance grows by exactly the two refunds.
+    const aliceLamportsAfter = await connection.getBalance(alice.publicKey);
+    assert.equal(aliceLamportsAfter - aliceLamportsBefore, vaultRent + offerRent);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a taker paying in wrapped SOL keeps their existing wrapped account and balance.
+  it("Lets Bob pay for an offer from his pre-wrapped SOL account, leaving it open", async () => {
+    const wantedLamports = new BN(LAMPORTS_PER_SOL / 10);
+    const wrappedLamports = new BN(LAMPORTS_PER_SOL / 4);
+
+    // A legacy Token A mint, since Bob's wrapped SOL lives under the legacy token program.
+    const legacyMintA = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      null,
+      6,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    const ata = (mint: PublicKey, owner: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, owner, true, TOKEN_PROGRAM_ID);
+    const aliceLegacyTokenAccountA = await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      legacyMintA,
+      alice.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    await mintTo(
+      connection,
+      payer,
+      legacyMintA,
+      aliceLegacyTokenAccountA.address,
+      payer,
+      tokenAOfferedAmount.toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    const bobWrappedSol = await createWrappedNativeAccount(
+      connection,
+      payer,
+      bob.publicKey,
+      wrappedLamports.toNumber(),
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      NATIVE_MINT,
+      alice.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+
+    const offerId = getRandomBigNumber();
+    const { offer } = deriveOfferAccounts(offerId);
+    const wrappedAccounts = {
+      ...accounts,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      tokenMintA: legacyMintA,
+      tokenMintB: NATIVE_MINT,
+      makerTokenAccountA: aliceLegacyTokenAccountA.address,
+      makerTokenAccountB: ata(NATIVE_MINT, alice.publicKey),
+      takerTokenAccountA: ata(legacyMintA, bob.publicKey),
+      takerTokenAccountB: bobWrappedSol,
+      treasuryTokenAccountB: ata(NATIVE_MINT, accounts.treasury),
+      offer,
+      vault: ata(legacyMintA, offer),
+    };
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        wantedLamports,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts(wrappedAccounts)
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts(wrappedAccounts)
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+
+    // Bob's wrapped SOL account is still open, holding what he did not spend.
+    assert.isNotNull(await connection.getAccountInfo(bobWrappedSol));
+    assert((await getTokenBalance(bobWrappedSol)).eq(wrappedLamports.sub(wantedLamports)));
+    assert((await getTokenBalance(wrappedAccounts.takerTokenAccountA)).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that only the config admin can force-close an offer, and only back to its maker.
+  it("Lets the admin force-close Alice's offer back to her, but not Bob", async () => {
+    const aliceBalanceBefore = await getTokenBalance(accounts.makerTokenAccountA);
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+
+    // Bob is not the admin, so he cannot close it.
+    await expectAnchorError(
+      program.methods
+        .adminCloseOffer()
+        .accounts({ ...accounts, admin: bob.publicKey, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "Unauthorized"
+    );
+
+    const signature = await program.methods
+      .adminCloseOffer()
+      .accounts({ ...accounts, admin: payer.publicKey, offer, vault })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // Validate Alice got all of her Token A back and both accounts are gone.
+    const aliceBalanceAfter = await getTokenBalance(accounts.makerTokenAccountA);
+    assert(aliceBalanceAfter.eq(aliceBalanceBefore));
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert.isNull(await connection.getAccountInfo(vault));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the admin can blacklist a mint, blocking new offers on it until it is removed.
+  it("Rejects Alice's offer on a mint the admin blacklisted, until it is removed", async () => {
+    const blacklist = PublicKey.findProgramAddressSync([Buffer.from("blacklist")], program.programId)[0];
+
+    // Only the admin may change the blacklist.
+    await expectAnchorError(
+      program.methods
+        .addBlacklistedMint(accounts.tokenMintB)
+        .accounts({ admin: bob.publicKey, config: accounts.config, blacklist })
+        .signers([bob])
+        .rpc(),
+      "Unauthorized"
+    );
+
+    const addSignature = await program.methods
+      .addBlacklistedMint(accounts.tokenMintB)
+      .accounts({ admin: payer.publicKey, config: accounts.config, blacklist })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, addSignature);
+
+    try {
+      // Token B is now blocked as the wanted token.
+      await expectAnchorError(createOffer(getRandomBigNumber()), "MintBlacklisted");
+    } finally {
+      // Unblock it so the remaining tests can run.
+      const removeSignature = await program.methods
+        .removeBlacklistedMint(accounts.tokenMintB)
+        .accounts({ admin: payer.publicKey, config: accounts.config, blacklist })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, removeSignature);
+    }
+
+    const { offer } = await createOffer(getRandomBigNumber());
+    assert.isNotNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an enabled allowlist limits new offers to its mints, and a disabled one changes nothing.
+  it("Rejects Alice's offer on a mint missing from the enabled allowlist", async () => {
+    const allowlist = PublicKey.findProgramAddressSync([Buffer.from("allowlist")], program.programId)[0];
+    const setAllowlistEnabled = async (enabled: boolean) => {
+      const signature = await program.methods
+        .updateConfig(FEE_BPS, false, 0, new BN(0), new BN(0), 0, enabled)
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, signature);
+    };
+    const updateAllowlist = async (mint: PublicKey, allowed: boolean) => {
+      const method = allowed ? program.methods.addAllowedMint(mint) : program.methods.removeAllowedMint(mint);
+      const signature = await method
+        .accounts({ admin: payer.publicKey, config: accounts.config, allowlist })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, signature);
+    };
+
+    // Only Token A is on the list: it has no effect until the allowlist is enabled.
+    await updateAllowlist(accounts.tokenMintA, true);
+    await createOffer(getRandomBigNumber());
+
+    await setAllowlistEnabled(true);
+    try {
+      // Token B is not on the list.
+      await expectAnchorError(createOffer(getRandomBigNumber()), "MintNotAllowed");
+
+      // Once both mints are allowed, the offer goes through.
+      await updateAllowlist(accounts.tokenMintB, true);
+      const { offer } = await createOffer(getRandomBigNumber());
+      assert.isNotNull(await connection.getAccountInfo(offer));
+    } finally {
+      // Disable and empty the list so the remaining tests can run.
+      await setAllowlistEnabled(false);
+      await updateAllowlist(accounts.tokenMintA, false);
+      await updateAllowlist(accounts.tokenMintB, false);
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that Alice's offer index lists the offers she made with it, until they are pruned.
+  it("Lists Alice's offers in her maker index and prunes the cancelled one", async () => {
+    const makerIndex = PublicKey.findProgramAddressSync(
+      [Buffer.from("maker_index"), alice.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    const createSignature = await program.methods
+      .createMakerIndex()
+      .accounts({ maker: alice.publicKey, makerIndex })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, createSignature);
+
+    // Alice makes two offers, appending each to her index.
+    const offers = [];
+    for (const offerId of [getRandomBigNumber(), getRandomBigNumber()]) {
+      const { offer, vault } = deriveOfferAccounts(offerId);
+      const signature = await program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, makerIndex, offer, vault })
+        .signers([alice])
+        .rpc();
+      await confirmTransaction(connection, signature);
+      offers.push({ offer, vault });
+    }
+
+    // Validate both offers can be read back from the index.
+    const index = await program.account.makerIndex.fetch(makerIndex);
+    assert.deepEqual(
+      index.offers.map((offer) => offer.toBase58()),
+      offers.map(({ offer }) => offer.toBase58())
+    );
+    for (const offer of index.offers) {
+      assert((await program.account.offer.fetch(offer)).maker.equals(alice.publicKey));
+    }
+
+    // Once the first offer is cancelled, pruning drops it and keeps the open one.
+    const cancelSignature = await program.methods
+      .cancelOffer(false)
+      .accounts({ ...accounts, ...offers[0] })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, cancelSignature);
+    const pruneSignature = await program.methods
+      .pruneMakerIndex()
+      .accounts({ maker: alice.publicKey, makerIndex })
+      .remainingAccounts(
+        offers.map(({ offer }) => ({ pubkey: offer, isSigner: false, isWritable: false }))
+      )
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, pruneSignature);
+
+    const pruned = await program.account.makerIndex.fetch(makerIndex);
+    assert.deepEqual(
+      pruned.offers.map((offer) => offer.toBase58()),
+      [offers[1].offer.toBase58()]
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an expiry must give takers at least the minimum offer duration.
+  it("Rejects an expiry just under the minimum offer duration but accepts one just over it", async () => {
+    const now = await getValidatorTime();
+
+    await expectAnchorError(
+      createOffer(getRandomBigNumber(), tokenAOfferedAmount, tokenBWantedAmount, now.addn(MIN_OFFER_DURATION - 5)),
+      "ExpiryTooSoon"
+    );
+
+    const expiry = now.addn(MIN_OFFER_DURATION + 5);
+    const { offer } = await createOffer(getRandomBigNumber(), tokenAOfferedAmount, tokenBWantedAmount, expiry);
+    assert((await program.account.offer.fetch(offer)).expiry.eq(expiry));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that new offers carry the current layout version and cannot be migrated again.
+  it("Stamps new offers with the current version and refuses to migrate them", async () => {
+    const { offer } = await createOffer(getRandomBigNumber());
+    assert.equal((await program.account.offer.fetch(offer)).version, 6);
+
+    // The offer is already at the current version, so there is nothing to migrate to.
+    await expectAnchorError(
+      program.methods
+        .migrateOffer()
+        .accounts({ maker: alice.publicKey, offer })
+        .signers([alice])
+        .rpc(),
+      "InvalidOfferVersion"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that resizing an offer to the current layout keeps every stored field intact.
+  it("Resizes Alice's offer to the current layout without changing its fields", async () => {
+    const { offer } = await createOffer(
+      getRandomBigNumber(),
+      tokenAOfferedAmount,
+      tokenBWantedAmount,
+      NO_EXPIRY,
+      null,
+      NO_MIN_FILL,
+      "Selling A for B"
+    );
+    const before = await program.account.offer.fetch(offer);
+    const sizeBefore = (await connection.getAccountInfo(offer)).data.length;
+
+    const signature = await program.methods
+      .resizeOffer()
+      .accounts({ maker: alice.publicKey, offer })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // The offer was already at the current size, and its fields are untouched.
+    const after = await program.account.offer.fetch(offer);
+    assert.equal((await connection.getAccountInfo(offer)).data.length, sizeBefore);
+    assert.equal(after.label, "Selling A for B");
+    assert(after.tokenARemaining.eq(before.tokenARemaining));
+    assert(after.tokenBWantedAmount.eq(before.tokenBWantedAmount));
+    assert.equal(after.bump, before.bump);
+
+    // Only the maker may resize it.
+    await expectAnchorError(
+      program.methods
+        .resizeOffer()
+        .accounts({ maker: bob.publicKey, offer })
+        .signers([bob])
+        .rpc(),
+      "Unauthorized"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer account too short for the current layout is grown, with its fields kept.
+  it("Resizes an offer made before versioning to the current layout", async () => {
+    // The validator is seeded with this offer (see Anchor.toml). Its maker is baked into the offer's
+    // address, so it is derived from a fixed public seed rather than generated: a throwaway key
+    // that guards nothing.
+    const legacyMaker = Keypair.fromSeed(
+      createHash("sha256").update("swap-app legacy offer maker").digest()
+    );
+    const offer = new PublicKey("2Av6Aew2HvvzcphwjLJcwvHS3Pd6jKTeJvoMVcuFVgrv");
+    const airdrop = await connection.requestAirdrop(legacyMaker.publicKey, LAMPORTS_PER_SOL);
+    await confirmTransaction(connection, airdrop);
+    const sizeBefore = (await connection.getAccountInfo(offer)).data.length;
+    const currentSize = program.account.offer.size;
+    assert.isBelow(sizeBefore, currentSize);
+
+    const signature = await program.methods
+      .resizeOffer()
+      .accounts({ maker: legacyMaker.publicKey, offer })
+      .signers([legacyMaker])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // The account is now the current size, its old fields intact and the new ones defaulted.
+    const resized = await connection.getAccountInfo(offer);
+    assert.equal(resized.data.length, currentSize);
+    assert.isAtLeast(resized.lamports, await connection.getMinimumBalanceForRentExemption(currentSize));
+    const after = await program.account.offer.fetch(offer);
+    assert(after.maker.equals(legacyMaker.publicKey));
+    assert(after.id.eq(new BN(7)));
+    assert.equal(after.label, "Made before versioning");
+    assert(after.tokenARemaining.eq(new BN(100)));
+    assert(after.tokenBWantedAmount.eq(new BN(50)));
+    assert.equal(after.version, 0);
+    assert(after.rentPayer.equals(PublicKey.default));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a take from a vault holding less than the offer records fails before Bob pays.
+  it("Refuses to take an offer whose vault was drained with VaultUnderfunded", async () => {
+    // The validator is seeded with this offer and its vault (see Anchor.toml): it promises 100 of a
+    // legacy Token A mint for 50 lamports of wrapped SOL, but its vault only holds 40. Its maker is
+    // a bare address, since nothing signs for it.
+    const drainedMaker = new PublicKey("EkSkZ4mHApuPuaqivuJbuPbMbKtmNQf8pF2HwYHUa9nc");
+    const drainedMintA = new PublicKey("5Y6v93JN8jBxDJ7QQpNRQMXPkKdzRBdtGcRimR3MX68g");
+    const offer = new PublicKey("DLW5S5P7DSfSgM4N2rVbwBLMuMEmsKcp5pnEWWv17Moa");
+    const vault = new PublicKey("EWeWuhy8TTe8wD8TcRp2zpidZoh2UbKQ4HfrAYqsMkSt");
+    const ata = (mint: PublicKey, owner: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, owner, true, TOKEN_PROGRAM_ID);
+    const bobWrappedSol = await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      NATIVE_MINT,
+      bob.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      NATIVE_MINT,
+      drainedMaker,
+      true,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    const before = await program.account.offer.fetch(offer);
+    assert(before.tokenARemaining.eq(new BN(100)));
+    assert((await getTokenBalance(vault)).eq(new BN(40)));
+    const bobBalanceBBefore = await getTokenBalance(bobWrappedSol.address);
+    const makerBalanceBBefore = await getTokenBalance(ata(NATIVE_MINT, drainedMaker));
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(new BN(10), NO_PRICE_LIMIT, "", false)
+        .accounts({
+          ...accounts,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          maker: drainedMaker,
+          rentPayer: drainedMaker,
+          tokenMintA: drainedMintA,
+          tokenMintB: NATIVE_MINT,
+          takerTokenAccountA: ata(drainedMintA, bob.publicKey),
+          takerTokenAccountB: bobWrappedSol.address,
+          makerTokenAccountB: ata(NATIVE_MINT, drainedMaker),
+          treasuryTokenAccountB: ata(NATIVE_MINT, accounts.treasury),
+          offer,
+          vault,
+        })
+        .signers([bob])
+        .rpc(),
+      "VaultUnderfunded"
+    );
+
+    // No Token B moved, and the offer and its vault are untouched.
+    assert((await getTokenBalance(bobWrappedSol.address)).eq(bobBalanceBBefore));
+    assert((await getTokenBalance(ata(NATIVE_MINT, drainedMaker))).eq(makerBalanceBBefore));
+    assert((await getTokenBalance(vault)).eq(new BN(40)));
+    assert((await program.account.offer.fetch(offer)).tokenARemaining.eq(new BN(100)));
+
+    // The integrity check names the same shortfall.
+    await expectAnchorError(
+      program.methods.verifyOfferIntegrity().accounts({ offer, vault }).rpc(),
+      "VaultBalanceMismatch"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer stored away from the address its bump derives is flagged.
+  it("Flags an offer stored away from its derived address with InvalidOfferBump", async () => {
+    // The validator is seeded with a copy of the drained offer at an address hashed from a fixed
+    // seed (see Anchor.toml), where no instruction could have created it.
+    const offer = new PublicKey("Hr2dWqcRVNVwwkBPNXhSF6kGigBJ8ZgAgMyRMKSSbQXi");
+    const vault = new PublicKey("EWeWuhy8TTe8wD8TcRp2zpidZoh2UbKQ4HfrAYqsMkSt");
+    const stored = await program.account.offer.fetch(offer);
+    const [derived] = PublicKey.findProgramAddressSync(
+      [Buffer.from("offer"), stored.creator.toBuffer(), stored.id.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    assert.isFalse(derived.equals(offer));
+
+    await expectAnchorError(
+      program.methods.verifyOfferIntegrity().accounts({ offer, vault }).rpc(),
+      "InvalidOfferBump"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer account longer than the current layout is refused rather than truncated.
+  it("Refuses to resize an offer larger than the current layout with OfferShrinkNotAllowed", async () => {
+    // The validator is seeded with a copy of the drained offer 8 bytes past the current layout (see
+    // Anchor.toml), as no offer written so far has been.
+    const offer = new PublicKey("CC6mWPR64Vk2iNcU6z9eKEkuFnGQBLcyFwmaVfNjDCiC");
+    const oversize = program.account.offer.size + 8;
+    assert.equal((await connection.getAccountInfo(offer)).data.length, oversize);
+
+    await expectAnchorError(
+      program.methods
+        .resizeOffer()
+        .accounts({ maker: bob.publicKey, offer })
+        .signers([bob])
+        .rpc(),
+      "OfferShrinkNotAllowed"
+    );
+
+    // The account keeps every byte.
+    assert.equal((await connection.getAccountInfo(offer)).data.length, oversize);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a program impersonating the token program is refused before any transfer.
+  it("Rejects a take that passes an arbitrary program as the token program", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+
+    await expectAnchorError(
+      program.methods
+        .takeOfferExistingAccounts(tokenAOfferedAmount, NO_PRICE_LIMIT)
+        .accounts({ ...accounts, offer, vault, tokenProgram: SystemProgram.programId })
+        .signers([bob])
+        .rpc(),
+      "InvalidProgramId"
+    );
+
+    // Validate Bob paid nothing and the offer is still open.
+    assert((await getTokenBalance(accounts.takerTokenAccountB)).eq(bobBalanceBBefore));
+    assert.isNotNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a quote matches what the take then charges, under the protocol fee.
+  it("Quotes what Bob would pay for half of a fee-bearing offer, matching the take", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const half = tokenAOfferedAmount.divn(2);
+    const expectedTokenB = tokenBWantedAmount.divn(2);
+
+    const quote = await program.methods
+      .quoteTake(half)
+      .accounts({ config: accounts.config, offer })
+      .view();
+    assert(quote.tokenAAmount.eq(half));
+    assert(quote.tokenBAmount.eq(expectedTokenB));
+    assert(quote.protocolFee.eq(protocolFee(expectedTokenB)));
+    assert(quote.royalty.isZero());
+    assert(quote.makerAmount.eq(expectedTokenB.sub(protocolFee(expectedTokenB))));
+
+    // Taking the quoted amount costs Bob exactly the quoted Token B.
+    const bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+    const signature = await program.methods
+      .takeOffer(half, quote.tokenBAmount, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, signature);
+    const bobBalanceBAfter = await getTokenBalance(accounts.takerTokenAccountB);
+    assert(bobBalanceBBefore.sub(bobBalanceBAfter).eq(quote.tokenBAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an oracle-priced offer is priced from its feed plus the spread, and refuses stale prices.
+  it("Prices Alice's oracle offer from a mock feed plus her spread, and rejects a stale feed", async () => {
+    const spreadBps = 100; // 1% over the oracle price.
+    const priceFeed = Keypair.generate();
+    const createFeedSignature = await program.methods
+      .createPriceFeed(new BN(2), 0) // One whole Token A is worth two whole Token B.
+      .accounts({ authority: payer.publicKey, priceFeed: priceFeed.publicKey })
+      .signers([payer, priceFeed])
+      .rpc();
+    await confirmTransaction(connection, createFeedSignature);
+
+    const makeOracleOffer = async () => {
+      const offerId = getRandomBigNumber();
+      const { offer, vault } = deriveOfferAccounts(offerId);
+      const signature = await program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          priceFeed.publicKey,
+          spreadBps,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc();
+      await confirmTransaction(connection, signature);
+      return { offer, vault };
+    };
+
+    // Both mints have the same decimals, so the offer asks twice its Token A, plus the spread.
+    const { offer, vault } = await makeOracleOffer();
+    const stored = await program.account.offer.fetch(offer);
+    assert.equal(stored.tokenADecimals, stored.tokenBDecimals);
+    const expectedTokenB = tokenAOfferedAmount.muln(2).muln(10_000 + spreadBps).divn(10_000);
+
+    const quote = await program.methods
+      .quoteTake(tokenAOfferedAmount)
+      .accounts({ config: accounts.config, offer, priceFeed: priceFeed.publicKey })
+      .view();
+    assert(quote.tokenBAmount.eq(expectedTokenB));
+
+    // Bob pays the oracle price rather than the offer's fixed amount.
+    const bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault, priceFeed: priceFeed.publicKey })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+    const bobBalanceBAfter = await getTokenBalance(accounts.takerTokenAccountB);
+    assert(bobBalanceBBefore.sub(bobBalanceBAfter).eq(expectedTokenB));
+
+    // Once the feed has not been updated for longer than the program accepts, takes fail.
+    const stale = await makeOracleOffer();
+    const publishTime = (await program.account.priceFeed.fetch(priceFeed.publicKey)).publishTime;
+    await waitForValidatorTime(publishTime.addn(MAX_ORACLE_AGE));
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, ...stale, priceFeed: priceFeed.publicKey })
+        .signers([bob])
+        .rpc(),
+      "StaleOracle"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a committed take only goes through when the revealed nonce matches.
+  it("Takes an offer through commit and reveal, rejecting a reveal with the wrong nonce", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const commitment = PublicKey.findProgramAddressSync(
+      [Buffer.from("commit"), offer.toBuffer(), bob.publicKey.toBuffer()],
+      program.programId
+    )[0];
+
+    // Bob commits to the hash of his key and a secret nonce.
+    const nonce = randomBytes(32);
+    const hash = createHash("sha256").update(bob.publicKey.toBuffer()).update(nonce).digest();
+    const commitSignature = await program.methods
+      .commitTake([...hash])
+      .accounts({ taker: bob.publicKey, offer, commitment })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, commitSignature);
+
+    // Wait out the reveal delay.
+    const committedSlot = (await program.account.takeCommitment.fetch(commitment)).slot.toNumber();
+    while ((await connection.getSlot()) < committedSlot + 2) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+
+    const reveal = (revealedNonce: Buffer) =>
+      program.methods
+        .revealTake([...revealedNonce], tokenAOfferedAmount, NO_PRICE_LIMIT, false)
+        .accounts({ take: { ...accounts, offer, vault }, commitment })
+        .signers([bob])
+        .rpc();
+
+    // Any other nonce is refused.
+    await expectAnchorError(reveal(randomBytes(32)), "CommitmentMismatch");
+
+    // The real nonce takes the offer and consumes the commitment.
+    const bobBalanceABefore = await getTokenBalance(accounts.takerTokenAccountA);
+    await confirmTransaction(connection, await reveal(nonce));
+    const bobBalanceAAfter = await getTokenBalance(accounts.takerTokenAccountA);
+    assert(bobBalanceAAfter.sub(bobBalanceABefore).eq(tokenAOfferedAmount));
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert.isNull(await connection.getAccountInfo(commitment));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that only the admin can close the config, and that its rent goes back to them.
+  it("Lets the admin close the config and reclaim its rent, but not Bob", async () => {
+    const [blacklist] = PublicKey.findProgramAddressSync([Buffer.from("blacklist")], program.programId);
+    const [allowlist] = PublicKey.findProgramAddressSync([Buffer.from("allowlist")], program.programId);
+    const [feeExempt] = PublicKey.findProgramAddressSync([Buffer.from("fee_exempt")], program.programId);
+    const closeAccounts = { config: accounts.config, blacklist, allowlist, feeExempt };
+
+    await expectAnchorError(
+      program.methods
+        .closeConfig()
+        .accounts({ admin: bob.publicKey, ...closeAccounts })
+        .signers([bob])
+        .rpc(),
+      "Unauthorized"
+    );
+
+    let rent = 0;
+    for (const account of [accounts.config, blacklist, allowlist, feeExempt]) {
+      rent += (await connection.getAccountInfo(account)).lamports;
+    }
+    const adminLamportsBefore = await connection.getBalance(payer.publicKey);
+    try {
+      const signature = await program.methods
+        .closeConfig()
+        .accounts({ admin: payer.publicKey, ...closeAccounts })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, signature);
+
+      // Validate the accounts are gone and the admin got their rent, less the transaction fee.
+      assert.isNull(await connection.getAccountInfo(accounts.config));
+      const adminLamportsAfter = await connection.getBalance(payer.publicKey);
+      assert.isAbove(adminLamportsAfter - adminLamportsBefore, rent - 0.001 * LAMPORTS_PER_SOL);
+    } finally {
+      // Re-create the config so the remaining tests can run.
+      const initializeSignature = await program.methods
+        .initializeConfig(FEE_BPS)
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, initializeSignature);
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a take paying the maker's Token B straight back into the taker's account is refused.
+  it("Rejects a take when the offer's Token B destination is Bob's own paying account", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        accounts.takerTokenAccountB,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({
+          ...accounts,
+          offer,
+          vault,
+          makerTokenAccountB: null,
+          tokenBDestination: accounts.takerTokenAccountB,
+        })
+        .signers([bob])
+        .rpc(),
+      "AccountAliasConflict"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that cancelling a partially filled offer refunds only what is left in the vault.
+  it("Refunds only the unfilled Token A when Alice cancels a partially filled offer", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const filled = tokenAOfferedAmount.divn(4);
+
+    const takeSignature = await program.methods
+      .takeOffer(filled, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+
+    const aliceBalanceBefore = await getTokenBalance(accounts.makerTokenAccountA);
+    const cancelSignature = await program.methods
+      .cancelOffer(false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, cancelSignature);
+
+    // Validate Alice got back exactly the three quarters Bob did not take.
+    const aliceBalanceAfter = await getTokenBalance(accounts.makerTokenAccountA);
+    assert(aliceBalanceAfter.sub(aliceBalanceBefore).eq(tokenAOfferedAmount.sub(filled)));
+    assert.isNull(await connection.getAccountInfo(vault));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a third party can pay an offer's rent and gets all of it back when the offer closes.
+  it("Lets a sponsor pay an offer's rent and refunds it to them on cancel", async () => {
+    const sponsor = await createFundedWallet();
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const sponsorLamportsBefore = await connection.getBalance(sponsor.publicKey);
+
+    // Alice authorizes the deposit while the sponsor pays for the offer and its vault.
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, rentPayer: sponsor.publicKey, offer, vault })
+      .signers([alice, sponsor])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+    assert((await program.account.offer.fetch(offer)).rentPayer.equals(sponsor.publicKey));
+    const rent =
+      (await connection.getAccountInfo(offer)).lamports +
+      (await connection.getAccountInfo(vault)).lamports;
+    assert.equal(await connection.getBalance(sponsor.publicKey), sponsorLamportsBefore - rent);
+
+    // The rent cannot be redirected to the maker, and batches have no room for a sponsor.
+    await expectAnchorError(
+      program.methods.cancelOffer(false).accounts({ ...accounts, offer, vault }).signers([alice]).rpc(),
+      "RentPayerMismatch"
+    );
+    await expectAnchorError(
+      program.methods
+        .cancelOffersBatch()
+        .accounts({ maker: alice.publicKey, tokenProgram: TOKEN_PROGRAM })
+        .remainingAccounts(
+          [offer, accounts.tokenMintA, vault, accounts.makerTokenAccountA].map((pubkey) => ({
+            pubkey,
+            isSigner: false,
+            isWritable: true,
+          }))
+        )
+        .signers([alice])
+        .rpc(),
+      "BatchAccountsMismatch"
+    );
+
+    // Alice cancels; her tokens come back to her and the rent to the sponsor.
+    const cancelSignature = await program.methods
+      .cancelOffer(false)
+      .accounts({ ...accounts, rentPayer: sponsor.publicKey, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, cancelSignature);
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert.equal(await connection.getBalance(sponsor.publicKey), sponsorLamportsBefore);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that auto-id offers take consecutive ids from the maker's stats and never collide.
+  it("Gives two auto-id offers distinct ids and addresses from Alice's counter", async () => {
+    const makerStats = deriveMakerStats(alice.publicKey);
+    const makeAutoOffer = (offerId: BN) => {
+      const { offer, vault } = deriveOfferAccounts(offerId);
+      return program.methods
+        .makeOfferAuto(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc();
+    };
+
+    // An id other than the counter's is refused.
+    const firstId = (await program.account.makerStats.fetch(makerStats)).nextId;
+    await expectAnchorError(makeAutoOffer(firstId.addn(1)), "OfferIdMismatch");
+
+    await confirmTransaction(connection, await makeAutoOffer(firstId));
+    const secondId = (await program.account.makerStats.fetch(makerStats)).nextId;
+    assert(secondId.eq(firstId.addn(1)));
+    await confirmTransaction(connection, await makeAutoOffer(secondId));
+
+    // The two offers live at different addresses and record their own ids.
+    const first = deriveOfferAccounts(firstId).offer;
+    const second = deriveOfferAccounts(secondId).offer;
+    assert(!first.equals(second));
+    assert((await program.account.offer.fetch(first)).id.eq(firstId));
+    assert((await program.account.offer.fetch(second)).id.eq(secondId));
+    assert((await program.account.makerStats.fetch(makerStats)).nextId.eq(firstId.addn(2)));
+
+    // Reusing a spent id is refused by the counter before it can clash with the first offer.
+    await expectAnchorError(makeAutoOffer(firstId), "OfferIdMismatch");
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the admin can sweep fees out of the treasury and nobody else can.
+  it("Lets only the admin withdraw fees accrued in the treasury", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+
+    const treasuryBalance = await getTokenBalance(accounts.treasuryTokenAccountB);
+    assert(treasuryBalance.gte(protocolFee(tokenBWantedAmount)));
+    const destination = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintB,
+        payer.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    const withdrawAccounts = {
+      config: accounts.config,
+      mint: accounts.tokenMintB,
+      treasuryTokenAccount: accounts.treasuryTokenAccountB,
+      destination,
+      tokenProgram: TOKEN_PROGRAM,
+    };
+
+    // Bob is not the admin, so he cannot move the fees to himself.
+    await expectAnchorError(
+      program.methods
+        .withdrawTreasury(new BN(0))
+        .accounts({ ...withdrawAccounts, admin: bob.publicKey, destination: accounts.takerTokenAccountB })
+        .signers([bob])
+        .rpc(),
+      "Unauthorized"
+    );
+
+    // The admin withdraws one unit, then sweeps the rest.
+    const destinationBefore = await getTokenBalance(destination);
+    const partialSignature = await program.methods
+      .withdrawTreasury(new BN(1))
+      .accounts({ ...withdrawAccounts, admin: payer.publicKey })
+      .rpc();
+    await confirmTransaction(connection, partialSignature);
+    assert((await getTokenBalance(accounts.treasuryTokenAccountB)).eq(treasuryBalance.subn(1)));
+
+    const sweepSignature = await program.methods
+      .withdrawTreasury(new BN(0))
+      .accounts({ ...withdrawAccounts, admin: payer.publicKey })
+      .rpc();
+    await confirmTransaction(connection, sweepSignature);
+    assert((await getTokenBalance(accounts.treasuryTokenAccountB)).isZero());
+    assert((await getTokenBalance(destination)).eq(destinationBefore.add(treasuryBalance)));
+
+    // An empty treasury has nothing left to withdraw.
+    await expectAnchorError(
+      program.methods
+        .withdrawTreasury(new BN(0))
+        .accounts({ ...withdrawAccounts, admin: payer.publicKey })
+        .rpc(),
+      "ZeroAmount"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the integrity check passes a healthy offer and names the invariant a broken one violates.
+  it("Verifies a healthy offer and reports each violated invariant", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const verify = (vaultAccount: PublicKey) =>
+      program.methods.verifyOfferIntegrity().accounts({ offer, vault: vaultAccount }).rpc();
+
+    await confirmTransaction(connection, await verify(vault));
+
+    // Accounts passed off as the vault are told apart from the real one.
+    await expectAnchorError(verify(accounts.takerTokenAccountB), "VaultMintMismatch");
+    await expectAnchorError(verify(accounts.makerTokenAccountA), "VaultAccountMismatch");
+
+    // Tokens sent straight to the vault leave it holding more than the offer records.
+    await transfer(
+      connection,
+      payer,
+      accounts.makerTokenAccountA,
+      vault,
+      alice,
+      1,
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+    await expectAnchorError(verify(vault), "VaultSurplus");
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a maker can be paid into a token account that is not their associated one.
+  it("Pays Alice's Token B into her custodian's non-associated token account", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const custodianAccount = await createAccount(
+      connection,
+      payer,
+      accounts.tokenMintB,
+      alice.publicKey,
+      Keypair.generate(),
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const aliceAtaBalanceBefore = await getTokenBalance(accounts.makerTokenAccountB);
+
+    // An account Alice does not own cannot stand in for hers.
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, makerTokenAccountB: accounts.takerTokenAccountB, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "ConstraintTokenOwner"
+    );
+
+    const signature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, makerTokenAccountB: custodianAccount, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // The custodian account receives the payment and Alice's associated account is untouched.
+    assert(
+      (await getTokenBalance(custodianAccount)).eq(
+        tokenBWantedAmount.sub(protocolFee(tokenBWantedAmount))
+      )
+    );
+    assert((await getTokenBalance(accounts.makerTokenAccountB)).eq(aliceAtaBalanceBefore));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a complete take stays within a compute budget integrators can bundle alongside.
+  it("Fully takes a plain offer within the take's compute budget", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const signature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // The runtime reports what the transaction consumed; a regression past the budget fails here.
+    const transaction = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    assert.isAtMost(transaction.meta.computeUnitsConsumed, TAKE_COMPUTE_BUDGET);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a take failing at the withdrawal, after Token B has already moved, leaves nothing changed.
+  it("Reverts the whole take when withdrawing from a frozen vault fails", async () => {
+    // A Token A mint with a freeze authority, so its vault can be frozen after the offer is made.
+    const freezableMintA = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      payer.publicKey,
+      6,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const aliceFreezableAccountA = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        freezableMintA,
+        alice.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    await mintTo(
+      connection,
+      payer,
+      freezableMintA,
+      aliceFreezableAccountA,
+      payer,
+      tokenAOfferedAmount.toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId, accounts.maker, freezableMintA);
+    const freezableAccounts = {
+      ...accounts,
+      tokenMintA: freezableMintA,
+      makerTokenAccountA: aliceFreezableAccountA,
+      takerTokenAccountA: getAssociatedTokenAddressSync(freezableMintA, bob.publicKey, false, TOKEN_PROGRAM),
+      offer,
+      vault,
+    };
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts(freezableAccounts)
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+    await freezeAccount(connection, payer, vault, freezableMintA, payer, [], undefined, TOKEN_PROGRAM);
+
+    const bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+    const aliceBalanceBBefore = await getTokenBalance(accounts.makerTokenAccountB);
+    const treasuryBalanceBefore = await getTokenBalance(accounts.treasuryTokenAccountB);
+
+    // The Token B transfers succeed, then the token program refuses to move Token A out of
+    // the frozen vault (its AccountFrozen error, 0x11), failing the transaction.
+    let takeError: unknown = null;
+    try {
+      await program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts(freezableAccounts)
+        .signers([bob])
+        .rpc();
+    } catch (error) {
+      takeError = error;
+    }
+    assert.match(String(takeError), /0x11/);
+
+    // Nobody was paid, and the offer still records the whole fill as outstanding.
+    assert((await getTokenBalance(accounts.takerTokenAccountB)).eq(bobBalanceBBefore));
+    assert((await getTokenBalance(accounts.makerTokenAccountB)).eq(aliceBalanceBBefore));
+    assert((await getTokenBalance(accounts.treasuryTokenAccountB)).eq(treasuryBalanceBefore));
+    assert((await program.account.offer.fetch(offer)).tokenARemaining.eq(tokenAOfferedAmount));
+    assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a maker can push an offer's expiry back but never bring it forward.
+  it("Extends an offer's expiry and refuses to shorten it", async () => {
+    const expiry = (await getValidatorTime()).addn(MIN_OFFER_DURATION + 5);
+    const { offer } = await createOffer(getRandomBigNumber(), tokenAOfferedAmount, tokenBWantedAmount, expiry);
+    const extend = (newExpiry: BN, maker = alice) =>
+      program.methods
+        .extendExpiry(newExpiry)
+        .accounts({ maker: maker.publicKey, offer })
+        .signers([maker])
+        .rpc();
+
+    // Only the maker may extend it.
+    await expectAnchorError(extend(expiry.addn(3600), bob), "ConstraintHasOne");
+
+    const extended = expiry.addn(3600);
+    await confirmTransaction(connection, await extend(extended));
+    assert((await program.account.offer.fetch(offer)).expiry.eq(extended));
+
+    // An earlier expiry, or the same one again, is refused.
+    await expectAnchorError(extend(expiry), "InvalidExpiry");
+    await expectAnchorError(extend(extended), "InvalidExpiry");
+    assert((await program.account.offer.fetch(offer)).expiry.eq(extended));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a taker's memo is written into the take's transaction logs, and that long memos are refused.
+  it("Annotates a take with the taker's memo through the Memo program", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const memoProgram = [{ pubkey: MEMO_PROGRAM_ID, isSigner: false, isWritable: false }];
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "x".repeat(MAX_MEMO_LEN + 1), false)
+        .accounts({ ...accounts, offer, vault })
+        .remainingAccounts(memoProgram)
+        .signers([bob])
+        .rpc(),
+      "MemoTooLong"
+    );
+
+    const memo = "Compliance ref 2024-0042";
+    const signature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, memo, false)
+      .accounts({ ...accounts, offer, vault })
+      .remainingAccounts(memoProgram)
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    const transaction = await connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const logs = transaction.meta.logMessages.join("\n");
+    assert.include(logs, `Program ${MEMO_PROGRAM_ID.toBase58()} invoke`);
+    assert.include(logs, memo);
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that takers holding enough of the governance token pay the discounted fee, and others the full fee.
+  it("Discounts the protocol fee for takers holding the governance token", async () => {
+    const governanceThreshold = 100;
+    const discountedFeeBps = 10;
+    const governanceMint = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      null,
+      6,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const bobGovernanceAccount = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        governanceMint,
+        bob.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    const setDiscount = async (mint: PublicKey, threshold: number, feeBps: number) =>
+      confirmTransaction(
+        connection,
+        await program.methods
+          .setFeeDiscount(mint, new BN(threshold), feeBps)
+          .accounts({ admin: payer.publicKey, config: accounts.config })
+          .rpc()
+      );
+
+    // Takes one offer in full and returns the fee the treasury collected.
+    const takeAndMeasureFee = async (governanceTokenAccount: PublicKey | null) => {
+      const { offer, vault } = await createOffer(getRandomBigNumber());
+      const treasuryBefore = await getTokenBalance(accounts.treasuryTokenAccountB);
+      const signature = await program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, governanceTokenAccount, offer, vault })
+        .signers([bob])
+        .rpc();
+      await confirmTransaction(connection, signature);
+      return (await getTokenBalance(accounts.treasuryTokenAccountB)).sub(treasuryBefore);
+    };
+
+    await setDiscount(governanceMint, governanceThreshold, discountedFeeBps);
+    try {
+      // Below the threshold, and without the account, the full fee applies.
+      await mintTo(
+        connection,
+        payer,
+        governanceMint,
+        bobGovernanceAccount,
+        payer,
+        governanceThreshold - 1,
+        [],
+        undefined,
+        TOKEN_PROGRAM
+      );
+      assert((await takeAndMeasureFee(bobGovernanceAccount)).eq(protocolFee(tokenBWantedAmount)));
+      assert((await takeAndMeasureFee(null)).eq(protocolFee(tokenBWantedAmount)));
+
+      // At the threshold, the discounted fee applies.
+      await mintTo(
+        connection,
+        payer,
+        governanceMint,
+        bobGovernanceAccount,
+        payer,
+        1,
+        [],
+        undefined,
+        TOKEN_PROGRAM
+      );
+      assert(
+        (await takeAndMeasureFee(bobGovernanceAccount)).eq(
+          protocolFee(tokenBWantedAmount, discountedFeeBps)
+        )
+      );
+
+      // An account of some other mint does not count.
+      await expectAnchorError(takeAndMeasureFee(accounts.takerTokenAccountA), "GovernanceMintMismatch");
+    } finally {
+      await setDiscount(PublicKey.default, 0, 0);
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a market maker can post a ladder of three offers at different prices in one transaction.
+  it("Posts a three-rung ladder of offers for the same pair", async () => {
+    const ids = [getRandomBigNumber(), getRandomBigNumber(), getRandomBigNumber()];
+    const offeredAmounts = ids.map(() => tokenAOfferedAmount.divn(3));
+    const wantedAmounts = [tokenBWantedAmount, tokenBWantedAmount.muln(11).divn(10), tokenBWantedAmount.muln(12).divn(10)];
+    const rungs = ids.map((id) => deriveOfferAccounts(id));
+    const rungAccounts = rungs.flatMap(({ offer, vault }) =>
+      [offer, vault].map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }))
+    );
+    const aliceBalanceABefore = await getTokenBalance(accounts.makerTokenAccountA);
+    const openOffersBefore = (await program.account.makerStats.fetch(deriveMakerStats(alice.publicKey))).openOffers;
+
+    // Mismatched arrays are refused before anything is created.
+    await expectAnchorError(
+      program.methods
+        .makeOffersLadder(ids, offeredAmounts.slice(1), wantedAmounts, NO_EXPIRY)
+        .accounts({ ...accounts })
+        .remainingAccounts(rungAccounts)
+        .signers([alice])
+        .rpc(),
+      "BatchAccountsMismatch"
+    );
+
+    const signature = await program.methods
+      .makeOffersLadder(ids, offeredAmounts, wantedAmounts, NO_EXPIRY)
+      .accounts({ ...accounts })
+      .remainingAccounts(rungAccounts)
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // Every rung is its own offer at its own price, with its Token A in its own vault.
+    for (const [i, { offer, vault }] of rungs.entries()) {
+      const offerAccount = await program.account.offer.fetch(offer);
+      assert(offerAccount.id.eq(ids[i]));
+      assert(offerAccount.tokenARemaining.eq(offeredAmounts[i]));
+      assert(offerAccount.tokenBWantedAmount.eq(wantedAmounts[i]));
+      assert((await getTokenBalance(vault)).eq(offeredAmounts[i]));
+    }
+    const totalOffered = offeredAmounts.reduce((total, amount) => total.add(amount), new BN(0));
+    assert((await getTokenBalance(accounts.makerTokenAccountA)).eq(aliceBalanceABefore.sub(totalOffered)));
+    assert.equal(
+      (await program.account.makerStats.fetch(deriveMakerStats(alice.publicKey))).openOffers,
+      openOffersBefore + 3
+    );
+
+    // A rung takes like any other offer.
+    const takeSignature = await program.methods
+      .takeOffer(offeredAmounts[0], NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer: rungs[0].offer, vault: rungs[0].vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+    assert.isNull(await connection.getAccountInfo(rungs[0].offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that wrapped SOL stays wrapped unless the taker asks for it to be unwrapped.
+  it("Keeps Bob's wrapped SOL until he takes with unwrap set", async () => {
+    const carol = await createFundedWallet();
+    const solOfferedAmount = new BN(LAMPORTS_PER_SOL / 2);
+    const halfSolAmount = solOfferedAmount.divn(2);
+
+    // The native mint lives under the legacy Token Program, so Token B must as well.
+    const legacyMintB = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      null,
+      6,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    const bobLegacyTokenAccountB = await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      legacyMintB,
+      bob.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    await mintTo(
+      connection,
+      payer,
+      legacyMintB,
+      bobLegacyTokenAccountB.address,
+      payer,
+      1_000_000_000,
+      [],
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    await getOrCreateAssociatedTokenAccount(
+      connection,
+      payer,
+      legacyMintB,
+      carol.publicKey,
+      false,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+
+    const offerId = getRandomBigNumber();
+    const offer = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("offer"),
+        carol.publicKey.toBuffer(),
+        offerId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+    const ata = (mint: PublicKey, owner: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, owner, true, TOKEN_PROGRAM_ID);
+    const nativeAccounts = {
+      ...accounts,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      maker: carol.publicKey,
+      rentPayer: carol.publicKey,
+      tokenMintA: NATIVE_MINT,
+      tokenMintB: legacyMintB,
+      makerTokenAccountA: ata(NATIVE_MINT, carol.publicKey),
+      makerTokenAccountB: ata(legacyMintB, carol.publicKey),
+      takerTokenAccountA: ata(NATIVE_MINT, bob.publicKey),
+      takerTokenAccountB: bobLegacyTokenAccountB.address,
+      treasuryTokenAccountB: ata(legacyMintB, accounts.treasury),
+      offer,
+      vault: ata(NATIVE_MINT, offer),
+    };
+
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        solOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts(nativeAccounts)
+      .signers([carol])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    // Without unwrap, Bob's half arrives as wrapped SOL and his account stays open.
+    const keepSignature = await program.methods
+      .takeOffer(halfSolAmount, NO_PRICE_LIMIT, "", false)
+      .accounts(nativeAccounts)
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, keepSignature);
+    assert((await getTokenBalance(nativeAccounts.takerTokenAccountA)).eq(halfSolAmount));
+
+    // With unwrap, the account is closed and both halves, plus its rent, land as plain SOL.
+    const wrappedAccountLamports = await connection.getBalance(nativeAccounts.takerTokenAccountA);
+    const bobLamportsBefore = await connection.getBalance(bob.publicKey);
+    const unwrapSignature = await program.methods
+      .takeOffer(solOfferedAmount.sub(halfSolAmount), NO_PRICE_LIMIT, "", true)
+      .accounts(nativeAccounts)
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, unwrapSignature);
+
+    assert.isNull(await connection.getAccountInfo(nativeAccounts.takerTokenAccountA));
+    const bobLamportsAfter = await connection.getBalance(bob.publicKey);
+    assert.equal(
+      bobLamportsAfter - bobLamportsBefore,
+      wrappedAccountLamports + solOfferedAmount.sub(halfSolAmount).toNumber()
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a deposit leaving the vault holding more than the offer records is refused.
+  it("Refuses a SOL offer whose vault address was pre-funded with DepositMismatch", async () => {
+    const carol = await createFundedWallet();
+    const solOfferedAmount = new BN(LAMPORTS_PER_SOL / 2);
+    const strayLamports = 1_000;
+
+    // The native mint lives under the legacy Token Program, so Token B must as well.
+    const legacyMintB = await createMint(connection, payer, payer.publicKey, null, 6, undefined, undefined, TOKEN_PROGRAM_ID);
+    const offerId = getRandomBigNumber();
+    const offer = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("offer"),
+        carol.publicKey.toBuffer(),
+        offerId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+    const ata = (mint: PublicKey, owner: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, owner, true, TOKEN_PROGRAM_ID);
+    const vault = ata(NATIVE_MINT, offer);
+
+    // Anyone can send SOL to the vault's address before it exists. Wrapped SOL counts whatever the
+    // account holds past its rent as tokens, so the vault opens already holding some.
+    const vaultRent = await connection.getMinimumBalanceForRentExemption(ACCOUNT_SIZE);
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        SystemProgram.transfer({ fromPubkey: payer.publicKey, toPubkey: vault, lamports: vaultRent + strayLamports })
+      ),
+      [payer]
+    );
+
+    await expectAnchorError(
+      program.methods
+        .makeOffer(
+          offerId,
+          solOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({
+          ...accounts,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          maker: carol.publicKey,
+          rentPayer: carol.publicKey,
+          tokenMintA: NATIVE_MINT,
+          tokenMintB: legacyMintB,
+          makerTokenAccountA: ata(NATIVE_MINT, carol.publicKey),
+          offer,
+          vault,
+        })
+        .signers([carol])
+        .rpc(),
+      "DepositMismatch"
+    );
+
+    // No offer was made, and the stray SOL is still sitting at the vault's address.
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert.equal(await connection.getBalance(vault), vaultRent + strayLamports);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a take refuses a vault the offer does not own.
+  it("Refuses to take from a vault owned by another authority", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const strangerVault = await createAccount(
+      connection,
+      payer,
+      accounts.tokenMintA,
+      Keypair.generate().publicKey,
+      Keypair.generate(),
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+    // The vault's account constraints reject it through either take instruction.
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault: strangerVault })
+        .signers([bob])
+        .rpc(),
+      "ConstraintTokenOwner"
+    );
+    await expectAnchorError(
+      program.methods
+        .takeOfferExistingAccounts(tokenAOfferedAmount, NO_PRICE_LIMIT)
+        .accounts({ ...accounts, offer, vault: strangerVault })
+        .signers([bob])
+        .rpc(),
+      "ConstraintTokenOwner"
+    );
+
+    // The offer and its real vault are untouched.
+    assert((await program.account.offer.fetch(offer)).tokenARemaining.eq(tokenAOfferedAmount));
+    assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a taker rebate is paid out of Token B the maker escrows with the offer, leaving the
+  // maker's proceeds whole, and that what is left of the reserve goes back to the maker.
+  it("Pays Bob back half of his fee out of the Token B Alice escrowed for rebates", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const reserveVault = rebateReserveAccounts(offer)[1].pubkey;
+    const takerRebateBps = 5_000;
+    const fee = protocolFee(tokenBWantedAmount);
+    const rebate = fee.muln(takerRebateBps).divn(10_000);
+    const rebateReserve = rebate.muln(2); // Twice what the take earns, so some is left over.
+    await fundAliceTokenB(rebateReserve);
+
+    // A rebate above the whole fee is refused, as is a rebate without a reserve or a reserve
+    // without a rebate.
+    await expectAnchorError(makeRebateOffer(offerId, 10_001, rebateReserve), "InvalidFeeBps");
+    await expectAnchorError(
+      makeRebateOffer(offerId, takerRebateBps, NO_REBATE_RESERVE, false),
+      "RebateReserveMismatch"
+    );
+    await expectAnchorError(makeRebateOffer(offerId, 0, rebateReserve), "RebateReserveMismatch");
+
+    const aliceBalanceBBeforeMake = await getTokenBalance(accounts.makerTokenAccountB);
+    await confirmTransaction(connection, await makeRebateOffer(offerId, takerRebateBps, rebateReserve));
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert.equal(offerAccount.takerRebateBps, takerRebateBps);
+    assert(offerAccount.rebateReserve.eq(rebateReserve));
+    assert((await getTokenBalance(reserveVault)).eq(rebateReserve));
+    const aliceBalanceBBefore = await getTokenBalance(accounts.makerTokenAccountB);
+    assert(aliceBalanceBBeforeMake.sub(aliceBalanceBBefore).eq(rebateReserve));
+
+    // The quote charges the full price, leaves Alice her full share, and shows the rebate paid back.
+    const quote = await program.methods
+      .quoteTake(tokenAOfferedAmount)
+      .accounts({ config: accounts.config, offer })
+      .view();
+    assert(quote.tokenBAmount.eq(tokenBWantedAmount));
+    assert(quote.makerAmount.eq(tokenBWantedAmount.sub(fee)));
+    assert(quote.takerRebate.eq(rebate));
+
+    // The reserve must be passed while the offer holds one.
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "RebateReserveMismatch"
+    );
+
+    const bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+    const treasuryBalanceBBefore = await getTokenBalance(accounts.treasuryTokenAccountB);
+    const signature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .remainingAccounts(rebateReserveAccounts(offer))
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // Bob nets the rebate and the treasury still receives the full fee.
+    assert(bobBalanceBBefore.sub(await getTokenBalance(accounts.takerTokenAccountB)).eq(tokenBWantedAmount.sub(rebate)));
+    assert((await getTokenBalance(accounts.treasuryTokenAccountB)).sub(treasuryBalanceBBefore).eq(fee));
+
+    // Alice receives her full proceeds plus what is left of her reserve, whose vault is closed, so
+    // over the offer's life she nets the price less the fee and the rebate she funded.
+    const aliceBalanceBAfter = await getTokenBalance(accounts.makerTokenAccountB);
+    assert(aliceBalanceBAfter.sub(aliceBalanceBBefore).eq(tokenBWantedAmount.sub(fee).add(rebateReserve.sub(rebate))));
+    assert(aliceBalanceBAfter.sub(aliceBalanceBBeforeMake).eq(tokenBWantedAmount.sub(fee).sub(rebate)));
+    assert.isNull(await connection.getAccountInfo(reserveVault));
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a rebate is capped at what is left of the reserve, and that once it runs dry its vault
+  // is closed and later takes simply earn no rebate.
+  it("Stops paying Bob rebates once Alice's reserve runs dry", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const reserveVault = rebateReserveAccounts(offer)[1].pubkey;
+    const halfAmount = tokenAOfferedAmount.divn(2);
+    const halfPrice = tokenBWantedAmount.divn(2);
+    const rebateReserve = new BN(1); // Less than the rebate either half earns.
+    await fundAliceTokenB(rebateReserve);
+    await confirmTransaction(connection, await makeRebateOffer(offerId, 5_000, rebateReserve));
+
+    // The first half earns only what is left of the reserve, which runs dry and is closed.
+    let bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+    let aliceBalanceBBefore = await getTokenBalance(accounts.makerTokenAccountB);
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .takeOffer(halfAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .remainingAccounts(rebateReserveAccounts(offer))
+        .signers([bob])
+        .rpc()
+    );
+    assert(bobBalanceBBefore.sub(await getTokenBalance(accounts.takerTokenAccountB)).eq(halfPrice.sub(rebateReserve)));
+    assert((await getTokenBalance(accounts.makerTokenAccountB)).sub(aliceBalanceBBefore).eq(halfPrice.sub(protocolFee(halfPrice))));
+    assert((await program.account.offer.fetch(offer)).rebateReserve.isZero());
+    assert.isNull(await connection.getAccountInfo(reserveVault));
+
+    // The second half is taken without the reserve's accounts, paying the full price.
+    bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+    aliceBalanceBBefore = await getTokenBalance(accounts.makerTokenAccountB);
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .takeOffer(halfAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc()
+    );
+    assert(bobBalanceBBefore.sub(await getTokenBalance(accounts.takerTokenAccountB)).eq(halfPrice));
+    assert((await getTokenBalance(accounts.makerTokenAccountB)).sub(aliceBalanceBBefore).eq(halfPrice.sub(protocolFee(halfPrice))));
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that cancelling an offer hands its whole rebate reserve back to the maker.
+  it("Refunds Alice's rebate reserve when she cancels her offer", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const reserveVault = rebateReserveAccounts(offer)[1].pubkey;
+    const rebateReserve = new BN(10_000);
+    await fundAliceTokenB(rebateReserve);
+    await confirmTransaction(connection, await makeRebateOffer(offerId, 5_000, rebateReserve));
+    const aliceBalanceBBefore = await getTokenBalance(accounts.makerTokenAccountB);
+
+    // Like the basket, the reserve must be passed to refund it.
+    await expectAnchorError(
+      program.methods
+        .cancelOffer(false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc(),
+      "RebateReserveMismatch"
+    );
+
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .cancelOffer(false)
+        .accounts({ ...accounts, offer, vault })
+        .remainingAccounts(rebateReserveAccounts(offer))
+        .signers([alice])
+        .rpc()
+    );
+    assert((await getTokenBalance(accounts.makerTokenAccountB)).sub(aliceBalanceBBefore).eq(rebateReserve));
+    assert.isNull(await connection.getAccountInfo(reserveVault));
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a taker can pay for a wrapped SOL offer in plain SOL.
+  it("Wraps Bob's SOL to pay for wrapped SOL and closes his account when he unwraps", async () => {
+    const wantedLamports = new BN(LAMPORTS_PER_SOL / 10);
+
+    // A legacy Token A mint, since wrapped SOL lives under the legacy token program.
+    const legacyMintA = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      null,
+      6,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    const ata = (mint: PublicKey, owner: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, owner, true, TOKEN_PROGRAM_ID);
+    const openAccount = (mint: PublicKey, owner: PublicKey) =>
+      getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        mint,
+        owner,
+        true,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM_ID
+      );
+    const aliceLegacyTokenAccountA = await openAccount(legacyMintA, alice.publicKey);
+    await mintTo(
+      connection,
+      payer,
+      legacyMintA,
+      aliceLegacyTokenAccountA.address,
+      payer,
+      tokenAOfferedAmount.toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+
+    // Every account the take touches exists up front, so Bob's SOL only moves for the payment.
+    // His wrapped SOL account is empty: the whole price has to come from his SOL.
+    await openAccount(NATIVE_MINT, alice.publicKey);
+    await openAccount(NATIVE_MINT, accounts.treasury);
+    await openAccount(legacyMintA, bob.publicKey);
+    const bobWrappedSol = (await openAccount(NATIVE_MINT, bob.publicKey)).address;
+    assert((await getTokenBalance(bobWrappedSol)).isZero());
+
+    const offerId = getRandomBigNumber();
+    const { offer } = deriveOfferAccounts(offerId);
+    const wrappedAccounts = {
+      ...accounts,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      tokenMintA: legacyMintA,
+      tokenMintB: NATIVE_MINT,
+      makerTokenAccountA: aliceLegacyTokenAccountA.address,
+      makerTokenAccountB: ata(NATIVE_MINT, alice.publicKey),
+      takerTokenAccountA: ata(legacyMintA, bob.publicKey),
+      takerTokenAccountB: bobWrappedSol,
+      treasuryTokenAccountB: ata(NATIVE_MINT, accounts.treasury),
+      offer,
+      vault: ata(legacyMintA, offer),
+    };
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        wantedLamports,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts(wrappedAccounts)
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    const wrappedAccountRent = await connection.getBalance(bobWrappedSol);
+    const bobLamportsBefore = await connection.getBalance(bob.publicKey);
+    const aliceWrappedBefore = await getTokenBalance(wrappedAccounts.makerTokenAccountB);
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", true)
+      .accounts(wrappedAccounts)
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+
+    // Bob paid the price in SOL and got his account's rent back when it was closed.
+    assert.isNull(await connection.getAccountInfo(bobWrappedSol));
+    const bobLamportsAfter = await connection.getBalance(bob.publicKey);
+    assert.equal(bobLamportsAfter - bobLamportsBefore, wrappedAccountRent - wantedLamports.toNumber());
+
+    // Alice received wrapped SOL, less the protocol fee, and Bob his Token A.
+    assert(
+      (await getTokenBalance(wrappedAccounts.makerTokenAccountB))
+        .sub(aliceWrappedBefore)
+        .eq(wantedLamports.sub(protocolFee(wantedLamports)))
+    );
+    assert((await getTokenBalance(wrappedAccounts.takerTokenAccountA)).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test the offer PDA's seed layout at both ends of the id range.
+  it("Derives offer addresses from 8 little-endian id bytes for ids 0 and u64::MAX", async () => {
+    // A fresh maker, so no id of theirs is taken yet.
+    const edgeMaker = await createFundedWallet();
+    const edgeTokenAccountA = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintA,
+        edgeMaker.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    await transfer(
+      connection,
+      payer,
+      accounts.makerTokenAccountA,
+      edgeTokenAccountA,
+      alice,
+      tokenAOfferedAmount.muln(2).toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+    const edgeIds: [BN, Buffer][] = [
+      [new BN(0), Buffer.alloc(8, 0x00)],
+      [new BN("18446744073709551615"), Buffer.alloc(8, 0xff)],
+    ];
+    for (const [id, idBytes] of edgeIds) {
+      // The seed layout: "offer", the maker's 32 key bytes, then exactly 8 little-endian id bytes.
+      assert(id.toArrayLike(Buffer, "le", 8).equals(idBytes));
+      const expectedOffer = PublicKey.findProgramAddressSync(
+        [Buffer.from("offer"), edgeMaker.publicKey.toBuffer(), idBytes],
+        program.programId
+      )[0];
+      const { offer, vault } = deriveOfferAccounts(id, edgeMaker.publicKey);
+      assert(offer.equals(expectedOffer));
+
+      // The program's seeds constraint accepts the offer only at that address.
+      const signature = await program.methods
+        .makeOffer(
+          id,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({
+          ...accounts,
+          maker: edgeMaker.publicKey,
+          rentPayer: edgeMaker.publicKey,
+          makerTokenAccountA: edgeTokenAccountA,
+          offer,
+          vault,
+        })
+        .signers([edgeMaker])
+        .rpc();
+      await confirmTransaction(connection, signature);
+      assert((await program.account.offer.fetch(offer)).id.eq(id));
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a cancel can close the maker's Token A account, and keeps it when it holds tokens.
+  it("Closes Carol's wrapped SOL account on cancel but keeps Alice's funded Token A account", async () => {
+    // Alice's refund lands in her Token A account, so it is not empty and stays open.
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const aliceBalanceABefore = await getTokenBalance(accounts.makerTokenAccountA);
+    const keepSignature = await program.methods
+      .cancelOffer(true)
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, keepSignature);
+    assert((await getTokenBalance(accounts.makerTokenAccountA)).eq(aliceBalanceABefore.add(tokenAOfferedAmount)));
+
+    // Carol offers plain SOL, so her wrapped SOL account was only opened for the offer.
+    const carol = await createFundedWallet();
+    const solOfferedAmount = new BN(LAMPORTS_PER_SOL / 2);
+    const legacyMintB = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      null,
+      6,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    const offerId = getRandomBigNumber();
+    const { offer: solOffer } = deriveOfferAccounts(offerId, carol.publicKey);
+    const ata = (mint: PublicKey, owner: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, owner, true, TOKEN_PROGRAM_ID);
+    const nativeAccounts = {
+      ...accounts,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      canceller: carol.publicKey,
+      maker: carol.publicKey,
+      rentPayer: carol.publicKey,
+      tokenMintA: NATIVE_MINT,
+      tokenMintB: legacyMintB,
+      makerTokenAccountA: ata(NATIVE_MINT, carol.publicKey),
+      offer: solOffer,
+      vault: ata(NATIVE_MINT, solOffer),
+    };
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        solOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts(nativeAccounts)
+      .signers([carol])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+
+    // Cancelling with the flag unwraps the refund and closes the account, so every lamport
+    // Carol put in, rent included, comes back as plain SOL.
+    const carolLamportsBefore = await connection.getBalance(carol.publicKey);
+    const lockedLamports =
+      (await connection.getBalance(nativeAccounts.makerTokenAccountA)) +
+      (await connection.getBalance(nativeAccounts.vault)) +
+      (await connection.getBalance(solOffer));
+    const closeSignature = await program.methods
+      .cancelOffer(true)
+      .accounts(nativeAccounts)
+      .signers([carol])
+      .rpc();
+    await confirmTransaction(connection, closeSignature);
+
+    assert.isNull(await connection.getAccountInfo(nativeAccounts.makerTokenAccountA));
+    assert.equal((await connection.getBalance(carol.publicKey)) - carolLamportsBefore, lockedLamports);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test the configured bounds on the Token A amount of new offers.
+  it("Refuses offers outside the config's minimum and maximum offer amounts", async () => {
+    const minOfferAmount = tokenAOfferedAmount.divn(4);
+    const maxOfferAmount = tokenAOfferedAmount.divn(2);
+    const setLimits = async (min: BN, max: BN) =>
+      confirmTransaction(
+        connection,
+        await program.methods
+          .setOfferAmountLimits(min, max)
+          .accounts({ admin: payer.publicKey, config: accounts.config })
+          .rpc()
+      );
+    const offerOf = (amount: BN) => createOffer(getRandomBigNumber(), amount);
+
+    // A minimum above the maximum is refused.
+    await expectAnchorError(setLimits(maxOfferAmount.addn(1), maxOfferAmount), "InvalidOfferAmountLimits");
+
+    await setLimits(minOfferAmount, maxOfferAmount);
+    try {
+      // Just outside either bound is refused.
+      await expectAnchorError(offerOf(minOfferAmount.subn(1)), "OfferAmountOutOfRange");
+      await expectAnchorError(offerOf(maxOfferAmount.addn(1)), "OfferAmountOutOfRange");
+
+      // Both bounds themselves are allowed, as is anything between them.
+      for (const amount of [minOfferAmount, maxOfferAmount, minOfferAmount.add(maxOfferAmount).divn(2)]) {
+        const { offer } = await offerOf(amount);
+        assert((await program.account.offer.fetch(offer)).tokenAOfferedAmount.eq(amount));
+      }
+
+      // A zero bound turns that side off.
+      await setLimits(new BN(0), maxOfferAmount);
+      await offerOf(new BN(1));
+    } finally {
+      await setLimits(new BN(0), new BN(0));
+    }
+
+    // Without limits, the full amount is accepted again.
+    await offerOf(tokenAOfferedAmount);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a top-up cannot grow an offer past the config's maximum offer amount.
+  it("Refuses to top up Alice's offer past the maximum offer amount", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .setOfferAmountLimits(new BN(0), tokenAOfferedAmount)
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .rpc()
+    );
+    try {
+      await expectAnchorError(
+        program.methods
+          .topUpOffer(new BN(1))
+          .accounts({ ...accounts, offer, vault })
+          .signers([alice])
+          .rpc(),
+        "OfferAmountOutOfRange"
+      );
+      assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount));
+    } finally {
+      await confirmTransaction(
+        connection,
+        await program.methods
+          .setOfferAmountLimits(new BN(0), new BN(0))
+          .accounts({ admin: payer.publicKey, config: accounts.config })
+          .rpc()
+      );
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a rebalance cannot grow an offer past the config's maximum offer amount either.
+  it("Refuses to rebalance Alice's offer past the maximum offer amount", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .setOfferAmountLimits(new BN(0), tokenAOfferedAmount)
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .rpc()
+    );
+    try {
+      await expectAnchorError(
+        program.methods
+          .rebalanceOffer(new BN(1), tokenBWantedAmount.muln(2))
+          .accounts({ ...accounts, offer, vault })
+          .signers([alice])
+          .rpc(),
+        "OfferAmountOutOfRange"
+      );
+      const offerAccount = await program.account.offer.fetch(offer);
+      assert(offerAccount.tokenARemaining.eq(tokenAOfferedAmount));
+      assert(offerAccount.tokenBWantedAmount.eq(tokenBWantedAmount));
+    } finally {
+      await confirmTransaction(
+        connection,
+        await program.methods
+          .setOfferAmountLimits(new BN(0), new BN(0))
+          .accounts({ admin: payer.publicKey, config: accounts.config })
+          .rpc()
+      );
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a reduction cannot shrink an offer below the config's minimum offer amount.
+  it("Refuses to reduce Alice's offer below the minimum offer amount", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .setOfferAmountLimits(tokenAOfferedAmount.divn(2), new BN(0))
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .rpc()
+    );
+    try {
+      await expectAnchorError(
+        program.methods
+          .reduceOffer(tokenAOfferedAmount.divn(2).addn(1))
+          .accounts({ ...accounts, offer, vault })
+          .signers([alice])
+          .rpc(),
+        "OfferAmountOutOfRange"
+      );
+
+      // Reducing to exactly the minimum is allowed.
+      await confirmTransaction(
+        connection,
+        await program.methods
+          .reduceOffer(tokenAOfferedAmount.divn(2))
+          .accounts({ ...accounts, offer, vault })
+          .signers([alice])
+          .rpc()
+      );
+      assert((await program.account.offer.fetch(offer)).tokenARemaining.eq(tokenAOfferedAmount.sub(tokenAOfferedAmount.divn(2))));
+    } finally {
+      await confirmTransaction(
+        connection,
+        await program.methods
+          .setOfferAmountLimits(new BN(0), new BN(0))
+          .accounts({ admin: payer.publicKey, config: accounts.config })
+          .rpc()
+      );
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that pair indexes list each pair's open offers as they are made, cancelled and taken.
+  it("Keeps separate offer lists for the A/B and B/A pairs", async () => {
+    const derivePairIndex = (tokenMintA: PublicKey, tokenMintB: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("pair"), tokenMintA.toBuffer(), tokenMintB.toBuffer()],
+        program.programId
+      )[0];
+    const abIndex = derivePairIndex(accounts.tokenMintA, accounts.tokenMintB);
+    const baIndex = derivePairIndex(accounts.tokenMintB, accounts.tokenMintA);
+    for (const [tokenMintA, tokenMintB] of [
+      [accounts.tokenMintA, accounts.tokenMintB],
+      [accounts.tokenMintB, accounts.tokenMintA],
+    ]) {
+      const signature = await program.methods
+        .createPairIndex()
+        .accounts({ payer: payer.publicKey, tokenMintA, tokenMintB })
+        .rpc();
+      await confirmTransaction(connection, signature);
+    }
+    const listed = async (pairIndex: PublicKey) =>
+      (await program.account.pairIndex.fetch(pairIndex)).offers.map((offer) => offer.toBase58());
+
+    // Alice offers Token A for Token B twice, and Bob offers Token B for Token A once.
+    const makeIndexedOffer = async (maker: Keypair, makerTokenAccountA: PublicKey, tokenMintA: PublicKey, tokenMintB: PublicKey) => {
+      const offerId = getRandomBigNumber();
+      const { offer, vault } = deriveOfferAccounts(offerId, maker.publicKey, tokenMintA);
+      const signature = await program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({
+          ...accounts,
+          maker: maker.publicKey,
+          rentPayer: maker.publicKey,
+          tokenMintA,
+          tokenMintB,
+          makerTokenAccountA,
+          pairIndex: derivePairIndex(tokenMintA, tokenMintB),
+          offer,
+          vault,
+        })
+        .signers([maker])
+        .rpc();
+      await confirmTransaction(connection, signature);
+      return { offer, vault };
+    };
+    const first = await makeIndexedOffer(alice, accounts.makerTokenAccountA, accounts.tokenMintA, accounts.tokenMintB);
+    const second = await makeIndexedOffer(alice, accounts.makerTokenAccountA, accounts.tokenMintA, accounts.tokenMintB);
+    const reverse = await makeIndexedOffer(bob, accounts.takerTokenAccountB, accounts.tokenMintB, accounts.tokenMintA);
+
+    // Each pair only lists its own offers.
+    assert.deepEqual(await listed(abIndex), [first.offer.toBase58(), second.offer.toBase58()]);
+    assert.deepEqual(await listed(baIndex), [reverse.offer.toBase58()]);
+
+    // Cancelling and taking remove offers from their pair.
+    const cancelSignature = await program.methods
+      .cancelOffer(false)
+      .accounts({ ...accounts, pairIndex: abIndex, ...first })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, cancelSignature);
+    assert.deepEqual(await listed(abIndex), [second.offer.toBase58()]);
+
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, pairIndex: abIndex, ...second })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+    assert.deepEqual(await listed(abIndex), []);
+    assert.deepEqual(await listed(baIndex), [reverse.offer.toBase58()]);
+
+    // An offer closed without the index, here by its maker cancelling, is dropped by a prune.
+    const reverseCancelSignature = await program.methods
+      .cancelOffer(false)
+      .accounts({
+        ...accounts,
+        canceller: bob.publicKey,
+        maker: bob.publicKey,
+        rentPayer: bob.publicKey,
+        tokenMintA: accounts.tokenMintB,
+        makerTokenAccountA: accounts.takerTokenAccountB,
+        ...reverse,
+      })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, reverseCancelSignature);
+    const pruneSignature = await program.methods
+      .prunePairIndex()
+      .accounts({ pairIndex: baIndex })
+      .remainingAccounts([{ pubkey: reverse.offer, isSigner: false, isWritable: false }])
+      .rpc();
+    await confirmTransaction(connection, pruneSignature);
+    assert.deepEqual(await listed(baIndex), []);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Property-style test: across many prices, fills, fees and royalties, the fee, the royalty and
+  // the maker's remainder always add up to exactly what the taker pays.
+  it("Splits every Token B payment into parts that sum to the taker's total", async () => {
+    const offeredAmount = new BN(999);
+    // Every price asks at least one Token B base unit per Token A unit, so no fill rounds to nothing.
+    const wantedAmounts = [new BN(1_000), new BN(123_457), new BN(999_999), new BN("1000000000003")];
+    // Fee and royalty can together take up to the whole payment.
+    const royaltyBpsValues = [0, 1, 333, 5_000];
+    const feeBpsValues = [0, FEE_BPS, 5_000];
+    const fills = [new BN(1), new BN(333), offeredAmount];
+    const setFeeBps = async (feeBps: number) =>
+      confirmTransaction(
+        connection,
+        await program.methods
+          .updateConfig(feeBps, false, 0, new BN(0), new BN(0), 0, false)
+          .accounts({ admin: payer.publicKey, config: accounts.config })
+          .rpc()
+      );
+
+    const offers: { offer: PublicKey; royaltyBps: number }[] = [];
+    for (const wantedAmount of wantedAmounts) {
+      for (const royaltyBps of royaltyBpsValues) {
+        const offerId = getRandomBigNumber();
+        const { offer, vault } = deriveOfferAccounts(offerId);
+        const signature = await program.methods
+          .makeOffer(
+            offerId,
+            offeredAmount,
+            wantedAmount,
+            NO_EXPIRY,
+            null,
+            [],
+            NO_MIN_FILL,
+            null,
+            "",
+            null,
+            payer.publicKey,
+            royaltyBps,
+            PublicKey.default,
+            0,
+            0,
+            false,
+            NO_REBATE_RESERVE
+          )
+          .accounts({ ...accounts, offer, vault })
+          .signers([alice])
+          .rpc();
+        await confirmTransaction(connection, signature);
+        offers.push({ offer, royaltyBps });
+      }
+    }
+
+    try {
+      for (const feeBps of feeBpsValues) {
+        await setFeeBps(feeBps);
+        for (const { offer, royaltyBps } of offers) {
+          for (const fill of fills) {
+            const quote = await program.methods
+              .quoteTake(fill)
+              .accounts({ config: accounts.config, offer })
+              .view();
+            assert(
+              quote.protocolFee.add(quote.royalty).add(quote.makerAmount).eq(quote.tokenBAmount),
+              `fee ${feeBps} bps, royalty ${royaltyBps} bps, fill ${fill}`
+            );
+
+            // Fee and royalty are rounded down from the full payment; the rounding dust is the maker's.
+            assert(quote.protocolFee.eq(quote.tokenBAmount.muln(feeBps).divn(10_000)));
+            assert(quote.royalty.eq(quote.tokenBAmount.muln(royaltyBps).divn(10_000)));
+          }
+        }
+      }
+    } finally {
+      await setFeeBps(FEE_BPS);
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test the maker's analytics counters through a full offer lifecycle, and the admin's reset.
+  it("Counts Alice's made and taken offers, and lets only the admin reset the counts", async () => {
+    const makerStats = deriveMakerStats(alice.publicKey);
+    const statsBefore = await program.account.makerStats.fetch(makerStats);
+
+    // Alice makes two offers; Bob fills one in two takes, and Alice cancels the other.
+    const filled = await createOffer(getRandomBigNumber());
+    const cancelled = await createOffer(getRandomBigNumber());
+    const halfOfferedAmount = tokenAOfferedAmount.divn(2);
+    for (const amount of [halfOfferedAmount, tokenAOfferedAmount.sub(halfOfferedAmount)]) {
+      const signature = await program.methods
+        .takeOffer(amount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, ...filled })
+        .signers([bob])
+        .rpc();
+      await confirmTransaction(connection, signature);
+
+      // A partial fill does not count as a taken offer.
+      const stats = await program.account.makerStats.fetch(makerStats);
+      const fullyTaken = amount.eq(halfOfferedAmount) ? 0 : 1;
+      assert(stats.offersTaken.eq(statsBefore.offersTaken.addn(fullyTaken)));
+    }
+    const cancelSignature = await program.methods
+      .cancelOffer(false)
+      .accounts({ ...accounts, ...cancelled })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, cancelSignature);
+
+    const statsAfter = await program.account.makerStats.fetch(makerStats);
+    assert(statsAfter.offersCreated.eq(statsBefore.offersCreated.addn(2)));
+    assert(statsAfter.offersTaken.eq(statsBefore.offersTaken.addn(1)));
+    assert.equal(statsAfter.openOffers, statsBefore.openOffers);
+
+    // Only the admin can reset the counters, and the reset leaves the open-offer count alone.
+    await expectAnchorError(
+      program.methods
+        .resetMakerStats()
+        .accounts({ admin: bob.publicKey, config: accounts.config, makerStats })
+        .signers([bob])
+        .rpc(),
+      "Unauthorized"
+    );
+    const resetSignature = await program.methods
+      .resetMakerStats()
+      .accounts({ admin: payer.publicKey, config: accounts.config, makerStats })
+      .rpc();
+    await confirmTransaction(connection, resetSignature);
+    const statsReset = await program.account.makerStats.fetch(makerStats);
+    assert(statsReset.offersCreated.isZero());
+    assert(statsReset.offersTaken.isZero());
+    assert.equal(statsReset.openOffers, statsBefore.openOffers);
+    assert(statsReset.nextId.eq(statsBefore.nextId));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a rebated partial fill leaves the offer priced as it was.
+  it("Takes a rebated partial fill off Alice's wanted amount at the full price", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          5_000,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc()
+    );
+
+    const half = tokenAOfferedAmount.divn(2);
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .takeOffer(half, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc()
+    );
+
+    // The rebate Bob kept does not leave Alice wanting more for the rest of her tokens.
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.tokenBWantedAmount.eq(tokenBWantedAmount.sub(tokenBWantedAmount.mul(half).div(tokenAOfferedAmount))));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that 0-decimal mints on both sides fill in whole units, with no fill rounding to nothing.
+  it("Fills an offer of a 0-decimal mint for another 0-decimal mint in whole units", async () => {
+    const createWholeUnitMint = () =>
+      createMint(connection, payer, payer.publicKey, null, 0, undefined, undefined, TOKEN_PROGRAM);
+    const wholeMintA = await createWholeUnitMint();
+    const wholeMintB = await createWholeUnitMint();
+    const openAccount = async (mint: PublicKey, owner: PublicKey, amount: number) => {
+      const { address } = await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        mint,
+        owner,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      );
+      if (amount > 0) {
+        await mintTo(connection, payer, mint, address, payer, amount, [], undefined, TOKEN_PROGRAM);
+      }
+      return address;
+    };
+    const wholeAccounts = {
+      ...accounts,
+      tokenMintA: wholeMintA,
+      tokenMintB: wholeMintB,
+      makerTokenAccountA: await openAccount(wholeMintA, alice.publicKey, 10),
+      makerTokenAccountB: await openAccount(wholeMintB, alice.publicKey, 0),
+      takerTokenAccountA: await openAccount(wholeMintA, bob.publicKey, 0),
+      takerTokenAccountB: await openAccount(wholeMintB, bob.publicKey, 3),
+      treasuryTokenAccountB: getAssociatedTokenAddressSync(wholeMintB, accounts.treasury, true, TOKEN_PROGRAM),
+    };
+
+    // Alice offers 10 whole Token A for 3 whole Token B.
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId, alice.publicKey, wholeMintA);
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        new BN(10),
+        new BN(3),
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...wholeAccounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert.equal(offerAccount.tokenADecimals, 0);
+    assert.equal(offerAccount.tokenBDecimals, 0);
+
+    const take = (amount: number) =>
+      program.methods
+        .takeOffer(new BN(amount), NO_PRICE_LIMIT, "", false)
+        .accounts({ ...wholeAccounts, offer, vault })
+        .signers([bob])
+        .rpc();
+
+    // One Token A is worth less than one whole Token B, so it costs one, rounded up rather than
+    // given away; the protocol fee on it rounds to nothing.
+    await confirmTransaction(connection, await take(1));
+    assert((await getTokenBalance(wholeAccounts.takerTokenAccountA)).eqn(1));
+    assert((await getTokenBalance(wholeAccounts.takerTokenAccountB)).eqn(2));
+    assert((await getTokenBalance(wholeAccounts.makerTokenAccountB)).eqn(1));
+
+    // Four of the remaining nine are worth 8 / 9 of a Token B, rounded up to one.
+    await confirmTransaction(connection, await take(4));
+    assert((await getTokenBalance(wholeAccounts.takerTokenAccountA)).eqn(5));
+    assert((await getTokenBalance(wholeAccounts.takerTokenAccountB)).eqn(1));
+    const partiallyFilled = await program.account.offer.fetch(offer);
+    assert(partiallyFilled.tokenARemaining.eqn(5));
+    assert(partiallyFilled.tokenBWantedAmount.eqn(1));
+
+    // The remaining five cost the remaining one, so the offer closes with nothing left over.
+    await confirmTransaction(connection, await take(5));
+    assert((await getTokenBalance(wholeAccounts.takerTokenAccountA)).eqn(10));
+    assert((await getTokenBalance(wholeAccounts.takerTokenAccountB)).isZero());
+    assert((await getTokenBalance(wholeAccounts.makerTokenAccountB)).eqn(3));
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a take refuses a maker account other than the offer's maker.
+  it("Refuses to take Alice's offer with another maker's account", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+
+    // Another maker with open-offer stats of their own, so only the offer's maker check stands.
+    const otherMaker = await createFundedWallet();
+    const otherTokenAccountA = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintA,
+        otherMaker.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    await transfer(
+      connection,
+      payer,
+      accounts.makerTokenAccountA,
+      otherTokenAccountA,
+      alice,
+      tokenAOfferedAmount.toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const otherOfferId = getRandomBigNumber();
+    const otherOffer = deriveOfferAccounts(otherOfferId, otherMaker.publicKey);
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .makeOffer(
+          otherOfferId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({
+          ...accounts,
+          maker: otherMaker.publicKey,
+          rentPayer: otherMaker.publicKey,
+          makerTokenAccountA: otherTokenAccountA,
+          ...otherOffer,
+        })
+        .signers([otherMaker])
+        .rpc()
+    );
+
+    // The offer's `has_one` rejects the mismatched maker through either take instruction.
+    const mismatchedAccounts = {
+      ...accounts,
+      maker: otherMaker.publicKey,
+      makerStats: deriveMakerStats(otherMaker.publicKey),
+      makerTokenAccountB: null,
+      offer,
+      vault,
+    };
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts(mismatchedAccounts)
+        .signers([bob])
+        .rpc(),
+      "ConstraintHasOne"
+    );
+    await expectAnchorError(
+      program.methods
+        .takeOfferExistingAccounts(tokenAOfferedAmount, NO_PRICE_LIMIT)
+        .accounts(mismatchedAccounts)
+        .signers([bob])
+        .rpc(),
+      "ConstraintHasOne"
+    );
+
+    // Alice's offer is untouched.
+    assert((await program.account.offer.fetch(offer)).tokenARemaining.eq(tokenAOfferedAmount));
+    assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an expired offer can be swept by anyone, but only once the maker's grace period is over.
+  it("Lets Bob sweep Alice's expired offer only after the grace period", async () => {
+    const gracePeriod = 10;
+    const setGracePeriod = async (seconds: BN) =>
+      confirmTransaction(
+        connection,
+        await program.methods
+          .setExpiryGracePeriod(seconds)
+          .accounts({ admin: payer.publicKey, config: accounts.config })
+          .rpc()
+      );
+
+    await expectAnchorError(setGracePeriod(new BN(-1)), "InvalidGracePeriod");
+    await setGracePeriod(new BN(gracePeriod));
+    try {
+      const expiry = (await getValidatorTime()).addn(MIN_OFFER_DURATION + 5);
+      const { offer, vault } = await createOffer(getRandomBigNumber(), tokenAOfferedAmount, tokenBWantedAmount, expiry);
+      const sweep = () =>
+        program.methods
+          .sweepExpiredOffer()
+          .accounts({ ...accounts, sweeper: bob.publicKey, offer, vault })
+          .signers([bob])
+          .rpc();
+
+      // Neither before expiry nor within the grace period after it can Bob sweep the offer.
+      await expectAnchorError(sweep(), "OfferNotSweepable");
+      await waitForValidatorTime(expiry);
+      await expectAnchorError(sweep(), "OfferNotSweepable");
+      assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount));
+
+      // Once it is over, Bob's sweep refunds Alice her Token A and closes the offer and its vault.
+      await waitForValidatorTime(expiry.addn(gracePeriod));
+      const aliceBalanceBefore = await getTokenBalance(accounts.makerTokenAccountA);
+      await confirmTransaction(connection, await sweep());
+      assert((await getTokenBalance(accounts.makerTokenAccountA)).sub(aliceBalanceBefore).eq(tokenAOfferedAmount));
+      assert.isNull(await connection.getAccountInfo(offer));
+      assert.isNull(await connection.getAccountInfo(vault));
+    } finally {
+      await setGracePeriod(new BN(0));
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer can want native SOL, paid through the system program instead of a token.
+  it("Swaps Alice's Token A for SOL paid straight into her wallet", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const lamportsWanted = new BN(LAMPORTS_PER_SOL / 10);
+    const solAccounts = { ...accounts, offer, vault };
+
+    // Fees below the rent-exempt minimum can only be paid into a funded treasury.
+    await confirmTransaction(connection, await connection.requestAirdrop(accounts.treasury, LAMPORTS_PER_SOL));
+
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .makeOfferForSol(offerId, tokenAOfferedAmount, lamportsWanted, NO_EXPIRY)
+        .accounts(solAccounts)
+        .signers([alice])
+        .rpc()
+    );
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.tokenMintB.equals(PublicKey.default));
+    assert(offerAccount.tokenBWantedAmount.eq(lamportsWanted));
+    assert.equal(offerAccount.tokenBDecimals, 9);
+
+    const takeWithSol = (amount: BN, maxLamports: BN) =>
+      program.methods
+        .takeOfferWithSol(amount, maxLamports)
+        .accounts(solAccounts)
+        .signers([bob])
+        .rpc();
+    const half = tokenAOfferedAmount.divn(2);
+    const halfLamports = lamportsWanted.divn(2);
+    await expectAnchorError(takeWithSol(half, halfLamports.subn(1)), "SlippageExceeded");
+
+    // Bob takes half: Alice's wallet gets the price less the fee, and the treasury the fee.
+    const bobBalanceABefore = await getTokenBalance(accounts.takerTokenAccountA);
+    const aliceLamportsBefore = await connection.getBalance(alice.publicKey);
+    const treasuryLamportsBefore = await connection.getBalance(accounts.treasury);
+    await confirmTransaction(connection, await takeWithSol(half, halfLamports));
+    const fee = protocolFee(halfLamports);
+    assert.equal((await connection.getBalance(alice.publicKey)) - aliceLamportsBefore, halfLamports.sub(fee).toNumber());
+    assert.equal((await connection.getBalance(accounts.treasury)) - treasuryLamportsBefore, fee.toNumber());
+    assert((await getTokenBalance(accounts.takerTokenAccountA)).sub(bobBalanceABefore).eq(half));
+    assert((await program.account.offer.fetch(offer)).tokenBWantedAmount.eq(lamportsWanted.sub(halfLamports)));
+
+    // Taking the rest closes the offer and its vault.
+    await confirmTransaction(connection, await takeWithSol(tokenAOfferedAmount.sub(half), NO_PRICE_LIMIT));
+    assert((await getTokenBalance(accounts.takerTokenAccountA)).sub(bobBalanceABefore).eq(tokenAOfferedAmount));
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert.isNull(await connection.getAccountInfo(vault));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a SOL take refuses to leave the maker's wallet holding too little to be rent-exempt.
+  it("Refuses to pay SOL into a maker's wallet it would leave below rent exemption", async () => {
+    const { maker, makerTokenAccountA } = await createTokenAMaker(LAMPORTS_PER_SOL, tokenAOfferedAmount);
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId, maker.publicKey);
+    const solAccounts = {
+      ...accounts,
+      maker: maker.publicKey,
+      rentPayer: maker.publicKey,
+      makerTokenAccountA,
+      makerStats: deriveMakerStats(maker.publicKey),
+      offer,
+      vault,
+    };
+    const lamportsWanted = new BN(10_000);
+    await confirmTransaction(connection, await connection.requestAirdrop(accounts.treasury, LAMPORTS_PER_SOL));
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .makeOfferForSol(offerId, tokenAOfferedAmount, lamportsWanted, NO_EXPIRY)
+        .accounts(solAccounts)
+        .signers([maker])
+        .rpc()
+    );
+
+    // The maker empties their wallet, so the small price alone would leave it below rent exemption.
+    const emptyTransaction = new Transaction().add(
+      SystemProgram.transfer({
+        fromPubkey: maker.publicKey,
+        toPubkey: payer.publicKey,
+        lamports: await connection.getBalance(maker.publicKey),
+      })
+    );
+    await sendAndConfirmTransaction(connection, emptyTransaction, [payer, maker]);
+    assert.equal(await connection.getBalance(maker.publicKey), 0);
+
+    const takeWithSol = () =>
+      program.methods
+        .takeOfferWithSol(tokenAOfferedAmount, NO_PRICE_LIMIT)
+        .accounts(solAccounts)
+        .signers([bob])
+        .rpc();
+    await expectAnchorError(takeWithSol(), "RecipientNotRentExempt");
+    assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount));
+
+    // Once the wallet is rent-exempt again, the take goes through and pays it.
+    await confirmTransaction(connection, await connection.requestAirdrop(maker.publicKey, LAMPORTS_PER_SOL));
+    await confirmTransaction(connection, await takeWithSol());
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a SOL take applies the same frozen-account check as a regular take.
+  it("Refuses to pay Bob's SOL for Token A into his frozen token account", async () => {
+    const freezableMintA = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      payer.publicKey,
+      6,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const ownAccount = async (owner: PublicKey) =>
+      (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          payer,
+          freezableMintA,
+          owner,
+          false,
+          undefined,
+          undefined,
+          TOKEN_PROGRAM
+        )
+      ).address;
+    const makerTokenAccountA = await ownAccount(alice.publicKey);
+    const takerTokenAccountA = await ownAccount(bob.publicKey);
+    await mintTo(connection, payer, freezableMintA, makerTokenAccountA, payer, tokenAOfferedAmount.toNumber(), [], undefined, TOKEN_PROGRAM);
+
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId, accounts.maker, freezableMintA);
+    const solAccounts = { ...accounts, tokenMintA: freezableMintA, makerTokenAccountA, takerTokenAccountA, offer, vault };
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .makeOfferForSol(offerId, tokenAOfferedAmount, new BN(LAMPORTS_PER_SOL / 10), NO_EXPIRY)
+        .accounts(solAccounts)
+        .signers([alice])
+        .rpc()
+    );
+    await freezeAccount(connection, payer, takerTokenAccountA, freezableMintA, payer, [], undefined, TOKEN_PROGRAM);
+
+    const aliceLamportsBefore = await connection.getBalance(alice.publicKey);
+    await expectAnchorError(
+      program.methods
+        .takeOfferWithSol(tokenAOfferedAmount, NO_PRICE_LIMIT)
+        .accounts(solAccounts)
+        .signers([bob])
+        .rpc(),
+      "FrozenTokenAccount"
+    );
+    assert.equal(await connection.getBalance(alice.publicKey), aliceLamportsBefore);
+    assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that malformed baskets are refused before any basket vault is opened.
+  it("Rejects basket offers with too many mints, missing accounts or a repeated mint", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const makeBasketOffer = (basketAmounts: BN[], basketAccounts: PublicKey[]) =>
+      program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          basketAmounts,
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, offer, vault })
+        .remainingAccounts(
+          basketAccounts.map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }))
+        )
+        .signers([alice])
+        .rpc();
+    const one = new BN(1);
+
+    await expectAnchorError(makeBasketOffer([one, one, one], []), "BasketTooLarge");
+    await expectAnchorError(makeBasketOffer([one], []), "BasketAccountsMismatch");
+
+    // Token B cannot also be an extra basket mint.
+    await expectAnchorError(
+      makeBasketOffer(
+        [one],
+        [accounts.tokenMintB, Keypair.generate().publicKey, Keypair.generate().publicKey]
+      ),
+      "DuplicateBasketMint"
+    );
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a take fails cleanly when the fee and royalty together exceed the payment.
+  it("Refuses a take whose fee and royalty add up to more than the payment", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          Keypair.generate().publicKey,
+          10_000, // The whole payment as royalty, leaving nothing for the protocol fee.
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc()
+    );
+
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "MathOverflow"
+    );
+    assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that batch cancels and ladders are refused above their caps.
+  it("Refuses a cancel batch or a ladder above its cap", async () => {
+    const dummyAccounts = (count: number) =>
+      Array.from({ length: count }, () => ({
+        pubkey: Keypair.generate().publicKey,
+        isSigner: false,
+        isWritable: true,
+      }));
+
+    // Six offers of four accounts each, one more than `MAX_BATCH_CANCEL`.
+    await expectAnchorError(
+      program.methods
+        .cancelOffersBatch()
+        .accounts({ maker: alice.publicKey, tokenProgram: TOKEN_PROGRAM })
+        .remainingAccounts(dummyAccounts(6 * 4))
+        .signers([alice])
+        .rpc(),
+      "BatchTooLarge"
+    );
+
+    // Six rungs of two accounts each, one more than `MAX_LADDER_RUNGS`.
+    const rungs = Array.from({ length: 6 }, () => getRandomBigNumber());
+    await expectAnchorError(
+      program.methods
+        .makeOffersLadder(
+          rungs,
+          rungs.map(() => tokenAOfferedAmount),
+          rungs.map(() => tokenBWantedAmount),
+          NO_EXPIRY
+        )
+        .accounts({ ...accounts })
+        .remainingAccounts(dummyAccounts(6 * 2))
+        .signers([alice])
+        .rpc(),
+      "LadderTooLarge"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer cannot be handed over to the maker it already belongs to.
+  it("Refuses to transfer Alice's offer to Alice", async () => {
+    const { offer } = await createOffer(getRandomBigNumber());
+    await expectAnchorError(
+      program.methods
+        .transferOfferOwnership(alice.publicKey)
+        .accounts({ maker: alice.publicKey, offer })
+        .signers([alice])
+        .rpc(),
+      "OwnershipTransferToSelf"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the blacklist, allowlist and fee-exempt list stop growing at their caps.
+  it("Refuses to grow the blacklist, allowlist or fee-exempt list past their caps", async () => {
+    const MAX_BLACKLISTED_MINTS = 16;
+    const MAX_ALLOWLISTED_MINTS = 32;
+    const MAX_FEE_EXEMPT_TAKERS = 16;
+    const blacklist = PublicKey.findProgramAddressSync([Buffer.from("blacklist")], program.programId)[0];
+    const allowlist = PublicKey.findProgramAddressSync([Buffer.from("allowlist")], program.programId)[0];
+    const feeExempt = PublicKey.findProgramAddressSync([Buffer.from("fee_exempt")], program.programId)[0];
+    const adminAccounts = { admin: payer.publicKey, config: accounts.config, blacklist, allowlist, feeExempt };
+
+    // Fill each list with throwaway keys up to its cap, check one more is refused, then empty it again.
+    const fillToCap = async (
+      listed: number,
+      cap: number,
+      add: (mint: PublicKey) => Promise<string>,
+      remove: (mint: PublicKey) => Promise<string>,
+      errorCode: string
+    ) => {
+      const added: PublicKey[] = [];
+      try {
+        for (let i = listed; i < cap; i++) {
+          const mint = Keypair.generate().publicKey;
+          await confirmTransaction(connection, await add(mint));
+          added.push(mint);
+        }
+        await expectAnchorError(add(Keypair.generate().publicKey), errorCode);
+      } finally {
+        for (const mint of added) {
+          await confirmTransaction(connection, await remove(mint));
+        }
+      }
+    };
+
+    await fillToCap(
+      (await program.account.blacklist.fetch(blacklist)).mints.length,
+      MAX_BLACKLISTED_MINTS,
+      (mint) => program.methods.addBlacklistedMint(mint).accounts(adminAccounts).rpc(),
+      (mint) => program.methods.removeBlacklistedMint(mint).accounts(adminAccounts).rpc(),
+      "BlacklistFull"
+    );
+    await fillToCap(
+      (await program.account.allowlist.fetch(allowlist)).mints.length,
+      MAX_ALLOWLISTED_MINTS,
+      (mint) => program.methods.addAllowedMint(mint).accounts(adminAccounts).rpc(),
+      (mint) => program.methods.removeAllowedMint(mint).accounts(adminAccounts).rpc(),
+      "AllowlistFull"
+    );
+    await fillToCap(
+      (await program.account.feeExempt.fetch(feeExempt)).takers.length,
+      MAX_FEE_EXEMPT_TAKERS,
+      (taker) => program.methods.addFeeExemptTaker(taker).accounts(adminAccounts).rpc(),
+      (taker) => program.methods.removeFeeExemptTaker(taker).accounts(adminAccounts).rpc(),
+      "FeeExemptListFull"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a full maker index or pair index refuses further offers instead of dropping them.
+  it("Refuses a new offer once the maker's index or the pair's index is full", async () => {
+    const MAX_INDEX_OFFERS = 32;
+
+    // A fresh maker and a fresh pair, so both indexes start empty.
+    const indexMaker = await createFundedWallet();
+    const indexTokenAccountA = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintA,
+        indexMaker.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    await transfer(
+      connection,
+      payer,
+      accounts.makerTokenAccountA,
+      indexTokenAccountA,
+      alice,
+      MAX_INDEX_OFFERS + 2,
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const indexMintB = await createMint(connection, payer, payer.publicKey, null, 6, undefined, undefined, TOKEN_PROGRAM);
+    const makerIndex = PublicKey.findProgramAddressSync(
+      [Buffer.from("maker_index"), indexMaker.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    const pairIndex = PublicKey.findProgramAddressSync(
+      [Buffer.from("pair"), accounts.tokenMintA.toBuffer(), indexMintB.toBuffer()],
+      program.programId
+    )[0];
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .createMakerIndex()
+        .accounts({ maker: indexMaker.publicKey, makerIndex })
+        .signers([indexMaker])
+        .rpc()
+    );
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .createPairIndex()
+        .accounts({ payer: payer.publicKey, tokenMintA: accounts.tokenMintA, tokenMintB: indexMintB })
+        .rpc()
+    );
+
+    const makeIndexedOffer = (indexes: { makerIndex?: PublicKey; pairIndex?: PublicKey }) => {
+      const offerId = getRandomBigNumber();
+      return program.methods
+        .makeOffer(
+          offerId,
+          new BN(1),
+          new BN(1),
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({
+          ...accounts,
+          maker: indexMaker.publicKey,
+          rentPayer: indexMaker.publicKey,
+          tokenMintB: indexMintB,
+          makerTokenAccountA: indexTokenAccountA,
+          makerIndex: indexes.makerIndex ?? null,
+          pairIndex: indexes.pairIndex ?? null,
+          ...deriveOfferAccounts(offerId, indexMaker.publicKey),
+        })
+        .signers([indexMaker])
+        .rpc();
+    };
+    for (let i = 0; i < MAX_INDEX_OFFERS; i++) {
+      await confirmTransaction(connection, await makeIndexedOffer({ makerIndex, pairIndex }));
+    }
+
+    await expectAnchorError(makeIndexedOffer({ makerIndex, pairIndex }), "MakerIndexFull");
+    await expectAnchorError(makeIndexedOffer({ pairIndex }), "PairIndexFull");
+    assert.equal((await program.account.makerIndex.fetch(makerIndex)).offers.length, MAX_INDEX_OFFERS);
+    assert.equal((await program.account.pairIndex.fetch(pairIndex)).offers.length, MAX_INDEX_OFFERS);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test the checks specific to oracle-priced offers and their feeds.
+  it("Refuses a zero feed price, an oracle take without its feed, and repricing an oracle offer", async () => {
+    const zeroPriceFeed = Keypair.generate();
+    await expectAnchorError(
+      program.methods
+        .createPriceFeed(new BN(0), 0)
+        .accounts({ authority: payer.publicKey, priceFeed: zeroPriceFeed.publicKey })
+        .signers([payer, zeroPriceFeed])
+        .rpc(),
+      "InvalidOracle"
+    );
+
+    // An offer naming a feed Bob does not pass.
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          Keypair.generate().publicKey,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc()
+    );
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "OracleAccountMismatch"
+    );
+
+    // Its price comes from the feed, so Alice cannot set a wanted amount for it.
+    await expectAnchorError(
+      program.methods
+        .updateOffer(tokenBWantedAmount.muln(2))
+        .accounts({ maker: alice.publicKey, offer })
+        .signers([alice])
+        .rpc(),
+      "OracleOfferCannotBeRepriced"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a commit-only offer refuses direct takes and can still be taken through a reveal.
+  it("Refuses a direct take of a commit-only offer with CommitRevealRequired", async () => {
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          true,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc()
+    );
+    assert.isTrue((await program.account.offer.fetch(offer)).commitOnly);
+
+    // Bob's direct takes are refused, and he pays nothing.
+    const bobBalanceBBefore = await getTokenBalance(accounts.takerTokenAccountB);
+    await expectAnchorError(
+      program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "CommitRevealRequired"
+    );
+    await expectAnchorError(
+      program.methods
+        .takeOfferExistingAccounts(tokenAOfferedAmount, NO_PRICE_LIMIT)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "CommitRevealRequired"
+    );
+    assert((await getTokenBalance(accounts.takerTokenAccountB)).eq(bobBalanceBBefore));
+
+    // Committing and revealing takes it.
+    const commitment = PublicKey.findProgramAddressSync(
+      [Buffer.from("commit"), offer.toBuffer(), bob.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    const nonce = randomBytes(32);
+    const hash = createHash("sha256").update(bob.publicKey.toBuffer()).update(nonce).digest();
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .commitTake([...hash])
+        .accounts({ taker: bob.publicKey, offer, commitment })
+        .signers([bob])
+        .rpc()
+    );
+    const committedSlot = (await program.account.takeCommitment.fetch(commitment)).slot.toNumber();
+    while ((await connection.getSlot()) < committedSlot + 2) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .revealTake([...nonce], tokenAOfferedAmount, NO_PRICE_LIMIT, false)
+        .accounts({ take: { ...accounts, offer, vault }, commitment })
+        .signers([bob])
+        .rpc()
+    );
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a commitment cannot be revealed in the slot it was made.
+  it("Refuses to reveal Bob's commitment in the transaction that made it", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const commitment = PublicKey.findProgramAddressSync(
+      [Buffer.from("commit"), offer.toBuffer(), bob.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    const nonce = randomBytes(32);
+    const hash = createHash("sha256").update(bob.publicKey.toBuffer()).update(nonce).digest();
+    const commitInstruction = await program.methods
+      .commitTake([...hash])
+      .accounts({ taker: bob.publicKey, offer, commitment })
+      .instruction();
+
+    await expectAnchorError(
+      program.methods
+        .revealTake([...nonce], tokenAOfferedAmount, NO_PRICE_LIMIT, false)
+        .accounts({ take: { ...accounts, offer, vault }, commitment })
+        .preInstructions([commitInstruction])
+        .signers([bob])
+        .rpc(),
+      "RevealTooEarly"
+    );
+    assert.isNull(await connection.getAccountInfo(commitment));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a commitment left too long cannot be revealed, and that Bob can close it instead.
+  it("Refuses to reveal Bob's commitment once it is too old, and lets him close it", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const commitment = PublicKey.findProgramAddressSync(
+      [Buffer.from("commit"), offer.toBuffer(), bob.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    const nonce = randomBytes(32);
+    const hash = createHash("sha256").update(bob.publicKey.toBuffer()).update(nonce).digest();
+    const commitSignature = await program.methods
+      .commitTake([...hash])
+      .accounts({ taker: bob.publicKey, offer, commitment })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, commitSignature);
+
+    // Wait past the reveal window (MAX_REVEAL_DELAY_SLOTS).
+    const committedSlot = (await program.account.takeCommitment.fetch(commitment)).slot.toNumber();
+    while ((await connection.getSlot()) <= committedSlot + 150) {
+      await new Promise((resolve) => setTimeout(resolve, 400));
+    }
+
+    await expectAnchorError(
+      program.methods
+        .revealTake([...nonce], tokenAOfferedAmount, NO_PRICE_LIMIT, false)
+        .accounts({ take: { ...accounts, offer, vault }, commitment })
+        .signers([bob])
+        .rpc(),
+      "RevealTooLate"
+    );
+    assert.isNotNull(await connection.getAccountInfo(offer));
+
+    // Only Bob can close his commitment, and its rent goes back to him.
+    await expectAnchorError(
+      program.methods
+        .closeCommitment()
+        .accounts({ taker: alice.publicKey, commitment })
+        .signers([alice])
+        .rpc(),
+      "ConstraintSeeds"
+    );
+    const rent = (await connection.getAccountInfo(commitment)).lamports;
+    const bobLamportsBefore = await connection.getBalance(bob.publicKey);
+    const closeSignature = await program.methods
+      .closeCommitment()
+      .accounts({ taker: bob.publicKey, commitment })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, closeSignature);
+    assert.isNull(await connection.getAccountInfo(commitment));
+    assert.equal(await connection.getBalance(bob.publicKey), bobLamportsBefore + rent);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the admin cannot withdraw more fees than the treasury holds.
+  it("Refuses to withdraw more than the treasury's fee balance", async () => {
+    const destination = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintB,
+        payer.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    const treasuryBalance = await getTokenBalance(accounts.treasuryTokenAccountB);
+    await expectAnchorError(
+      program.methods
+        .withdrawTreasury(treasuryBalance.addn(1))
+        .accounts({
+          admin: payer.publicKey,
+          config: accounts.config,
+          mint: accounts.tokenMintB,
+          treasuryTokenAccount: accounts.treasuryTokenAccountB,
+          destination,
+          tokenProgram: TOKEN_PROGRAM,
+        })
+        .rpc(),
+      "InsufficientTreasuryBalance"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer wanting a token cannot be taken with SOL.
+  it("Refuses to take Alice's token offer with SOL", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    await expectAnchorError(
+      program.methods
+        .takeOfferWithSol(tokenAOfferedAmount, NO_PRICE_LIMIT)
+        .accounts({ ...accounts, offer, vault })
+        .signers([bob])
+        .rpc(),
+      "OfferNotForSol"
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a taker on the admin's fee-exempt list pays no fees, and pays them again once removed.
+  it("Lets the admin exempt Bob from the protocol and flat fees", async () => {
+    const [feeExempt] = PublicKey.findProgramAddressSync([Buffer.from("fee_exempt")], program.programId);
+    const flatFeeLamports = new BN(5_000);
+
+    // Only the admin may change the fee-exempt list.
+    await expectAnchorError(
+      program.methods
+        .addFeeExemptTaker(bob.publicKey)
+        .accounts({ admin: bob.publicKey, config: accounts.config, feeExempt })
+        .signers([bob])
+        .rpc(),
+      "Unauthorized"
+    );
+
+    // Fund the treasury to rent-exemption so it could receive a flat fee smaller than that minimum.
+    const airdropSignature = await connection.requestAirdrop(accounts.treasury, LAMPORTS_PER_SOL);
+    await confirmTransaction(connection, airdropSignature);
+    const setFeeSignature = await program.methods
+      .updateConfig(FEE_BPS, false, 0, new BN(0), flatFeeLamports, 0, false)
+      .accounts({ admin: payer.publicKey, config: accounts.config })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, setFeeSignature);
+    const takeFullOffer = async () => {
+      const { offer, vault } = await createOffer(getRandomBigNumber());
+      const treasuryLamportsBefore = await connection.getBalance(accounts.treasury);
+      const treasuryBalanceBefore = await getTokenBalance(accounts.treasuryTokenAccountB);
+      const aliceBalanceBefore = await getTokenBalance(accounts.makerTokenAccountB);
+      const signature = await program.methods
+        .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+        .accounts({ ...accounts, offer, vault, feeExempt })
+        .signers([bob])
+        .rpc();
+      await confirmTransaction(connection, signature);
+      return {
+        flatFee: (await connection.getBalance(accounts.treasury)) - treasuryLamportsBefore,
+        fee: (await getTokenBalance(accounts.treasuryTokenAccountB)).sub(treasuryBalanceBefore),
+        alicePaid: (await getTokenBalance(accounts.makerTokenAccountB)).sub(aliceBalanceBefore),
+      };
+    };
+
+    try {
+      const addSignature = await program.methods
+        .addFeeExemptTaker(bob.publicKey)
+        .accounts({ admin: payer.publicKey, config: accounts.config, feeExempt })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, addSignature);
+      assert((await program.account.feeExempt.fetch(feeExempt)).takers.some((taker) => taker.equals(bob.publicKey)));
+
+      // While exempt, Alice receives the full wanted amount and the treasury nothing.
+      const exempt = await takeFullOffer();
+      assert(exempt.fee.isZero());
+      assert.equal(exempt.flatFee, 0);
+      assert(exempt.alicePaid.eq(tokenBWantedAmount));
+
+      // A quote for Bob prices the take without either fee.
+      const quoted = await createOffer(getRandomBigNumber());
+      const quote = await program.methods
+        .quoteTake(tokenAOfferedAmount)
+        .accounts({ config: accounts.config, offer: quoted.offer, taker: bob.publicKey, feeExempt })
+        .view();
+      assert(quote.protocolFee.isZero());
+      assert(quote.flatFeeLamports.isZero());
+      assert(quote.tokenBAmount.eq(tokenBWantedAmount));
+
+      // A batch take charges him no fee on any of its offers either.
+      const batched = [quoted, await createOffer(getRandomBigNumber())];
+      const treasuryLamportsBefore = await connection.getBalance(accounts.treasury);
+      const treasuryBalanceBefore = await getTokenBalance(accounts.treasuryTokenAccountB);
+      const aliceBalanceBefore = await getTokenBalance(accounts.makerTokenAccountB);
+      const batchSignature = await program.methods
+        .takeOffersBatch(NO_PRICE_LIMIT)
+        .accounts({ ...accounts, feeExempt })
+        .remainingAccounts(
+          batched.flatMap(({ offer, vault }) => [
+            { pubkey: offer, isSigner: false, isWritable: true },
+            { pubkey: vault, isSigner: false, isWritable: true },
+            { pubkey: alice.publicKey, isSigner: false, isWritable: true },
+            { pubkey: accounts.makerTokenAccountB, isSigner: false, isWritable: true },
+            { pubkey: deriveMakerStats(alice.publicKey), isSigner: false, isWritable: true },
+          ])
+        )
+        .signers([bob])
+        .rpc();
+      await confirmTransaction(connection, batchSignature);
+      assert.equal(await connection.getBalance(accounts.treasury), treasuryLamportsBefore);
+      assert((await getTokenBalance(accounts.treasuryTokenAccountB)).eq(treasuryBalanceBefore));
+      assert(
+        (await getTokenBalance(accounts.makerTokenAccountB)).sub(aliceBalanceBefore).eq(tokenBWantedAmount.muln(2))
+      );
+
+      const removeSignature = await program.methods
+        .removeFeeExemptTaker(bob.publicKey)
+        .accounts({ admin: payer.publicKey, config: accounts.config, feeExempt })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, removeSignature);
+
+      // Once removed, Bob pays both fees again even when he passes the list.
+      const charged = await takeFullOffer();
+      assert(charged.fee.eq(protocolFee(tokenBWantedAmount)));
+      assert.equal(charged.flatFee, flatFeeLamports.toNumber());
+      assert(charged.alicePaid.eq(tokenBWantedAmount.sub(protocolFee(tokenBWantedAmount))));
+    } finally {
+      // Disable the flat fee so the remaining tests are unaffected.
+      const resetSignature = await program.methods
+        .updateConfig(FEE_BPS, false, 0, new BN(0), new BN(0), 0, false)
+        .accounts({ admin: payer.publicKey, config: accounts.config })
+        .signers([payer])
+        .rpc();
+      await confirmTransaction(connection, resetSignature);
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that compute checkpoints are only logged by builds with the `compute-logs` feature. Run
+  // `anchor run compute-logs` to build with it and check the checkpoints appear.
+  it("Logs compute checkpoints in make_offer and take_offer only with the compute-logs feature", async () => {
+    const computeLogs = process.env.COMPUTE_LOGS === "1";
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...accounts, offer, vault })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+
+    // The offer's address appears in both the make and the take, newest first.
+    const [take, make] = await connection.getSignaturesForAddress(offer, undefined, "confirmed");
+    assert.equal(take.signature, takeSignature);
+    const checkpoints = {
+      [make.signature]: ["make_offer checked", "make_offer deposited"],
+      [take.signature]: ["take_offer priced", "take_offer paid"],
+    };
+    for (const [signature, names] of Object.entries(checkpoints)) {
+      const transaction = await connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      const logs = transaction.meta.logMessages;
+      for (const name of names) {
+        const index = logs.indexOf(`Program log: compute checkpoint: ${name}`);
+        if (!computeLogs) {
+          assert.equal(index, -1, `Unexpected checkpoint "${name}" without the compute-logs feature`);
+          continue;
+        }
+        assert.isAbove(index, -1, `Missing checkpoint "${name}"`);
+        assert.match(logs[index + 1], /^Program consumption: \d+ units remaining$/);
+      }
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a maker can top up and re-price an offer in one call, and that both changes land together.
+  it("Lets Alice top up and re-price an open offer at once with rebalance_offer", async () => {
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const topUpAmount = tokenAOfferedAmount.divn(2);
+    const newWantedAmount = tokenBWantedAmount.muln(2);
+
+    // A rejected price leaves the vault and the offer as they were.
+    await expectAnchorError(
+      program.methods
+        .rebalanceOffer(topUpAmount, new BN(0))
+        .accounts({ ...accounts, offer, vault })
+        .signers([alice])
+        .rpc(),
+      "ZeroAmount"
+    );
+    assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount));
+
+    const signature = await program.methods
+      .rebalanceOffer(topUpAmount, newWantedAmount)
+      .accounts({ ...accounts, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // Validate the vault, both offered amounts and the wanted amount all changed.
+    const rebalancedAmount = tokenAOfferedAmount.add(topUpAmount);
+    assert((await getTokenBalance(vault)).eq(rebalancedAmount));
+    const offerAccount = await program.account.offer.fetch(offer);
+    assert(offerAccount.tokenAOfferedAmount.eq(rebalancedAmount));
+    assert(offerAccount.tokenARemaining.eq(rebalancedAmount));
+    assert(offerAccount.tokenBWantedAmount.eq(newWantedAmount));
+
+    // Validate indexers see the new price.
+    const offerUpdated = (await getEvents(signature)).find((event) => event.name === "offerUpdated");
+    assert(offerUpdated.data.tokenBWantedAmount.eq(newWantedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer whose maker is a program-owned PDA can still be taken.
+  it("Pays a maker that is a program-owned PDA rather than a system account", async () => {
+    // The config PDA stands in for a DAO's treasury PDA: it is owned by a program, not the system program.
+    const daoMaker = accounts.config;
+    assert((await connection.getAccountInfo(daoMaker)).owner.equals(program.programId));
+    const daoTokenAccountB = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintB,
+        daoMaker,
+        true,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+
+    // Alice hands her offer to the PDA, along with the rent she paid for it.
+    const { offer, vault } = await createOffer(getRandomBigNumber());
+    const transferSignature = await program.methods
+      .transferOfferOwnership(daoMaker)
+      .accounts({ maker: alice.publicKey, offer })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, transferSignature);
+
+    // Bob takes it, paying the PDA's Token B account.
+    const daoBalanceBefore = await getTokenBalance(daoTokenAccountB);
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({
+        ...accounts,
+        maker: daoMaker,
+        rentPayer: daoMaker,
+        makerStats: deriveMakerStats(daoMaker),
+        makerTokenAccountB: daoTokenAccountB,
+        offer,
+        vault,
+      })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+
+    assert(
+      (await getTokenBalance(daoTokenAccountB)).eq(
+        daoBalanceBefore.add(tokenBWantedAmount).sub(protocolFee(tokenBWantedAmount))
+      )
+    );
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a program-owned maker that paid its own rent gets it back from a full fill and from
+  // an admin close, rather than failing a system account check.
+  it("Returns the rent of a program-owned maker that paid it, on a full fill and an admin close", async () => {
+    // Carol makes two offers from her wallet, paying their rent herself.
+    const carol = await createFundedWallet();
+    const ownAccount = async (mint: PublicKey) =>
+      (
+        await getOrCreateAssociatedTokenAccount(
+          connection,
+          payer,
+          mint,
+          carol.publicKey,
+          false,
+          undefined,
+          undefined,
+          TOKEN_PROGRAM
+        )
+      ).address;
+    const carolTokenAccountA = await ownAccount(accounts.tokenMintA);
+    const carolTokenAccountB = await ownAccount(accounts.tokenMintB);
+    await transfer(
+      connection,
+      payer,
+      accounts.makerTokenAccountA,
+      carolTokenAccountA,
+      alice,
+      BigInt(tokenAOfferedAmount.muln(2).toString()),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+    const carolAccounts = {
+      ...accounts,
+      maker: carol.publicKey,
+      rentPayer: carol.publicKey,
+      makerTokenAccountA: carolTokenAccountA,
+      makerTokenAccountB: carolTokenAccountB,
+      makerStats: deriveMakerStats(carol.publicKey),
+    };
+    const ids = [getRandomBigNumber(), getRandomBigNumber()];
+    const offers = ids.map((id) => deriveOfferAccounts(id, carol.publicKey));
+    for (const [i, { offer, vault }] of offers.entries()) {
+      const makeSignature = await program.methods
+        .makeOffer(
+          ids[i],
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...carolAccounts, offer, vault })
+        .signers([carol])
+        .rpc();
+      await confirmTransaction(connection, makeSignature);
+    }
+
+    // Her wallet is then handed to a program, as a DAO would move funds under its own PDA.
+    const daoProgram = Keypair.generate().publicKey;
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(SystemProgram.assign({ accountPubkey: carol.publicKey, programId: daoProgram })),
+      [payer, carol]
+    );
+    assert((await connection.getAccountInfo(carol.publicKey)).owner.equals(daoProgram));
+    const rentOf = async ({ offer, vault }: { offer: PublicKey; vault: PublicKey }) =>
+      (await connection.getBalance(offer)) + (await connection.getBalance(vault));
+
+    // Bob fills the first offer completely, and its rent goes back to Carol.
+    const takenRent = await rentOf(offers[0]);
+    let carolLamportsBefore = await connection.getBalance(carol.publicKey);
+    const takeSignature = await program.methods
+      .takeOffer(tokenAOfferedAmount, NO_PRICE_LIMIT, "", false)
+      .accounts({ ...carolAccounts, ...offers[0] })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, takeSignature);
+    assert.isNull(await connection.getAccountInfo(offers[0].offer));
+    assert.equal(await connection.getBalance(carol.publicKey), carolLamportsBefore + takenRent);
+    assert(
+      (await getTokenBalance(carolTokenAccountB)).eq(tokenBWantedAmount.sub(protocolFee(tokenBWantedAmount)))
+    );
+
+    // The admin force-closes the second, refunding Carol her Token A and her rent.
+    const closedRent = await rentOf(offers[1]);
+    carolLamportsBefore = await connection.getBalance(carol.publicKey);
+    const carolBalanceABefore = await getTokenBalance(carolTokenAccountA);
+    const closeSignature = await program.methods
+      .adminCloseOffer()
+      .accounts({ ...carolAccounts, admin: payer.publicKey, ...offers[1] })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, closeSignature);
+    assert.isNull(await connection.getAccountInfo(offers[1].offer));
+    assert.equal(await connection.getBalance(carol.publicKey), carolLamportsBefore + closedRent);
+    assert((await getTokenBalance(carolTokenAccountA)).sub(carolBalanceABefore).eq(tokenAOfferedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Property-style test: with 9-decimal mints and adversarially small, odd fills, no partial fill
+  // ever hands out more Token A than the Token B it costs buys at the offer's price.
+  it("Never prices a partial fill of a 9-decimal offer below what its Token A is worth", async () => {
+    const createLargeDecimalMint = () =>
+      createMint(connection, payer, payer.publicKey, null, 9, undefined, undefined, TOKEN_PROGRAM);
+    const largeMintA = await createLargeDecimalMint();
+    const largeMintB = await createLargeDecimalMint();
+    // A billion whole Token A, in base units.
+    const offeredAmount = new BN("1000000000000000000");
+    const aliceLargeAccountA = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        largeMintA,
+        alice.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    await mintTo(
+      connection,
+      payer,
+      largeMintA,
+      aliceLargeAccountA,
+      payer,
+      BigInt(offeredAmount.muln(3).toString()),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+
+    const wantedAmounts = [new BN(1), new BN(7), new BN(999_999_999)];
+    const fills = [new BN(1), new BN(2), new BN(999_999_999), offeredAmount.divn(3), offeredAmount.subn(1)];
+    for (const wantedAmount of wantedAmounts) {
+      const offerId = getRandomBigNumber();
+      const { offer, vault } = deriveOfferAccounts(offerId, alice.publicKey, largeMintA);
+      const signature = await program.methods
+        .makeOffer(
+          offerId,
+          offeredAmount,
+          wantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({
+          ...accounts,
+          tokenMintA: largeMintA,
+          tokenMintB: largeMintB,
+          makerTokenAccountA: aliceLargeAccountA,
+          offer,
+          vault,
+        })
+        .signers([alice])
+        .rpc();
+      await confirmTransaction(connection, signature);
+
+      for (const fill of fills) {
+        const quote = await program.methods
+          .quoteTake(fill)
+          .accounts({ config: accounts.config, offer })
+          .view();
+        // Paying `tokenBAmount` for `fill` must cost at least the offer's price for it, and never nothing.
+        assert(quote.tokenBAmount.gtn(0), `wanted ${wantedAmount}, fill ${fill}`);
+        assert(
+          quote.tokenBAmount.mul(offeredAmount).gte(fill.mul(wantedAmount)),
+          `wanted ${wantedAmount}, fill ${fill}`
+        );
+      }
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that the current price follows a Dutch auction over time, and is the stored amount otherwise.
+  it("Reads the current price of a Dutch-auction offer before and after its auction", async () => {
+    const now = await getValidatorTime();
+    const startAmount = tokenBWantedAmount.muln(2);
+    const floorAmount = tokenBWantedAmount.divn(2);
+    const endTs = now.addn(30);
+    const auctionOffer = await createOffer(
+      getRandomBigNumber(),
+      tokenAOfferedAmount,
+      tokenBWantedAmount,
+      NO_EXPIRY,
+      null,
+      NO_MIN_FILL,
+      "",
+      { startAmount, floorAmount, startTs: now.addn(20), endTs }
+    );
+    const currentPrice = (offer: PublicKey) =>
+      program.methods.currentPrice().accounts({ config: accounts.config, offer }).view();
+
+    // Before the auction starts, the whole offer costs the start amount.
+    assert((await currentPrice(auctionOffer.offer)).eq(startAmount));
+
+    // Once it has ended, it costs the floor.
+    await waitForValidatorTime(endTs);
+    assert((await currentPrice(auctionOffer.offer)).eq(floorAmount));
+
+    // A fixed-price offer costs its stored wanted amount.
+    const fixedOffer = await createOffer(getRandomBigNumber());
+    assert((await currentPrice(fixedOffer.offer)).eq(tokenBWantedAmount));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a sponsor who paid an offer's rent can cancel it once it expires, refunding Alice.
+  it("Lets the sponsor of Alice's expired offer cancel it, refunding her Token A and their rent", async () => {
+    const setGracePeriod = async (seconds: BN) =>
+      confirmTransaction(
+        connection,
+        await program.methods
+          .setExpiryGracePeriod(seconds)
+          .accounts({ admin: payer.publicKey, config: accounts.config })
+          .rpc()
+      );
+    const sponsor = await createFundedWallet();
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const expiry = (await getValidatorTime()).addn(MIN_OFFER_DURATION + 5);
+
+    // Alice authorizes the deposit while the sponsor pays for the offer and its vault.
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        expiry,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, rentPayer: sponsor.publicKey, offer, vault })
+      .signers([alice, sponsor])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+    const rent = (await connection.getBalance(offer)) + (await connection.getBalance(vault));
+
+    const cancelBy = (canceller: Keypair, closeTokenAccountA = false) =>
+      program.methods
+        .cancelOffer(closeTokenAccountA)
+        .accounts({ ...accounts, canceller: canceller.publicKey, rentPayer: sponsor.publicKey, offer, vault })
+        .signers([canceller])
+        .rpc();
+
+    // The sponsor may not cancel the offer before it expires.
+    await expectAnchorError(cancelBy(sponsor), "OfferNotExpired");
+    await waitForValidatorTime(expiry);
+
+    // Once it has, Bob still may not, and the sponsor cannot close Alice's Token A account.
+    await expectAnchorError(cancelBy(bob), "Unauthorized");
+    await expectAnchorError(cancelBy(sponsor, true), "Unauthorized");
+
+    // The grace period only holds up sweeps, which the sponsor waits out like anyone else.
+    await setGracePeriod(new BN(3600));
+    try {
+      await expectAnchorError(
+        program.methods
+          .sweepExpiredOffer()
+          .accounts({ ...accounts, sweeper: sponsor.publicKey, rentPayer: sponsor.publicKey, offer, vault })
+          .signers([sponsor])
+          .rpc(),
+        "OfferNotSweepable"
+      );
+
+      // The sponsor cancels: Alice gets her Token A back and the sponsor, who pays no fee, the rent.
+      const aliceBalanceBefore = await getTokenBalance(accounts.makerTokenAccountA);
+      const sponsorLamportsBefore = await connection.getBalance(sponsor.publicKey);
+      await confirmTransaction(connection, await cancelBy(sponsor));
+      assert((await getTokenBalance(accounts.makerTokenAccountA)).sub(aliceBalanceBefore).eq(tokenAOfferedAmount));
+      assert.equal(await connection.getBalance(sponsor.publicKey), sponsorLamportsBefore + rent);
+      assert.isNull(await connection.getAccountInfo(offer));
+      assert.isNull(await connection.getAccountInfo(vault));
+    } finally {
+      await setGracePeriod(new BN(0));
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a maker short of the SOL they offer gets a clear error naming the amount needed.
+  it("Refuses a native SOL offer from a maker who cannot fund it with InsufficientRent", async () => {
+    // Dave holds a tenth of a SOL, enough for his accounts' rent but not the SOL he offers.
+    const dave = await createFundedWallet(LAMPORTS_PER_SOL / 10);
+    const solOfferedAmount = new BN(LAMPORTS_PER_SOL);
+    const offerId = getRandomBigNumber();
+    const offer = PublicKey.findProgramAddressSync(
+      [Buffer.from("offer"), dave.publicKey.toBuffer(), offerId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const legacyMintB = await createMint(
+      connection,
+      payer,
+      payer.publicKey,
+      null,
+      6,
+      undefined,
+      undefined,
+      TOKEN_PROGRAM_ID
+    );
+    const ata = (mint: PublicKey, owner: PublicKey) =>
+      getAssociatedTokenAddressSync(mint, owner, true, TOKEN_PROGRAM_ID);
+
+    const make = program.methods
+      .makeOffer(
+        offerId,
+        solOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({
+        ...accounts,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        maker: dave.publicKey,
+        rentPayer: dave.publicKey,
+        tokenMintA: NATIVE_MINT,
+        tokenMintB: legacyMintB,
+        makerTokenAccountA: ata(NATIVE_MINT, dave.publicKey),
+        makerStats: deriveMakerStats(dave.publicKey),
+        offer,
+        vault: ata(NATIVE_MINT, offer),
+      })
+      .preInstructions([
+        await checkOfferFunding(dave.publicKey, dave.publicKey, [offerId], solOfferedAmount, NATIVE_MINT, TOKEN_PROGRAM_ID),
+      ])
+      .signers([dave]);
+
+    // Dave pays the SOL he offers plus the rent of his wrapped SOL account, his stats, the offer
+    // and its vault, all priced from accounts of the same kind.
+    const reference = await createOffer(getRandomBigNumber());
+    const needed = solOfferedAmount
+      .addn((await connection.getAccountInfo(reference.offer)).lamports)
+      .addn((await connection.getAccountInfo(deriveMakerStats(alice.publicKey))).lamports)
+      .addn(2 * (await connection.getMinimumBalanceForRentExemption(ACCOUNT_SIZE)));
+
+    // The error's log names how much the offer needs from Dave.
+    try {
+      await make.rpc();
+      assert.fail("Expected the underfunded offer to fail");
+    } catch (error) {
+      const anchorError = assertAnchorError(error, "InsufficientRent");
+      assert(
+        anchorError.logs.some((log) => log.includes(`${dave.publicKey.toBase58()} holds`) && log.includes(`needs ${needed} from it`)),
+        anchorError.logs.join("\n")
+      );
+    }
+    assert.isNull(await connection.getAccountInfo(offer));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a rent payer who can cover the vault but not the offer account is refused before either is created.
+  it("Refuses a sponsored offer whose sponsor is short only of the offer's rent with InsufficientRent", async () => {
+    // An offer made the usual way shows what the offer account and its vault cost in rent.
+    const reference = await createOffer(getRandomBigNumber());
+    const offerRent = (await connection.getAccountInfo(reference.offer)).lamports;
+    const vaultRent = (await connection.getAccountInfo(reference.vault)).lamports;
+
+    // The sponsor holds the vault's rent and nothing towards the offer's.
+    const sponsor = await createFundedWallet(vaultRent);
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    try {
+      await program.methods
+        .makeOffer(
+          offerId,
+          tokenAOfferedAmount,
+          tokenBWantedAmount,
+          NO_EXPIRY,
+          null,
+          [],
+          NO_MIN_FILL,
+          null,
+          "",
+          null,
+          PublicKey.default,
+          0,
+          PublicKey.default,
+          0,
+          0,
+          false,
+          NO_REBATE_RESERVE
+        )
+        .accounts({ ...accounts, rentPayer: sponsor.publicKey, offer, vault })
+        .preInstructions([await checkOfferFunding(alice.publicKey, sponsor.publicKey, [offerId], tokenAOfferedAmount)])
+        .signers([alice, sponsor])
+        .rpc();
+      assert.fail("Expected the underfunded offer to fail");
+    } catch (error) {
+      const anchorError = assertAnchorError(error, "InsufficientRent");
+      assert(
+        anchorError.logs.some((log) =>
+          log.includes(`${sponsor.publicKey.toBase58()} holds ${vaultRent} lamports but the offer needs ${offerRent + vaultRent} from it`)
+        ),
+        anchorError.logs.join("\n")
+      );
+    }
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert.isNull(await connection.getAccountInfo(vault));
+    assert.equal(await connection.getBalance(sponsor.publicKey), vaultRent);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Give a fresh wallet holding `lamports` its own Token A account with `amount` of Token A in it.
+  const createTokenAMaker = async (lamports: number, amount: BN) => {
+    const maker = await createFundedWallet(lamports);
+    const makerTokenAccountA = (
+      await getOrCreateAssociatedTokenAccount(
+        connection,
+        payer,
+        accounts.tokenMintA,
+        maker.publicKey,
+        false,
+        undefined,
+        undefined,
+        TOKEN_PROGRAM
+      )
+    ).address;
+    await transfer(
+      connection,
+      payer,
+      accounts.makerTokenAccountA,
+      makerTokenAccountA,
+      alice,
+      amount.toNumber(),
+      [],
+      undefined,
+      TOKEN_PROGRAM
+    );
+    return { maker, makerTokenAccountA };
+  };
+
+  // Test that a SOL-wanting offer, whose accounts are all created by Anchor, fails its leading funding check clearly.
+  it("Refuses a SOL-wanting offer from a maker short of the offer's rent with InsufficientRent", async () => {
+    // An offer made the usual way shows what each account of an offer costs in rent.
+    const reference = await createOffer(getRandomBigNumber());
+    const offerRent = (await connection.getAccountInfo(reference.offer)).lamports;
+    const vaultRent = (await connection.getAccountInfo(reference.vault)).lamports;
+    const statsRent = (await connection.getAccountInfo(deriveMakerStats(alice.publicKey))).lamports;
+
+    // The new maker can pay for their stats and the vault, but not the offer as well.
+    const { maker, makerTokenAccountA } = await createTokenAMaker(statsRent + vaultRent, tokenAOfferedAmount);
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId, maker.publicKey);
+    try {
+      await program.methods
+        .makeOfferForSol(offerId, tokenAOfferedAmount, new BN(LAMPORTS_PER_SOL / 10), NO_EXPIRY)
+        .accounts({
+          ...accounts,
+          maker: maker.publicKey,
+          makerTokenAccountA,
+          makerStats: deriveMakerStats(maker.publicKey),
+          offer,
+          vault,
+        })
+        .preInstructions([
+          await checkOfferFunding(maker.publicKey, maker.publicKey, [offerId], tokenAOfferedAmount),
+        ])
+        .signers([maker])
+        .rpc();
+      assert.fail("Expected the underfunded offer to fail");
+    } catch (error) {
+      const anchorError = assertAnchorError(error, "InsufficientRent");
+      assert(
+        anchorError.logs.some((log) =>
+          log.includes(`needs ${statsRent + offerRent + vaultRent} from it`)
+        ),
+        anchorError.logs.join("\n")
+      );
+    }
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert.isNull(await connection.getAccountInfo(deriveMakerStats(maker.publicKey)));
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a ladder's funding check counts every rung, the maker's stats, and lamports already at a rung.
+  it("Checks a ladder's rent across its rungs and stats, net of lamports already at a rung", async () => {
+    const reference = await createOffer(getRandomBigNumber());
+    const offerRent = (await connection.getAccountInfo(reference.offer)).lamports;
+    const vaultRent = (await connection.getAccountInfo(reference.vault)).lamports;
+    const statsRent = (await connection.getAccountInfo(deriveMakerStats(alice.publicKey))).lamports;
+
+    // The new maker can pay for their stats and one rung, but the ladder has two.
+    const { maker, makerTokenAccountA } = await createTokenAMaker(statsRent + offerRent + vaultRent, tokenAOfferedAmount);
+    const ids = [getRandomBigNumber(), getRandomBigNumber()];
+    const rungs = ids.map((id) => deriveOfferAccounts(id, maker.publicKey));
+    const ladder = () =>
+      program.methods
+        .makeOffersLadder(
+          ids,
+          ids.map(() => tokenAOfferedAmount.divn(2)),
+          ids.map(() => tokenBWantedAmount),
+          NO_EXPIRY
+        )
+        .accounts({ ...accounts, maker: maker.publicKey, makerTokenAccountA, makerStats: deriveMakerStats(maker.publicKey) })
+        .remainingAccounts(
+          rungs.flatMap(({ offer, vault }) =>
+            [offer, vault].map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }))
+          )
+        )
+        .signers([maker]);
+    const expectShortfall = async (pending: Promise<string>, needed: number) => {
+      try {
+        await pending;
+        assert.fail("Expected the underfunded ladder to fail");
+      } catch (error) {
+        const anchorError = assertAnchorError(error, "InsufficientRent");
+        assert(anchorError.logs.some((log) => log.includes(`needs ${needed} from it`)), anchorError.logs.join("\n"));
+      }
+    };
+
+    // The ladder's own check prices both rungs, once Anchor has created the maker's stats.
+    await expectShortfall(ladder().rpc(), 2 * (offerRent + vaultRent));
+
+    // Placed ahead of the ladder, the funding check also prices the stats.
+    const check = await checkOfferFunding(maker.publicKey, maker.publicKey, ids, tokenAOfferedAmount);
+    await expectShortfall(ladder().preInstructions([check]).rpc(), statsRent + 2 * (offerRent + vaultRent));
+
+    // Once someone has sent the second rung's rent to its addresses, the maker can afford the rest.
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        SystemProgram.transfer({ fromPubkey: payer.publicKey, toPubkey: rungs[1].offer, lamports: offerRent }),
+        SystemProgram.transfer({ fromPubkey: payer.publicKey, toPubkey: rungs[1].vault, lamports: vaultRent })
+      ),
+      [payer]
+    );
+    await confirmTransaction(connection, await ladder().preInstructions([check]).rpc());
+    for (const { vault } of rungs) {
+      assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount.divn(2)));
+    }
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a ladder announces itself with one summary event rather than an event per rung.
+  it("Emits a single LadderCreated event for a three-rung ladder", async () => {
+    const ids = [getRandomBigNumber(), getRandomBigNumber(), getRandomBigNumber()];
+    const offeredAmounts = ids.map(() => tokenAOfferedAmount.divn(3));
+    const wantedAmounts = ids.map(() => tokenBWantedAmount);
+    const rungAccounts = ids
+      .map((id) => deriveOfferAccounts(id))
+      .flatMap(({ offer, vault }) =>
+        [offer, vault].map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }))
+      );
+
+    const signature = await program.methods
+      .makeOffersLadder(ids, offeredAmounts, wantedAmounts, NO_EXPIRY)
+      .accounts({ ...accounts })
+      .remainingAccounts(rungAccounts)
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // Exactly one summary event, and no per-rung OfferMade events.
+    const events = await getEvents(signature);
+    const laddersCreated = events.filter((event) => event.name === "ladderCreated");
+    assert.equal(laddersCreated.length, 1);
+    assert.isEmpty(events.filter((event) => event.name === "offerMade"));
+
+    const [ladderCreated] = laddersCreated;
+    assert(ladderCreated.data.maker.equals(alice.publicKey));
+    assert(ladderCreated.data.tokenMintA.equals(accounts.tokenMintA));
+    assert(ladderCreated.data.tokenMintB.equals(accounts.tokenMintB));
+    assert.equal(ladderCreated.data.rungCount, 3);
+    assert.deepEqual(
+      ladderCreated.data.ids.map((id: BN) => id.toString()),
+      ids.map((id) => id.toString())
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that an offer the admin force-closes is dropped from its pair's index, like a cancel.
+  it("Removes an offer the admin force-closes from its pair index", async () => {
+    const closeMintB = await createMint(connection, payer, payer.publicKey, null, 6, undefined, undefined, TOKEN_PROGRAM);
+    const pairIndex = PublicKey.findProgramAddressSync(
+      [Buffer.from("pair"), accounts.tokenMintA.toBuffer(), closeMintB.toBuffer()],
+      program.programId
+    )[0];
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .createPairIndex()
+        .accounts({ payer: payer.publicKey, tokenMintA: accounts.tokenMintA, tokenMintB: closeMintB })
+        .rpc()
+    );
+
+    const offerId = getRandomBigNumber();
+    const { offer, vault } = deriveOfferAccounts(offerId);
+    const makeSignature = await program.methods
+      .makeOffer(
+        offerId,
+        tokenAOfferedAmount,
+        tokenBWantedAmount,
+        NO_EXPIRY,
+        null,
+        [],
+        NO_MIN_FILL,
+        null,
+        "",
+        null,
+        PublicKey.default,
+        0,
+        PublicKey.default,
+        0,
+        0,
+        false,
+        NO_REBATE_RESERVE
+      )
+      .accounts({ ...accounts, tokenMintB: closeMintB, pairIndex, offer, vault })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, makeSignature);
+    assert.deepEqual(
+      (await program.account.pairIndex.fetch(pairIndex)).offers.map((listed) => listed.toBase58()),
+      [offer.toBase58()]
+    );
+
+    const closeSignature = await program.methods
+      .adminCloseOffer()
+      .accounts({ ...accounts, admin: payer.publicKey, pairIndex, offer, vault })
+      .signers([payer])
+      .rpc();
+    await confirmTransaction(connection, closeSignature);
+
+    // The offer is gone, and so is its entry in the pair's index.
+    assert.isNull(await connection.getAccountInfo(offer));
+    assert.isEmpty((await program.account.pairIndex.fetch(pairIndex)).offers);
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
+
+  // Test that a ladder still opens a rung whose address someone sent lamports to first, and that
+  // every rung is added to the pair's index.
+  it("Posts a ladder over a pre-funded rung address, indexing every rung", async () => {
+    const ladderMintB = await createMint(connection, payer, payer.publicKey, null, 6, undefined, undefined, TOKEN_PROGRAM);
+    const pairIndex = PublicKey.findProgramAddressSync(
+      [Buffer.from("pair"), accounts.tokenMintA.toBuffer(), ladderMintB.toBuffer()],
+      program.programId
+    )[0];
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .createPairIndex()
+        .accounts({ payer: payer.publicKey, tokenMintA: accounts.tokenMintA, tokenMintB: ladderMintB })
+        .rpc()
+    );
+
+    const ids = [getRandomBigNumber(), getRandomBigNumber()];
+    const rungs = ids.map((id) => deriveOfferAccounts(id));
+
+    // Someone sends a few lamports to the first rung's address before the ladder is posted.
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        SystemProgram.transfer({ fromPubkey: payer.publicKey, toPubkey: rungs[0].offer, lamports: 1_000_000 })
+      ),
+      [payer]
+    );
+
+    const signature = await program.methods
+      .makeOffersLadder(
+        ids,
+        ids.map(() => tokenAOfferedAmount.divn(2)),
+        ids.map(() => tokenBWantedAmount),
+        NO_EXPIRY
+      )
+      .accounts({ ...accounts, tokenMintB: ladderMintB, pairIndex })
+      .remainingAccounts(
+        rungs.flatMap(({ offer, vault }) =>
+          [offer, vault].map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }))
+        )
+      )
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, signature);
+
+    // Both rungs are live offers, and both are listed in the pair's index.
+    const offerSize = program.account.offer.size;
+    for (const { offer, vault } of rungs) {
+      const offerInfo = await connection.getAccountInfo(offer);
+      assert(offerInfo.owner.equals(program.programId));
+      assert.equal(offerInfo.data.length, offerSize);
+      assert((await getTokenBalance(vault)).eq(tokenAOfferedAmount.divn(2)));
+    }
+    assert.deepEqual(
+      (await program.account.pairIndex.fetch(pairIndex)).offers.map((listed) => listed.toBase58()),
+      rungs.map(({ offer }) => offer.toBase58())
+    );
+  }).slow(ANCHOR_SLOW_TEST_THRESHOLD);
 });